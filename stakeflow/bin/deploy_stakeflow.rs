@@ -1,42 +1,19 @@
 //! StakeFlow Testnet Deployment Script
 //! 
-//! Deploys StCSPRToken, StakeFlowVaultV3, and StakeFlowGovernance
+//! Deploys StakeFlowVaultV3 with its StCSPRToken, and StakeFlowGovernance
 //! with proper initialization to Casper testnet.
 
-use stakeflow::stcspr_token::StCSPRToken;
+use stakeflow::stcspr_token::{StCSPRToken, StCSPRTokenInitArgs};
 use stakeflow::stakeflow_vault_v3::StakeFlowVaultV3;
-use stakeflow::governance::StakeFlowGovernance;
-use odra::host::{HostEnv, NoArgs};
+use stakeflow::governance::{StakeFlowGovernance, StakeFlowGovernanceInitArgs};
+use odra::host::{HostEnv, HostRef, InstallConfig};
 use odra::casper_types::U512;
 use odra_cli::{
     deploy::DeployScript,
     ContractProvider, DeployedContractsContainer, DeployerExt, OdraCli,
 };
 
-/// Deploy StCSPR Token
-pub struct TokenDeployScript;
-
-impl DeployScript for TokenDeployScript {
-    fn deploy(
-        &self,
-        env: &HostEnv,
-        container: &mut DeployedContractsContainer
-    ) -> Result<(), odra_cli::deploy::Error> {
-        println!("📦 Deploying StCSPR Token...");
-        
-        let _token = StCSPRToken::load_or_deploy(
-            env,
-            NoArgs,
-            container,
-            400_000_000_000 // 400 CSPR gas limit
-        )?;
-
-        println!("✅ StCSPR Token deployed successfully!");
-        Ok(())
-    }
-}
-
-/// Deploy StakeFlow Vault V3
+/// Deploy StakeFlow Vault V3 and the stCSPR token it mints
 pub struct VaultDeployScript;
 
 impl DeployScript for VaultDeployScript {
@@ -51,14 +28,45 @@ impl DeployScript for VaultDeployScript {
         let treasury = env.caller();
         let unbonding_days = 7u64; // 7-day unbonding period
         
-        let _vault = StakeFlowVaultV3::load_or_deploy(
+        // Installed as upgradable so later fixes keep the address and balances
+        let mut vault = StakeFlowVaultV3::load_or_deploy_with_cfg(
             env,
+            None,
             (treasury, unbonding_days),
+            InstallConfig::upgradable::<StakeFlowVaultV3>(),
             container,
             400_000_000_000 // 400 CSPR gas limit
         )?;
 
         println!("✅ StakeFlow Vault V3 deployed successfully!");
+
+        // The token is minted by the vault, so it needs the vault's address
+        println!("📦 Deploying StCSPR Token...");
+        let mut token = StCSPRToken::load_or_deploy(
+            env,
+            StCSPRTokenInitArgs { vault: vault.address() },
+            container,
+            400_000_000_000 // 400 CSPR gas limit
+        )?;
+
+        if vault.get_token().is_none() {
+            env.set_gas(50_000_000_000); // 50 CSPR gas limit
+            vault.set_token(token.address());
+        }
+
+        // Transfers report lowered balances to the vault, which caps referred stake by them
+        if token.get_transfer_hook().is_none() {
+            env.set_gas(50_000_000_000); // 50 CSPR gas limit
+            token.set_transfer_hook(Some(vault.address()));
+        }
+
+        // Bound how far oracle reports may move the rate: 20% APR up, 5% down, one report per era
+        if vault.get_rate_limits().is_none() {
+            env.set_gas(50_000_000_000); // 50 CSPR gas limit
+            vault.set_rate_limits(2000, 500, vault.get_era_config().era_duration);
+        }
+
+        println!("✅ StCSPR Token deployed and linked to the vault!");
         Ok(())
     }
 }
@@ -73,10 +81,12 @@ impl DeployScript for GovernanceDeployScript {
         container: &mut DeployedContractsContainer
     ) -> Result<(), odra_cli::deploy::Error> {
         println!("📦 Deploying StakeFlow Governance...");
-        
+
+        // Voting power and quorum supply are read from the token's checkpoints
+        let token = container.contract_ref::<StCSPRToken>(env)?;
         let _governance = StakeFlowGovernance::load_or_deploy(
             env,
-            NoArgs,
+            StakeFlowGovernanceInitArgs { vault_address: token.address() },
             container,
             400_000_000_000 // 400 CSPR gas limit
         )?;
@@ -92,10 +102,9 @@ pub fn main() {
     
     OdraCli::new()
         .about("Deploy StakeFlow contracts to Casper testnet")
-        .deploy(TokenDeployScript)
-        .contract::<StCSPRToken>()
         .deploy(VaultDeployScript)
         .contract::<StakeFlowVaultV3>()
+        .contract::<StCSPRToken>()
         .deploy(GovernanceDeployScript)
         .contract::<StakeFlowGovernance>()
         .build()
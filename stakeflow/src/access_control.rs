@@ -0,0 +1,232 @@
+use odra::prelude::*;
+
+/// StakeFlow Access Control
+///
+/// Role-based permissions shared by every StakeFlow contract:
+/// - Distinct roles so keepers can operate the protocol without admin rights
+/// - Grant/revoke by the role's admin role, renounce by the holder
+/// - Configurable role-admin hierarchy (every role is administered by `Admin` by default)
+/// - A single owner holding `Admin`, moved with a two-step propose/accept transfer
+///
+/// Used as a `SubModule`; the parent seeds the owner and initial holders in its `init`.
+#[odra::module]
+pub struct AccessControl {
+    /// Role membership: (role, account) -> has role
+    roles: Mapping<(Role, Address), bool>,
+    /// Role administering each role: role -> admin role
+    role_admins: Mapping<Role, Role>,
+    /// Current owner
+    owner: Var<Address>,
+    /// Proposed owner waiting to accept
+    pending_owner: Var<Option<Address>>,
+}
+
+/// Protocol roles
+#[odra::odra_type]
+pub enum Role {
+    /// Manages roles and sensitive parameters (fees, treasury, validator set)
+    Admin,
+    /// Keeper running compounding, delegation and rebalancing
+    Operator,
+    /// Pauses contracts and cancels pending actions in an emergency
+    Guardian,
+    /// Reports off-chain data such as rewards
+    Oracle,
+    /// Registers cross-chain deposits
+    Relayer,
+    /// Mints and burns stCSPR (held by the vault)
+    Minter,
+}
+
+#[odra::module]
+impl AccessControl {
+    /// Grant a role; caller must hold the role's admin role
+    pub fn grant_role(&mut self, role: Role, account: Address) {
+        self.check_role(self.get_role_admin(role.clone()), self.env().caller());
+        self.setup_role(role, account);
+    }
+
+    /// Revoke a role; caller must hold the role's admin role
+    pub fn revoke_role(&mut self, role: Role, account: Address) {
+        self.check_role(self.get_role_admin(role.clone()), self.env().caller());
+        self.remove_role(role, account);
+    }
+
+    /// Give up a role held by the caller
+    pub fn renounce_role(&mut self, role: Role, account: Address) {
+        if account != self.env().caller() {
+            self.env().revert(AccessControlError::CanOnlyRenounceOwnRole);
+        }
+        self.remove_role(role, account);
+    }
+
+    /// Change the role administering `role`; caller must hold its current admin role
+    pub fn set_role_admin(&mut self, role: Role, admin_role: Role) {
+        let previous_admin_role = self.get_role_admin(role.clone());
+        self.check_role(previous_admin_role.clone(), self.env().caller());
+        self.role_admins.set(&role, admin_role.clone());
+
+        self.env().emit_event(RoleAdminChanged {
+            role,
+            previous_admin_role,
+            new_admin_role: admin_role,
+            timestamp: self.env().get_block_time(),
+        });
+    }
+
+    // ===== OWNERSHIP =====
+
+    /// Propose a new owner (owner only); takes effect once accepted
+    /// Proposing again replaces the pending owner
+    pub fn transfer_ownership(&mut self, new_owner: Address) {
+        let owner = self.get_owner();
+        if self.env().caller() != owner {
+            self.env().revert(AccessControlError::OnlyOwner);
+        }
+        self.pending_owner.set(Some(new_owner));
+
+        self.env().emit_event(OwnershipTransferStarted {
+            previous_owner: owner,
+            new_owner,
+            timestamp: self.env().get_block_time(),
+        });
+    }
+
+    /// Accept a pending ownership transfer (pending owner only)
+    /// The admin role moves from the previous owner to the new one
+    pub fn accept_ownership(&mut self) {
+        let caller = self.env().caller();
+        if self.get_pending_owner() != Some(caller) {
+            self.env().revert(AccessControlError::NotPendingOwner);
+        }
+
+        let previous_owner = self.get_owner();
+        self.pending_owner.set(None);
+        self.owner.set(caller);
+        self.remove_role(Role::Admin, previous_owner);
+        self.setup_role(Role::Admin, caller);
+
+        self.env().emit_event(OwnershipTransferred {
+            previous_owner,
+            new_owner: caller,
+            timestamp: self.env().get_block_time(),
+        });
+    }
+
+    // ===== VIEW FUNCTIONS =====
+
+    /// Get current owner
+    pub fn get_owner(&self) -> Address {
+        self.owner.get().unwrap_or_revert_with(&self.env(), AccessControlError::OwnerNotSet)
+    }
+
+    /// Get proposed owner, if a transfer is pending
+    pub fn get_pending_owner(&self) -> Option<Address> {
+        self.pending_owner.get().flatten()
+    }
+
+    /// Check if an account holds a role
+    pub fn has_role(&self, role: Role, account: Address) -> bool {
+        self.roles.get(&(role, account)).unwrap_or(false)
+    }
+
+    /// Get the role administering `role`
+    pub fn get_role_admin(&self, role: Role) -> Role {
+        self.role_admins.get(&role).unwrap_or(Role::Admin)
+    }
+
+    /// Assert an account holds a role
+    pub fn check_role(&self, role: Role, account: Address) {
+        if !self.has_role(role, account) {
+            self.env().revert(AccessControlError::MissingRole);
+        }
+    }
+
+    // ===== INTERNAL =====
+
+    /// Set the initial owner and grant it `Admin`; used by parents during `init`
+    pub(crate) fn init_owner(&mut self, owner: Address) {
+        self.owner.set(owner);
+        self.setup_role(Role::Admin, owner);
+    }
+
+    /// Grant a role without checking the caller; used by parents during `init`
+    pub(crate) fn setup_role(&mut self, role: Role, account: Address) {
+        if self.has_role(role.clone(), account) {
+            return;
+        }
+        self.roles.set(&(role.clone(), account), true);
+
+        self.env().emit_event(RoleGranted {
+            role,
+            account,
+            sender: self.env().caller(),
+            timestamp: self.env().get_block_time(),
+        });
+    }
+
+    fn remove_role(&mut self, role: Role, account: Address) {
+        if !self.has_role(role.clone(), account) {
+            return;
+        }
+        self.roles.set(&(role.clone(), account), false);
+
+        self.env().emit_event(RoleRevoked {
+            role,
+            account,
+            sender: self.env().caller(),
+            timestamp: self.env().get_block_time(),
+        });
+    }
+}
+
+// ===== ERRORS =====
+
+#[odra::odra_error]
+pub enum AccessControlError {
+    MissingRole = 100,
+    OnlyOwner = 101,
+    NotPendingOwner = 102,
+    CanOnlyRenounceOwnRole = 103,
+    OwnerNotSet = 104,
+}
+
+// ===== EVENTS =====
+
+#[odra::event]
+pub struct RoleGranted {
+    pub role: Role,
+    pub account: Address,
+    pub sender: Address,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct RoleRevoked {
+    pub role: Role,
+    pub account: Address,
+    pub sender: Address,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct RoleAdminChanged {
+    pub role: Role,
+    pub previous_admin_role: Role,
+    pub new_admin_role: Role,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct OwnershipTransferStarted {
+    pub previous_owner: Address,
+    pub new_owner: Address,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct OwnershipTransferred {
+    pub previous_owner: Address,
+    pub new_owner: Address,
+    pub timestamp: u64,
+}
@@ -0,0 +1,352 @@
+use odra::prelude::*;
+use odra::casper_types::{PublicKey, U512};
+
+/// Default Casper era length: 2 hours in milliseconds (block time units)
+pub const DEFAULT_ERA_DURATION: u64 = 2 * 60 * 60 * 1000;
+
+const DAY_MILLIS: u64 = 24 * 60 * 60 * 1000;
+
+/// StakeFlow Delegation Manager
+///
+/// Bridges a vault to the Casper system auction:
+/// - Registry of validators the vault may delegate to
+/// - Delegation/undelegation through Odra's auction host functions
+/// - Principal booked per validator (auction rewards are read back live)
+/// - CSPR in transit back from the auction while it unbonds
+/// - Era schedule predicting when undelegated CSPR returns
+///
+/// Used as a `SubModule` by `StakeFlowVaultV3`; the parent performs access control.
+#[odra::module]
+pub struct DelegationManager {
+    /// Validators the vault may delegate to
+    validators: List<PublicKey>,
+    /// Registered validators: public key -> registered
+    is_validator: Mapping<PublicKey, bool>,
+    /// Principal delegated per validator (excludes auction rewards)
+    validator_principal: Mapping<PublicKey, U512>,
+    /// Total principal delegated across all validators
+    total_delegated: Var<U512>,
+    /// Principal undelegated but not yet returned by the auction
+    total_unbonding: Var<U512>,
+    /// Active era schedule
+    era_config: Var<EraConfig>,
+    /// Principal expected back from the auction per era: era -> amount
+    unbonding_by_era: Mapping<u64, U512>,
+}
+
+/// Era schedule of the auction as modelled by the vault
+///
+/// Eras are counted from `start_era`, which began at block time `start_time`.
+/// Re-anchoring on every change keeps era numbers continuous.
+#[odra::odra_type]
+pub struct EraConfig {
+    /// Era length in milliseconds
+    pub era_duration: u64,
+    /// Number of eras an undelegation spends unbonding
+    pub unbonding_eras: u64,
+    /// First era governed by this config
+    pub start_era: u64,
+    /// Block time at which `start_era` began
+    pub start_time: u64,
+}
+
+#[odra::module]
+impl DelegationManager {
+    /// Set up the era schedule, starting era 0 at the current block time
+    pub fn init_eras(&mut self, era_duration: u64, unbonding_eras: u64) {
+        if era_duration == 0 || unbonding_eras == 0 {
+            self.env().revert(DelegationError::InvalidEraConfig);
+        }
+
+        self.era_config.set(EraConfig {
+            era_duration,
+            unbonding_eras,
+            start_era: 0,
+            start_time: self.env().get_block_time(),
+        });
+    }
+
+    /// Change the era schedule from the current era onwards
+    /// Unlock eras already handed out are not affected
+    pub fn set_era_config(&mut self, era_duration: u64, unbonding_eras: u64) {
+        if era_duration == 0 || unbonding_eras == 0 {
+            self.env().revert(DelegationError::InvalidEraConfig);
+        }
+
+        let current_era = self.current_era();
+        let start_time = self.era_start_time(current_era);
+        self.era_config.set(EraConfig {
+            era_duration,
+            unbonding_eras,
+            start_era: current_era,
+            start_time,
+        });
+
+        self.env().emit_event(UnbondingConfigUpdated {
+            era_duration,
+            unbonding_eras,
+            effective_era: current_era,
+            timestamp: self.env().get_block_time(),
+        });
+    }
+
+    /// Register a validator the vault may delegate to
+    pub fn add_validator(&mut self, validator: PublicKey) {
+        if self.is_validator(validator.clone()) {
+            self.env().revert(DelegationError::ValidatorAlreadyRegistered);
+        }
+        if self.env().get_validator_info(validator.clone()).is_none() {
+            self.env().revert(DelegationError::ValidatorNotInAuction);
+        }
+
+        self.is_validator.set(&validator, true);
+        self.validators.push(validator.clone());
+
+        self.env().emit_event(ValidatorRegistered {
+            validator,
+            timestamp: self.env().get_block_time(),
+        });
+    }
+
+    /// Delegate CSPR held by the contract to a registered validator
+    pub fn delegate(&mut self, validator: PublicKey, amount: U512) {
+        if amount.is_zero() {
+            self.env().revert(DelegationError::ZeroAmount);
+        }
+        if !self.is_validator(validator.clone()) {
+            self.env().revert(DelegationError::ValidatorNotRegistered);
+        }
+
+        // The auction rejects delegations that end up below the validator's minimum
+        let info = self.env().get_validator_info(validator.clone())
+            .unwrap_or_revert_with(&self.env(), DelegationError::ValidatorNotInAuction);
+        let current = self.env().delegated_amount(validator.clone());
+        if current + amount < U512::from(info.minimum_delegation_amount) {
+            self.env().revert(DelegationError::BelowAuctionMinimum);
+        }
+
+        self.env().delegate(validator.clone(), amount);
+
+        let principal = self.validator_principal.get(&validator).unwrap_or(U512::zero());
+        self.validator_principal.set(&validator, principal + amount);
+        self.total_delegated.set(self.total_delegated.get_or_default() + amount);
+
+        self.env().emit_event(Delegated {
+            validator: validator.clone(),
+            amount,
+            delegated_amount: self.env().delegated_amount(validator),
+            timestamp: self.env().get_block_time(),
+        });
+    }
+
+    /// Undelegate CSPR from a validator
+    /// Returns the principal moved into unbonding
+    pub fn undelegate(&mut self, validator: PublicKey, amount: U512) -> U512 {
+        if amount.is_zero() {
+            self.env().revert(DelegationError::ZeroAmount);
+        }
+        if !self.is_validator(validator.clone()) {
+            self.env().revert(DelegationError::ValidatorNotRegistered);
+        }
+
+        let info = self.env().get_validator_info(validator.clone())
+            .unwrap_or_revert_with(&self.env(), DelegationError::ValidatorNotInAuction);
+        let delegated = self.env().delegated_amount(validator.clone());
+        if amount > delegated {
+            self.env().revert(DelegationError::AmountExceedsDelegation);
+        }
+
+        // The auction force-undelegates any remainder below the minimum, so refuse to leave dust
+        let remaining = delegated - amount;
+        if !remaining.is_zero() && remaining < U512::from(info.minimum_delegation_amount) {
+            self.env().revert(DelegationError::BelowAuctionMinimum);
+        }
+
+        self.env().undelegate(validator.clone(), amount);
+
+        // Rewards on top of the principal are not booked; they surface as surplus balance
+        let principal = self.validator_principal.get(&validator).unwrap_or(U512::zero());
+        let principal_released = if amount < principal { amount } else { principal };
+        self.validator_principal.set(&validator, principal - principal_released);
+        self.total_delegated.set(self.total_delegated.get_or_default() - principal_released);
+        self.total_unbonding.set(self.total_unbonding.get_or_default() + principal_released);
+
+        let unlock_era = self.unlock_era();
+        let due = self.unbonding_by_era.get(&unlock_era).unwrap_or(U512::zero());
+        self.unbonding_by_era.set(&unlock_era, due + principal_released);
+
+        self.env().emit_event(Undelegated {
+            validator,
+            amount,
+            principal_released,
+            unlock_era,
+            timestamp: self.env().get_block_time(),
+        });
+
+        principal_released
+    }
+
+    /// Book each validator's principal at what the auction holds for it, rewards and slashing included
+    /// Called once a report has moved the rewards into pooled CSPR, so they count as delegated, not idle
+    pub fn book_auction_amounts(&mut self) {
+        let mut total = U512::zero();
+        for validator in self.validators() {
+            let amount = self.env().delegated_amount(validator.clone());
+            self.validator_principal.set(&validator, amount);
+            total += amount;
+        }
+        self.total_delegated.set(total);
+    }
+
+    /// Reconcile principal returned by the auction once unbonding completes
+    ///
+    /// `booked_balance` is the CSPR the parent expects to hold. Any balance above it
+    /// (up to the outstanding unbonding principal) has come back from the auction.
+    /// Returns the amount released from unbonding.
+    pub fn settle_unbonded(&mut self, booked_balance: U512) -> U512 {
+        let unbonding = self.total_unbonding.get_or_default();
+        let balance = self.env().self_balance();
+
+        if unbonding.is_zero() || balance <= booked_balance {
+            return U512::zero();
+        }
+
+        let returned = balance - booked_balance;
+        let released = if returned < unbonding { returned } else { unbonding };
+        self.total_unbonding.set(unbonding - released);
+
+        self.env().emit_event(UnbondingSettled {
+            amount: released,
+            remaining_unbonding: unbonding - released,
+            timestamp: self.env().get_block_time(),
+        });
+
+        released
+    }
+
+    // ===== VIEW FUNCTIONS =====
+
+    /// Check if a validator is registered
+    pub fn is_validator(&self, validator: PublicKey) -> bool {
+        self.is_validator.get(&validator).unwrap_or(false)
+    }
+
+    /// Get registered validators
+    pub fn validators(&self) -> Vec<PublicKey> {
+        self.validators.iter().collect()
+    }
+
+    /// Get amount currently delegated to a validator, as reported by the auction
+    pub fn delegated_amount(&self, validator: PublicKey) -> U512 {
+        self.env().delegated_amount(validator)
+    }
+
+    /// Get CSPR delegated across all validators, as reported by the auction
+    pub fn total_delegated_amount(&self) -> U512 {
+        self.validators().into_iter().map(|validator| self.env().delegated_amount(validator)).sum()
+    }
+
+    /// Get principal booked for a validator
+    pub fn validator_principal(&self, validator: PublicKey) -> U512 {
+        self.validator_principal.get(&validator).unwrap_or(U512::zero())
+    }
+
+    /// Get total principal delegated
+    pub fn total_delegated(&self) -> U512 {
+        self.total_delegated.get_or_default()
+    }
+
+    /// Get principal still unbonding
+    pub fn total_unbonding(&self) -> U512 {
+        self.total_unbonding.get_or_default()
+    }
+
+    /// Get principal scheduled to return from the auction in an era
+    pub fn unbonding_for_era(&self, era: u64) -> U512 {
+        self.unbonding_by_era.get(&era).unwrap_or(U512::zero())
+    }
+
+    /// Get the active era schedule
+    pub fn era_config(&self) -> EraConfig {
+        self.era_config.get().unwrap_or_revert_with(&self.env(), DelegationError::EraConfigNotSet)
+    }
+
+    /// Get the era the current block time falls in
+    pub fn current_era(&self) -> u64 {
+        let config = self.era_config();
+        let elapsed = self.env().get_block_time().saturating_sub(config.start_time);
+        config.start_era + elapsed / config.era_duration
+    }
+
+    /// Get the block time at which an era starts
+    pub fn era_start_time(&self, era: u64) -> u64 {
+        let config = self.era_config();
+        let eras_since_start = era.saturating_sub(config.start_era);
+        config.start_time + eras_since_start * config.era_duration
+    }
+
+    /// Get the era in which CSPR undelegated now is back in the vault
+    /// Undelegations take effect at the next era boundary, then unbond for `unbonding_eras`
+    pub fn unlock_era(&self) -> u64 {
+        self.current_era() + 1 + self.era_config().unbonding_eras
+    }
+}
+
+/// Convert an unbonding period in days into whole eras, rounding up
+pub fn unbonding_eras_for_days(days: u64, era_duration: u64) -> u64 {
+    (days * DAY_MILLIS).div_ceil(era_duration)
+}
+
+// ===== ERRORS =====
+
+#[odra::odra_error]
+pub enum DelegationError {
+    InvalidEraConfig = 400,
+    ValidatorAlreadyRegistered = 401,
+    ValidatorNotInAuction = 402,
+    ValidatorNotRegistered = 403,
+    ZeroAmount = 404,
+    BelowAuctionMinimum = 405,
+    AmountExceedsDelegation = 406,
+    EraConfigNotSet = 407,
+}
+
+// ===== EVENTS =====
+
+#[odra::event]
+pub struct ValidatorRegistered {
+    pub validator: PublicKey,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct Delegated {
+    pub validator: PublicKey,
+    pub amount: U512,
+    pub delegated_amount: U512,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct Undelegated {
+    pub validator: PublicKey,
+    pub amount: U512,
+    pub principal_released: U512,
+    pub unlock_era: u64,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct UnbondingSettled {
+    pub amount: U512,
+    pub remaining_unbonding: U512,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct UnbondingConfigUpdated {
+    pub era_duration: u64,
+    pub unbonding_eras: u64,
+    pub effective_era: u64,
+    pub timestamp: u64,
+}
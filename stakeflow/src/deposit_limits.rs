@@ -0,0 +1,220 @@
+use odra::prelude::*;
+use odra::casper_types::bytesrepr::{Bytes, ToBytes};
+use odra::casper_types::{PublicKey, U512};
+
+/// Smallest accepted deposit until an admin sets one: 10 CSPR in motes
+pub const DEFAULT_MIN_DEPOSIT: u64 = 10_000_000_000;
+
+/// Prefix of the message an attestation signer signs for an account
+const ATTESTATION_DOMAIN: &[u8] = b"stakeflow:allowlist";
+
+/// StakeFlow Deposit Guard
+///
+/// Limits for a guarded launch, shared by the vaults:
+/// - A configurable minimum deposit
+/// - A global TVL cap and a per-account cap on holdings
+/// - An optional allowlist mode; accounts join through an admin or by presenting
+///   an attestation signed by the configured attestation signer
+/// - Attestations are signed for an account's current epoch; removing the account
+///   from the allowlist starts a new epoch, so earlier attestations cannot re-add it
+///
+/// Used as a `SubModule`; the parent checks roles and supplies TVL and holdings.
+#[odra::module]
+pub struct DepositGuard {
+    /// Smallest accepted deposit (unset = `DEFAULT_MIN_DEPOSIT`)
+    min_deposit: Var<U512>,
+    /// Most CSPR the vault may hold (None = uncapped)
+    tvl_cap: Var<Option<U512>>,
+    /// Most CSPR one account may hold (None = uncapped)
+    account_cap: Var<Option<U512>>,
+    /// Only allowlisted accounts may deposit while set
+    allowlist_enabled: Var<bool>,
+    /// Allowlisted accounts: account -> allowed
+    allowlisted: Mapping<Address, bool>,
+    /// Key whose signed attestations allowlist an account
+    attestation_signer: Var<Option<PublicKey>>,
+    /// Epoch attestations must be signed for: account -> epoch
+    attestation_epochs: Mapping<Address, u64>,
+}
+
+#[odra::module]
+impl DepositGuard {
+    /// Set the minimum deposit and the TVL and per-account caps
+    pub fn set_limits(&mut self, min_deposit: U512, tvl_cap: Option<U512>, account_cap: Option<U512>) {
+        if min_deposit.is_zero() {
+            self.env().revert(DepositLimitError::InvalidMinimumDeposit);
+        }
+        self.min_deposit.set(min_deposit);
+        self.tvl_cap.set(tvl_cap);
+        self.account_cap.set(account_cap);
+
+        self.env().emit_event(DepositLimitsUpdated {
+            min_deposit,
+            tvl_cap,
+            account_cap,
+            timestamp: self.env().get_block_time(),
+        });
+    }
+
+    /// Turn allowlist mode on or off
+    pub fn set_allowlist_enabled(&mut self, enabled: bool) {
+        self.allowlist_enabled.set(enabled);
+
+        self.env().emit_event(AllowlistModeUpdated {
+            enabled,
+            timestamp: self.env().get_block_time(),
+        });
+    }
+
+    /// Add or remove an account from the allowlist
+    /// Removing it invalidates every attestation signed for the account so far
+    pub fn set_allowlisted(&mut self, account: Address, allowed: bool) {
+        self.allowlisted.set(&account, allowed);
+        if !allowed {
+            self.attestation_epochs.set(&account, self.attestation_epoch(account) + 1);
+        }
+
+        self.env().emit_event(AllowlistUpdated {
+            account,
+            allowed,
+            timestamp: self.env().get_block_time(),
+        });
+    }
+
+    /// Set or clear the key whose attestations allowlist accounts
+    pub fn set_attestation_signer(&mut self, signer: Option<PublicKey>) {
+        self.attestation_signer.set(signer.clone());
+
+        self.env().emit_event(AttestationSignerUpdated {
+            signer,
+            timestamp: self.env().get_block_time(),
+        });
+    }
+
+    /// Allowlist `account` with an attestation signed by the attestation signer
+    pub fn attest(&mut self, account: Address, signature: Bytes) {
+        let signer = self.attestation_signer.get().flatten()
+            .unwrap_or_revert_with(&self.env(), DepositLimitError::AttestationSignerNotSet);
+        let message = self.attestation_message(account);
+        if !self.env().verify_signature(&message, &signature, &signer) {
+            self.env().revert(DepositLimitError::InvalidAttestation);
+        }
+        self.set_allowlisted(account, true);
+    }
+
+    /// Revert unless `account` may add `amount`, given the vault's TVL and the account's holdings
+    pub fn check(&self, account: Address, amount: U512, tvl: U512, held: U512) {
+        if self.allowlist_enabled() && !self.is_allowlisted(account) {
+            self.env().revert(DepositLimitError::NotAllowlisted);
+        }
+        self.check_caps(amount, tvl, held);
+    }
+
+    /// Revert if adding `amount` breaks the TVL or per-account cap
+    /// Mint paths that skip the minimum and allowlist (bridged deposits) still call this
+    pub fn check_caps(&self, amount: U512, tvl: U512, held: U512) {
+        if self.tvl_cap().is_some_and(|cap| tvl + amount > cap) {
+            self.env().revert(DepositLimitError::TvlCapExceeded);
+        }
+        if self.account_cap().is_some_and(|cap| held + amount > cap) {
+            self.env().revert(DepositLimitError::AccountCapExceeded);
+        }
+    }
+
+    // ===== VIEW FUNCTIONS =====
+
+    /// Get the smallest accepted deposit
+    pub fn min_deposit(&self) -> U512 {
+        self.min_deposit.get().unwrap_or(U512::from(DEFAULT_MIN_DEPOSIT))
+    }
+
+    /// Get the TVL cap, if any
+    pub fn tvl_cap(&self) -> Option<U512> {
+        self.tvl_cap.get().flatten()
+    }
+
+    /// Get the per-account cap, if any
+    pub fn account_cap(&self) -> Option<U512> {
+        self.account_cap.get().flatten()
+    }
+
+    /// Check if allowlist mode is on
+    pub fn allowlist_enabled(&self) -> bool {
+        self.allowlist_enabled.get_or_default()
+    }
+
+    /// Check if an account is allowlisted
+    pub fn is_allowlisted(&self, account: Address) -> bool {
+        self.allowlisted.get(&account).unwrap_or(false)
+    }
+
+    /// Get the attestation signer, if any
+    pub fn attestation_signer(&self) -> Option<PublicKey> {
+        self.attestation_signer.get().flatten()
+    }
+
+    /// Get the epoch attestations for `account` must be signed for
+    pub fn attestation_epoch(&self, account: Address) -> u64 {
+        self.attestation_epochs.get(&account).unwrap_or_default()
+    }
+
+    /// Message the attestation signer signs to allowlist `account` on this contract in its current epoch
+    pub fn attestation_message(&self, account: Address) -> Bytes {
+        let mut message = ATTESTATION_DOMAIN.to_vec();
+        message.extend(self.env().self_address().to_bytes().unwrap_or_revert(&self.env()));
+        message.extend(account.to_bytes().unwrap_or_revert(&self.env()));
+        message.extend(self.attestation_epoch(account).to_bytes().unwrap_or_revert(&self.env()));
+        Bytes::from(message)
+    }
+
+    /// Most `account` can add before a cap or the allowlist stops it
+    pub fn remaining(&self, account: Address, tvl: U512, held: U512) -> U512 {
+        if self.allowlist_enabled() && !self.is_allowlisted(account) {
+            return U512::zero();
+        }
+        let by_tvl = self.tvl_cap().map_or(U512::MAX, |cap| cap.saturating_sub(tvl));
+        let by_account = self.account_cap().map_or(U512::MAX, |cap| cap.saturating_sub(held));
+        if by_tvl < by_account { by_tvl } else { by_account }
+    }
+}
+
+// ===== ERRORS =====
+
+#[odra::odra_error]
+pub enum DepositLimitError {
+    TvlCapExceeded = 1100,
+    AccountCapExceeded = 1101,
+    NotAllowlisted = 1102,
+    InvalidAttestation = 1103,
+    AttestationSignerNotSet = 1104,
+    InvalidMinimumDeposit = 1105,
+}
+
+// ===== EVENTS =====
+
+#[odra::event]
+pub struct DepositLimitsUpdated {
+    pub min_deposit: U512,
+    pub tvl_cap: Option<U512>,
+    pub account_cap: Option<U512>,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct AllowlistModeUpdated {
+    pub enabled: bool,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct AllowlistUpdated {
+    pub account: Address,
+    pub allowed: bool,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct AttestationSignerUpdated {
+    pub signer: Option<PublicKey>,
+    pub timestamp: u64,
+}
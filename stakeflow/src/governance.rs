@@ -1,5 +1,9 @@
 use odra::prelude::*;
+use odra::ContractRef;
 use odra::casper_types::{U512, U256};
+use odra::casper_types::bytesrepr::{Bytes, FromBytes, ToBytes};
+use crate::access_control::{AccessControl, Role};
+use crate::timelock::{self, Timelock, TimelockOperation};
 
 /// StakeFlow Governance Contract
 ///
@@ -10,13 +14,14 @@ use odra::casper_types::{U512, U256};
 /// - Emergency pause/unpause
 /// - Treasury management
 ///
-/// Voting power is proportional to stCSPR holdings (1 stCSPR = 1 vote)
+/// Voting power is delegated stCSPR (1 stCSPR = 1 vote), read on-chain from the token's
+/// checkpoints as it stood just before each proposal was created
 #[odra::module]
 pub struct StakeFlowGovernance {
-    /// Protocol owner (multi-sig or DAO in production)
-    owner: Var<Address>,
+    /// Role-based permissions (admin is a multi-sig or DAO in production)
+    access: SubModule<AccessControl>,
 
-    /// Vault contract address (to execute proposals)
+    /// V3 vault or stCSPR token address; voting power and supply are read from it
     vault_contract: Var<Address>,
 
     /// Proposals mapping: proposal_id -> Proposal
@@ -33,6 +38,27 @@ pub struct StakeFlowGovernance {
     quorum_percentage: Var<u32>,       // Minimum participation (in bps, 2000 = 20%)
     approval_threshold: Var<u32>,      // Approval threshold (in bps, 5000 = 50%)
     proposal_threshold: Var<U256>,     // Minimum stCSPR to create proposal
+
+    /// Delay queue for parameter changes
+    timelock: SubModule<Timelock>,
+}
+
+/// Admin calls that only take effect through the timelock
+#[odra::odra_type]
+pub enum GovernanceAdminCall {
+    /// Update governance parameters
+    UpdateGovernanceParams(GovernanceParamsUpdate),
+    /// Change the timelock's minimum delay (ms)
+    SetTimelockDelay(u64),
+}
+
+/// New governance parameters; `None` keeps the current value
+#[odra::odra_type]
+pub struct GovernanceParamsUpdate {
+    pub voting_period: Option<u64>,
+    pub quorum_bps: Option<u32>,
+    pub approval_bps: Option<u32>,
+    pub proposal_threshold: Option<U256>,
 }
 
 /// Proposal types
@@ -65,6 +91,8 @@ pub struct Proposal {
     pub against_votes: U256,
     pub executed: bool,
     pub cancelled: bool,
+    /// Block time voting power and total supply are read at
+    pub snapshot: u64,
 }
 
 /// Vote struct
@@ -90,9 +118,12 @@ pub enum ProposalStatus {
 #[odra::module]
 impl StakeFlowGovernance {
     /// Initialize governance
+    /// `vault_address` is the V3 vault or its stCSPR token; either serves voting power
     pub fn init(&mut self, vault_address: Address) {
         let caller = self.env().caller();
-        self.owner.set(caller);
+        self.access.init_owner(caller);
+        self.access.setup_role(Role::Guardian, caller);
+        self.timelock.init_delay(timelock::DEFAULT_MIN_DELAY);
         self.vault_contract.set(vault_address);
         self.next_proposal_id.set(0);
 
@@ -112,20 +143,20 @@ impl StakeFlowGovernance {
     // ===== PROPOSAL CREATION =====
 
     /// Create a new proposal
-    pub fn create_proposal(
-        &mut self,
-        proposal_type: ProposalType,
-        description: String,
-        voting_power: U256, // Caller's stCSPR balance from vault
-    ) -> u64 {
+    /// Voting power is snapshotted at the previous block time, so stCSPR acquired in the
+    /// proposal's block does not count
+    pub fn create_proposal(&mut self, proposal_type: ProposalType, description: String) -> u64 {
         let caller = self.env().caller();
+        let current_time = self.env().get_block_time();
+        let snapshot = current_time.saturating_sub(1);
 
         // Check proposal threshold
         let threshold = self.proposal_threshold.get_or_default();
-        assert!(voting_power >= threshold, "Insufficient stCSPR to create proposal");
+        if self.voting_power_source().get_past_votes(caller, snapshot) < threshold {
+            self.env().revert(GovernanceError::InsufficientVotingPower);
+        }
 
         let proposal_id = self.next_proposal_id.get_or_default();
-        let current_time = self.env().get_block_time();
         let voting_period = self.voting_period.get_or_default();
 
         let proposal = Proposal {
@@ -139,6 +170,7 @@ impl StakeFlowGovernance {
             against_votes: U256::zero(),
             executed: false,
             cancelled: false,
+            snapshot,
         };
 
         self.proposals.set(&proposal_id, proposal);
@@ -157,31 +189,38 @@ impl StakeFlowGovernance {
 
     // ===== VOTING =====
 
-    /// Cast a vote on a proposal
-    pub fn cast_vote(
-        &mut self,
-        proposal_id: u64,
-        support: bool,
-        voting_power: U256, // Caller's stCSPR balance from vault
-    ) {
+    /// Cast a vote on a proposal with the caller's voting power at the proposal's snapshot
+    pub fn cast_vote(&mut self, proposal_id: u64, support: bool) {
         let caller = self.env().caller();
         let current_time = self.env().get_block_time();
 
         // Get proposal
         let mut proposal = self.proposals.get(&proposal_id)
-            .expect("Proposal not found");
+            .unwrap_or_revert_with(&self.env(), GovernanceError::ProposalNotFound);
 
         // Check voting is active
-        assert!(current_time >= proposal.start_time, "Voting not started");
-        assert!(current_time < proposal.end_time, "Voting ended");
-        assert!(!proposal.cancelled, "Proposal cancelled");
-        assert!(!proposal.executed, "Proposal already executed");
+        if current_time < proposal.start_time {
+            self.env().revert(GovernanceError::VotingNotStarted);
+        }
+        if current_time >= proposal.end_time {
+            self.env().revert(GovernanceError::VotingEnded);
+        }
+        if proposal.cancelled {
+            self.env().revert(GovernanceError::ProposalCancelled);
+        }
+        if proposal.executed {
+            self.env().revert(GovernanceError::ProposalAlreadyExecuted);
+        }
 
         // Check user hasn't already voted
-        assert!(
-            self.votes.get(&(proposal_id, caller)).is_none(),
-            "Already voted"
-        );
+        if self.votes.get(&(proposal_id, caller)).is_some() {
+            self.env().revert(GovernanceError::AlreadyVoted);
+        }
+
+        let voting_power = self.voting_power_source().get_past_votes(caller, proposal.snapshot);
+        if voting_power.is_zero() {
+            self.env().revert(GovernanceError::InsufficientVotingPower);
+        }
 
         // Record vote
         let vote = Vote {
@@ -214,27 +253,39 @@ impl StakeFlowGovernance {
     // ===== PROPOSAL EXECUTION =====
 
     /// Execute a successful proposal
-    pub fn execute_proposal(&mut self, proposal_id: u64, total_stcspr_supply: U256) {
+    /// Quorum is measured against stCSPR total supply at the proposal's snapshot
+    pub fn execute_proposal(&mut self, proposal_id: u64) {
         let current_time = self.env().get_block_time();
 
         let mut proposal = self.proposals.get(&proposal_id)
-            .expect("Proposal not found");
+            .unwrap_or_revert_with(&self.env(), GovernanceError::ProposalNotFound);
 
         // Validate execution conditions
-        assert!(current_time >= proposal.end_time, "Voting period not ended");
-        assert!(!proposal.executed, "Already executed");
-        assert!(!proposal.cancelled, "Proposal cancelled");
+        if current_time < proposal.end_time {
+            self.env().revert(GovernanceError::VotingNotEnded);
+        }
+        if proposal.executed {
+            self.env().revert(GovernanceError::ProposalAlreadyExecuted);
+        }
+        if proposal.cancelled {
+            self.env().revert(GovernanceError::ProposalCancelled);
+        }
 
         // Check quorum
+        let total_stcspr_supply = self.voting_power_source().get_past_total_supply(proposal.snapshot);
         let total_votes = proposal.for_votes + proposal.against_votes;
         let quorum_bps = self.quorum_percentage.get_or_default();
         let quorum_required = (total_stcspr_supply * U256::from(quorum_bps)) / U256::from(10000u64);
-        assert!(total_votes >= quorum_required, "Quorum not reached");
+        if total_votes < quorum_required {
+            self.env().revert(GovernanceError::QuorumNotReached);
+        }
 
         // Check approval threshold
         let approval_bps = self.approval_threshold.get_or_default();
         let approval_required = (total_votes * U256::from(approval_bps)) / U256::from(10000u64);
-        assert!(proposal.for_votes >= approval_required, "Proposal defeated");
+        if proposal.for_votes < approval_required {
+            self.env().revert(GovernanceError::ProposalDefeated);
+        }
 
         // Mark as executed
         proposal.executed = true;
@@ -249,15 +300,19 @@ impl StakeFlowGovernance {
         });
     }
 
-    /// Cancel a proposal (owner only, before execution)
+    /// Cancel a proposal (guardian only, before execution)
     pub fn cancel_proposal(&mut self, proposal_id: u64) {
-        self.assert_owner();
+        self.assert_role(Role::Guardian);
 
         let mut proposal = self.proposals.get(&proposal_id)
-            .expect("Proposal not found");
+            .unwrap_or_revert_with(&self.env(), GovernanceError::ProposalNotFound);
 
-        assert!(!proposal.executed, "Already executed");
-        assert!(!proposal.cancelled, "Already cancelled");
+        if proposal.executed {
+            self.env().revert(GovernanceError::ProposalAlreadyExecuted);
+        }
+        if proposal.cancelled {
+            self.env().revert(GovernanceError::ProposalCancelled);
+        }
 
         proposal.cancelled = true;
         self.proposals.set(&proposal_id, proposal);
@@ -276,7 +331,7 @@ impl StakeFlowGovernance {
     }
 
     /// Get proposal status
-    pub fn get_proposal_status(&self, proposal_id: u64, total_supply: U256) -> ProposalStatus {
+    pub fn get_proposal_status(&self, proposal_id: u64) -> ProposalStatus {
         let proposal = match self.proposals.get(&proposal_id) {
             Some(p) => p,
             None => return ProposalStatus::Pending,
@@ -298,6 +353,7 @@ impl StakeFlowGovernance {
         }
 
         // Voting ended, check result
+        let total_supply = self.voting_power_source().get_past_total_supply(proposal.snapshot);
         let total_votes = proposal.for_votes + proposal.against_votes;
         let quorum_bps = self.quorum_percentage.get_or_default();
         let quorum_required = (total_supply * U256::from(quorum_bps)) / U256::from(10000u64);
@@ -343,32 +399,83 @@ impl StakeFlowGovernance {
 
     // ===== ADMIN FUNCTIONS =====
 
-    /// Update governance parameters (owner only)
-    pub fn update_governance_params(
-        &mut self,
-        new_voting_period: Option<u64>,
-        new_quorum_bps: Option<u32>,
-        new_approval_bps: Option<u32>,
-        new_proposal_threshold: Option<U256>,
-    ) {
-        self.assert_owner();
-
-        if let Some(period) = new_voting_period {
-            assert!(period >= 24 * 60 * 60, "Minimum 1 day voting period");
+    /// Queue a governance parameter or timelock change (admin only)
+    /// Returns timelock operation ID
+    pub fn schedule_admin_call(&mut self, call: GovernanceAdminCall) -> u64 {
+        self.assert_role(Role::Admin);
+        self.validate_admin_call(&call);
+        let bytes = call.to_bytes().unwrap_or_revert_with(&self.env(), GovernanceError::InvalidAdminCall);
+        self.timelock.schedule(Bytes::from(bytes))
+    }
+
+    /// Apply a queued admin call once its delay has passed (admin only)
+    pub fn execute_admin_call(&mut self, operation_id: u64) {
+        self.assert_role(Role::Admin);
+        let bytes = self.timelock.execute(operation_id);
+        let (call, _) = GovernanceAdminCall::from_bytes(bytes.as_slice())
+            .unwrap_or_revert_with(&self.env(), GovernanceError::InvalidAdminCall);
+        self.validate_admin_call(&call);
+
+        match call {
+            GovernanceAdminCall::UpdateGovernanceParams(params) => {
+                self.update_governance_params(params);
+            }
+            GovernanceAdminCall::SetTimelockDelay(min_delay) => {
+                self.timelock.set_min_delay(min_delay);
+            }
+        }
+    }
+
+    /// Cancel a queued admin call (guardian only)
+    pub fn cancel_admin_call(&mut self, operation_id: u64) {
+        self.assert_role(Role::Guardian);
+        self.timelock.cancel(operation_id);
+    }
+
+    /// Get a queued admin call
+    pub fn get_admin_call(&self, operation_id: u64) -> Option<TimelockOperation> {
+        self.timelock.get_operation(operation_id)
+    }
+
+    /// Get the timelock's minimum delay (ms)
+    pub fn get_timelock_delay(&self) -> u64 {
+        self.timelock.min_delay()
+    }
+
+    // ===== ACCESS CONTROL =====
+
+    delegate! {
+        to self.access {
+            fn grant_role(&mut self, role: Role, account: Address);
+            fn revoke_role(&mut self, role: Role, account: Address);
+            fn renounce_role(&mut self, role: Role, account: Address);
+            fn set_role_admin(&mut self, role: Role, admin_role: Role);
+            fn has_role(&self, role: Role, account: Address) -> bool;
+            fn transfer_ownership(&mut self, new_owner: Address);
+            fn accept_ownership(&mut self);
+            fn get_owner(&self) -> Address;
+            fn get_pending_owner(&self) -> Option<Address>;
+            fn get_role_admin(&self, role: Role) -> Role;
+        }
+    }
+
+    // ===== INTERNAL =====
+
+    /// Update governance parameters (through the timelock only)
+    fn update_governance_params(&mut self, params: GovernanceParamsUpdate) {
+        if let Some(period) = params.voting_period {
             self.voting_period.set(period);
         }
 
-        if let Some(quorum) = new_quorum_bps {
-            assert!(quorum <= 10000, "Max 100%");
+        if let Some(quorum) = params.quorum_bps {
             self.quorum_percentage.set(quorum);
         }
 
-        if let Some(approval) = new_approval_bps {
-            assert!(approval <= 10000 && approval >= 5000, "Approval between 50-100%");
+        if let Some(approval) = params.approval_bps {
             self.approval_threshold.set(approval);
         }
 
-        if let Some(threshold) = new_proposal_threshold {
+        if let Some(threshold) = params.proposal_threshold {
             self.proposal_threshold.set(threshold);
         }
 
@@ -377,13 +484,66 @@ impl StakeFlowGovernance {
         });
     }
 
-    // ===== INTERNAL =====
+    fn validate_admin_call(&self, call: &GovernanceAdminCall) {
+        if let GovernanceAdminCall::UpdateGovernanceParams(params) = call {
+            if let Some(period) = params.voting_period {
+                if period < 24 * 60 * 60 {
+                    self.env().revert(GovernanceError::VotingPeriodTooShort);
+                }
+            }
+            if let Some(quorum) = params.quorum_bps {
+                if quorum > 10000 {
+                    self.env().revert(GovernanceError::InvalidQuorum);
+                }
+            }
+            if let Some(approval) = params.approval_bps {
+                if !(5000..=10000).contains(&approval) {
+                    self.env().revert(GovernanceError::InvalidApprovalThreshold);
+                }
+            }
+        }
+    }
 
-    fn assert_owner(&self) {
-        let caller = self.env().caller();
-        let owner = self.owner.get().expect("Owner not set");
-        assert!(caller == owner, "Only owner");
+    /// Contract voting power and total supply are read from
+    fn voting_power_source(&self) -> VotingPowerSourceContractRef {
+        let source = self.vault_contract.get()
+            .unwrap_or_revert_with(&self.env(), GovernanceError::VotingPowerSourceNotSet);
+        VotingPowerSourceContractRef::new(self.env(), source)
     }
+
+    fn assert_role(&self, role: Role) {
+        self.access.check_role(role, self.env().caller());
+    }
+}
+
+/// Checkpointed stCSPR voting power: the stCSPR token, or a V3 vault reading through to it
+#[odra::external_contract]
+pub trait VotingPowerSource {
+    /// Delegated voting power of `account` at the end of block time `timestamp`
+    fn get_past_votes(&self, account: Address, timestamp: u64) -> U256;
+    /// Total supply at the end of block time `timestamp`
+    fn get_past_total_supply(&self, timestamp: u64) -> U256;
+}
+
+// ===== ERRORS =====
+
+#[odra::odra_error]
+pub enum GovernanceError {
+    InsufficientVotingPower = 6000,
+    ProposalNotFound = 6001,
+    VotingNotStarted = 6002,
+    VotingEnded = 6003,
+    VotingNotEnded = 6004,
+    ProposalCancelled = 6005,
+    ProposalAlreadyExecuted = 6006,
+    AlreadyVoted = 6007,
+    QuorumNotReached = 6008,
+    ProposalDefeated = 6009,
+    VotingPeriodTooShort = 6010,
+    InvalidQuorum = 6011,
+    InvalidApprovalThreshold = 6012,
+    InvalidAdminCall = 6013,
+    VotingPowerSourceNotSet = 6014,
 }
 
 // ===== EVENTS =====
@@ -436,90 +596,243 @@ pub struct GovernanceParamsUpdated {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use odra::host::{Deployer, HostRef};
+    use odra::host::{Deployer, HostEnv, HostRef};
+    use crate::stakeflow_vault_v3::{StakeFlowVaultV3, StakeFlowVaultV3InitArgs};
+    use crate::stcspr_token::{StCSPRToken, StCSPRTokenHostRef, StCSPRTokenInitArgs};
+    use crate::timelock::TimelockError;
+
+    /// Deploy governance over an stCSPR token minted by account 0
+    fn deploy_governance(env: &HostEnv) -> (StakeFlowGovernanceHostRef, StCSPRTokenHostRef) {
+        let token = StCSPRToken::deploy(env, StCSPRTokenInitArgs { vault: env.get_account(0) });
+        let gov = StakeFlowGovernance::deploy(env, StakeFlowGovernanceInitArgs {
+            vault_address: token.address(),
+        });
+        (gov, token)
+    }
+
+    /// Mint `amount` to `holder` and delegate it to themselves; returns the stCSPR minted
+    fn mint_votes(env: &HostEnv, token: &mut StCSPRTokenHostRef, holder: Address, amount: u64) -> U256 {
+        env.set_caller(env.get_account(0));
+        let minted = token.mint(holder, U256::from(amount));
+        env.set_caller(holder);
+        token.delegate(holder);
+        minted
+    }
 
     #[test]
     fn test_create_proposal() {
         let env = odra_test::env();
-        let vault = env.get_account(9);
-        let mut gov = StakeFlowGovernance::deploy(&env, StakeFlowGovernanceInitArgs {
-            vault_address: vault,
-        });
+        let (mut gov, mut token) = deploy_governance(&env);
+        let proposer = env.get_account(1);
+        let small_holder = env.get_account(2);
+        mint_votes(&env, &mut token, proposer, 2000);
+        mint_votes(&env, &mut token, small_holder, 500);
+        env.advance_block_time(1000);
 
         // User with 2000 stCSPR creates proposal
-        env.set_caller(env.get_account(1));
-        let voting_power = U256::from(2000);
-
+        env.set_caller(proposer);
         let proposal_id = gov.create_proposal(
             ProposalType::ChangePerformanceFee(300), // Change to 3%
             "Reduce performance fee to 3%".to_string(),
-            voting_power,
         );
 
         assert_eq!(proposal_id, 0);
 
         let proposal = gov.get_proposal(proposal_id).unwrap();
-        assert_eq!(proposal.proposer, env.get_account(1));
+        assert_eq!(proposal.proposer, proposer);
+        assert_eq!(proposal.snapshot, env.block_time() - 1);
         assert!(!proposal.executed);
+
+        // Below the 1000 stCSPR threshold, whatever the caller holds
+        env.set_caller(small_holder);
+        assert_eq!(
+            gov.try_create_proposal(ProposalType::EmergencyPause, "Pause".to_string()),
+            Err(GovernanceError::InsufficientVotingPower.into())
+        );
+
+        // stCSPR acquired in the proposal's own block is not counted yet
+        mint_votes(&env, &mut token, small_holder, 5000);
+        env.set_caller(small_holder);
+        assert_eq!(
+            gov.try_create_proposal(ProposalType::EmergencyPause, "Pause".to_string()),
+            Err(GovernanceError::InsufficientVotingPower.into())
+        );
     }
 
     #[test]
     fn test_voting() {
         let env = odra_test::env();
-        let vault = env.get_account(9);
-        let mut gov = StakeFlowGovernance::deploy(&env, StakeFlowGovernanceInitArgs {
-            vault_address: vault,
-        });
+        let (mut gov, mut token) = deploy_governance(&env);
+        let proposer = env.get_account(1);
+        let voter_for = env.get_account(2);
+        let voter_against = env.get_account(3);
+        let latecomer = env.get_account(4);
+        mint_votes(&env, &mut token, proposer, 2000);
+        let for_power = mint_votes(&env, &mut token, voter_for, 5000);
+        let against_power = mint_votes(&env, &mut token, voter_against, 2000);
+        env.advance_block_time(1000);
 
         // Create proposal
-        env.set_caller(env.get_account(1));
+        env.set_caller(proposer);
         let proposal_id = gov.create_proposal(
             ProposalType::ChangePerformanceFee(300),
             "Reduce fee".to_string(),
-            U256::from(2000),
         );
 
         // User 2 votes for
-        env.set_caller(env.get_account(2));
-        gov.cast_vote(proposal_id, true, U256::from(5000));
+        env.set_caller(voter_for);
+        gov.cast_vote(proposal_id, true);
 
         // User 3 votes against
-        env.set_caller(env.get_account(3));
-        gov.cast_vote(proposal_id, false, U256::from(2000));
+        env.set_caller(voter_against);
+        gov.cast_vote(proposal_id, false);
 
         let proposal = gov.get_proposal(proposal_id).unwrap();
-        assert_eq!(proposal.for_votes, U256::from(5000));
-        assert_eq!(proposal.against_votes, U256::from(2000));
+        assert_eq!(proposal.for_votes, for_power);
+        assert_eq!(proposal.against_votes, against_power);
+        assert!(env.emitted_event(&gov, VoteCast {
+            proposal_id,
+            voter: voter_for,
+            support: true,
+            voting_power: for_power,
+            timestamp: env.block_time(),
+        }));
+
+        // Votes bought or received after the snapshot do not count
+        env.advance_block_time(1000);
+        mint_votes(&env, &mut token, latecomer, 100_000);
+        env.set_caller(voter_for);
+        token.transfer(latecomer, for_power);
+        env.advance_block_time(1000);
+
+        env.set_caller(latecomer);
+        assert_eq!(
+            gov.try_cast_vote(proposal_id, true),
+            Err(GovernanceError::InsufficientVotingPower.into())
+        );
+        env.set_caller(voter_for);
+        assert_eq!(
+            gov.try_cast_vote(proposal_id, true),
+            Err(GovernanceError::AlreadyVoted.into())
+        );
+        assert_eq!(gov.get_proposal(proposal_id).unwrap().for_votes, for_power);
     }
 
     #[test]
     fn test_proposal_execution() {
         let env = odra_test::env();
-        let vault = env.get_account(9);
-        let mut gov = StakeFlowGovernance::deploy(&env, StakeFlowGovernanceInitArgs {
-            vault_address: vault,
-        });
-
-        // Create proposal
-        env.set_caller(env.get_account(1));
-        let proposal_id = gov.create_proposal(
-            ProposalType::EmergencyPause,
-            "Emergency pause".to_string(),
-            U256::from(2000),
-        );
+        let (mut gov, mut token) = deploy_governance(&env);
+        let proposer = env.get_account(1);
+        let whale = env.get_account(2);
+        mint_votes(&env, &mut token, proposer, 1000);
+        mint_votes(&env, &mut token, whale, 8000);
+        env.set_caller(env.get_account(0));
+        token.mint(env.get_account(3), U256::from(1000u64)); // Undelegated, still counts for quorum
+        env.advance_block_time(1000);
+
+        // Only the proposer votes: 1000 of 10000 stCSPR is short of the 20% quorum
+        env.set_caller(proposer);
+        let short_id = gov.create_proposal(ProposalType::EmergencyPause, "Emergency pause".to_string());
+        gov.cast_vote(short_id, true);
 
         // Vote with sufficient quorum and approval
-        env.set_caller(env.get_account(2));
-        gov.cast_vote(proposal_id, true, U256::from(8000));
+        let proposal_id = gov.create_proposal(ProposalType::EmergencyPause, "Emergency pause".to_string());
+        env.set_caller(whale);
+        gov.cast_vote(proposal_id, true);
+
+        // Minting after the snapshot does not move the quorum
+        env.set_caller(env.get_account(0));
+        token.mint(env.get_account(3), U256::from(1_000_000u64));
 
         // Advance time past voting period
         env.advance_block_time(4 * 24 * 60 * 60); // 4 days
 
+        assert_eq!(gov.get_proposal_status(short_id), ProposalStatus::Defeated);
+        assert_eq!(gov.try_execute_proposal(short_id), Err(GovernanceError::QuorumNotReached.into()));
+
         // Execute proposal
-        let total_supply = U256::from(10000); // Total stCSPR supply
-        gov.execute_proposal(proposal_id, total_supply);
+        assert_eq!(gov.get_proposal_status(proposal_id), ProposalStatus::Succeeded);
+        gov.execute_proposal(proposal_id);
 
         let proposal = gov.get_proposal(proposal_id).unwrap();
         assert!(proposal.executed);
     }
+
+    #[test]
+    fn test_vault_v3_as_voting_power_source() {
+        let env = odra_test::env();
+        let mut vault = StakeFlowVaultV3::deploy(&env, StakeFlowVaultV3InitArgs {
+            treasury_address: env.get_account(9),
+            unbonding_days: 7,
+        });
+        let mut token = StCSPRToken::deploy(&env, StCSPRTokenInitArgs { vault: vault.address() });
+        vault.set_token(token.address());
+        let mut gov = StakeFlowGovernance::deploy(&env, StakeFlowGovernanceInitArgs {
+            vault_address: vault.address(),
+        });
+
+        let holder = env.get_account(1);
+        env.set_caller(holder);
+        let stcspr = vault.with_tokens(U512::from(500_000_000_000u64)).deposit();
+        token.delegate(holder);
+        env.advance_block_time(1000);
+
+        let proposal_id = gov.create_proposal(ProposalType::EmergencyPause, "Emergency pause".to_string());
+        gov.cast_vote(proposal_id, true);
+        assert_eq!(gov.get_proposal(proposal_id).unwrap().for_votes, stcspr);
+
+        env.advance_block_time(4 * 24 * 60 * 60);
+        gov.execute_proposal(proposal_id);
+        assert!(gov.get_proposal(proposal_id).unwrap().executed);
+    }
+
+    #[test]
+    fn test_timelocked_governance_params() {
+        let env = odra_test::env();
+        let vault = env.get_account(9);
+        let mut gov = StakeFlowGovernance::deploy(&env, StakeFlowGovernanceInitArgs {
+            vault_address: vault,
+        });
+
+        let operation_id = gov.schedule_admin_call(GovernanceAdminCall::UpdateGovernanceParams(
+            GovernanceParamsUpdate {
+                voting_period: None,
+                quorum_bps: Some(3000),
+                approval_bps: None,
+                proposal_threshold: None,
+            },
+        ));
+        assert_eq!(gov.get_quorum_percentage(), 2000);
+
+        env.advance_block_time(gov.get_timelock_delay());
+        gov.execute_admin_call(operation_id);
+        assert_eq!(gov.get_quorum_percentage(), 3000);
+        assert_eq!(gov.get_approval_threshold(), 5000);
+    }
+
+    #[test]
+    fn test_cancelled_governance_params_never_apply() {
+        let env = odra_test::env();
+        let vault = env.get_account(9);
+        let mut gov = StakeFlowGovernance::deploy(&env, StakeFlowGovernanceInitArgs {
+            vault_address: vault,
+        });
+
+        let operation_id = gov.schedule_admin_call(GovernanceAdminCall::UpdateGovernanceParams(
+            GovernanceParamsUpdate {
+                voting_period: None,
+                quorum_bps: Some(0),
+                approval_bps: None,
+                proposal_threshold: None,
+            },
+        ));
+        gov.cancel_admin_call(operation_id);
+
+        env.advance_block_time(gov.get_timelock_delay());
+        assert_eq!(
+            gov.try_execute_admin_call(operation_id),
+            Err(TimelockError::OperationCancelled.into())
+        );
+        assert_eq!(gov.get_quorum_percentage(), 2000);
+    }
 }
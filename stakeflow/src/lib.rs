@@ -9,3 +9,15 @@ pub mod stcspr_token;
 // pub mod stakeflow_vault_v2;  // DISABLED - using V3 instead
 pub mod stakeflow_vault_v3;
 pub mod governance;
+pub mod delegation;
+pub mod withdrawal_queue;
+pub mod access_control;
+pub mod timelock;
+pub mod migration;
+pub mod oracle;
+pub mod rate_limits;
+pub mod deposit_limits;
+pub mod referrals;
+pub mod permit;
+pub mod votes;
+pub mod math;
@@ -0,0 +1,251 @@
+//! StakeFlow fixed-point math
+//!
+//! Shared by every vault and by `StCSPRToken`:
+//! - `mul_div` with an explicit rounding direction and a full 512-bit intermediate
+//! - Checked `U512` <-> `U256` conversions (CSPR amounts are `U512`, token amounts `U256`)
+//! - Share-price conversions between CSPR and stCSPR/shares, with virtual shares and assets
+//!
+//! Failures are returned as `MathError`, which callers revert with via `unwrap_or_revert`.
+
+use odra::prelude::*;
+use odra::casper_types::{U512, U256};
+
+/// Fixed-point scale of exchange rates: CSPR per stCSPR (or share), 1e9 = 1:1
+pub const RATE_SCALE: u64 = 1_000_000_000;
+
+/// Basis-point denominator (10000 = 100%)
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+/// Virtual shares and assets every share price is computed with
+///
+/// They pin an empty pool at 1:1 and dilute any donation made to inflate the price,
+/// so a first depositor can no longer round later deposits down to nothing.
+pub const VIRTUAL_SHARES: u64 = 1_000_000;
+/// Virtual CSPR paired with `VIRTUAL_SHARES`
+pub const VIRTUAL_ASSETS: u64 = 1_000_000;
+
+/// Rounding direction of a fixed-point result
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rounding {
+    /// Towards zero; use when the protocol pays out or mints
+    Down,
+    /// Away from zero; use when the protocol charges or burns
+    Up,
+}
+
+/// `a * b / denominator` without intermediate overflow
+pub fn mul_div(a: U256, b: U256, denominator: U256, rounding: Rounding) -> Result<U256, MathError> {
+    if denominator.is_zero() {
+        return Err(MathError::DivisionByZero);
+    }
+
+    // 256 x 256 bits always fits in 512
+    let product = to_u512(a) * to_u512(b);
+    let denominator = to_u512(denominator);
+    let mut quotient = product / denominator;
+    if rounding == Rounding::Up && !(product % denominator).is_zero() {
+        quotient += U512::one();
+    }
+    to_u256(quotient)
+}
+
+/// `mul_div` for CSPR amounts; inputs and result must fit in 256 bits
+pub fn mul_div_u512(a: U512, b: U512, denominator: U512, rounding: Rounding) -> Result<U512, MathError> {
+    mul_div(to_u256(a)?, to_u256(b)?, to_u256(denominator)?, rounding).map(to_u512)
+}
+
+/// Share of `amount` in basis points
+pub fn apply_bps(amount: U512, bps: u32, rounding: Rounding) -> Result<U512, MathError> {
+    mul_div_u512(amount, U512::from(bps), U512::from(BPS_DENOMINATOR), rounding)
+}
+
+/// `a - b`, failing instead of wrapping
+pub fn checked_sub(a: U512, b: U512) -> Result<U512, MathError> {
+    a.checked_sub(b).ok_or(MathError::Underflow)
+}
+
+/// Widen a token amount to a CSPR amount
+pub fn to_u512(value: U256) -> U512 {
+    let mut bytes = [0u8; 32];
+    value.to_little_endian(&mut bytes);
+    U512::from_little_endian(&bytes)
+}
+
+/// Narrow a CSPR amount to a token amount, failing if it does not fit
+pub fn to_u256(value: U512) -> Result<U256, MathError> {
+    if value.bits() > 256 {
+        return Err(MathError::ConversionOverflow);
+    }
+    let mut bytes = [0u8; 64];
+    value.to_little_endian(&mut bytes);
+    Ok(U256::from_little_endian(&bytes[..32]))
+}
+
+/// Shares `assets` buy in a pool of `total_assets` backing `total_shares`
+pub fn assets_to_shares(assets: U256, total_assets: U256, total_shares: U256, rounding: Rounding) -> Result<U256, MathError> {
+    let (virtual_assets, virtual_shares) = virtual_totals(total_assets, total_shares)?;
+    mul_div(assets, virtual_shares, virtual_assets, rounding)
+}
+
+/// Assets `shares` redeem for in a pool of `total_assets` backing `total_shares`
+pub fn shares_to_assets(shares: U256, total_assets: U256, total_shares: U256, rounding: Rounding) -> Result<U256, MathError> {
+    let (virtual_assets, virtual_shares) = virtual_totals(total_assets, total_shares)?;
+    mul_div(shares, virtual_assets, virtual_shares, rounding)
+}
+
+/// Share price of a pool, scaled by `RATE_SCALE` and rounded down
+pub fn share_price(total_assets: U256, total_shares: U256) -> Result<U256, MathError> {
+    shares_to_assets(U256::from(RATE_SCALE), total_assets, total_shares, Rounding::Down)
+}
+
+/// `assets_to_shares` for CSPR-denominated pools
+pub fn assets_to_shares_u512(assets: U512, total_assets: U512, total_shares: U512, rounding: Rounding) -> Result<U512, MathError> {
+    assets_to_shares(to_u256(assets)?, to_u256(total_assets)?, to_u256(total_shares)?, rounding).map(to_u512)
+}
+
+/// `shares_to_assets` for CSPR-denominated pools
+pub fn shares_to_assets_u512(shares: U512, total_assets: U512, total_shares: U512, rounding: Rounding) -> Result<U512, MathError> {
+    shares_to_assets(to_u256(shares)?, to_u256(total_assets)?, to_u256(total_shares)?, rounding).map(to_u512)
+}
+
+fn virtual_totals(total_assets: U256, total_shares: U256) -> Result<(U256, U256), MathError> {
+    let assets = total_assets.checked_add(U256::from(VIRTUAL_ASSETS)).ok_or(MathError::Overflow)?;
+    let shares = total_shares.checked_add(U256::from(VIRTUAL_SHARES)).ok_or(MathError::Overflow)?;
+    Ok((assets, shares))
+}
+
+// ===== ERRORS =====
+
+#[odra::odra_error]
+#[derive(Debug, PartialEq, Eq)]
+pub enum MathError {
+    Underflow = 700,
+    DivisionByZero = 701,
+    ConversionOverflow = 702,
+    Overflow = 703,
+}
+
+// ===== TESTS =====
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn any_u256() -> impl Strategy<Value = U256> {
+        prop_oneof![
+            any::<[u64; 4]>().prop_map(U256),
+            any::<u128>().prop_map(U256::from),
+            Just(U256::zero()),
+            Just(U256::one()),
+            Just(U256::MAX),
+        ]
+    }
+
+    fn nonzero_u256() -> impl Strategy<Value = U256> {
+        any_u256().prop_filter("non-zero", |value| !value.is_zero())
+    }
+
+    #[test]
+    fn test_mul_div_extremes() {
+        let max = U256::MAX;
+        assert_eq!(mul_div(max, max, max, Rounding::Down), Ok(max));
+        assert_eq!(mul_div(max, U256::from(2u64), max, Rounding::Up), Ok(U256::from(2u64)));
+        assert_eq!(mul_div(max, max, U256::one(), Rounding::Down), Err(MathError::ConversionOverflow));
+        assert_eq!(mul_div(max, max, U256::zero(), Rounding::Down), Err(MathError::DivisionByZero));
+        assert_eq!(mul_div(U256::from(7u64), U256::one(), U256::from(2u64), Rounding::Down), Ok(U256::from(3u64)));
+        assert_eq!(mul_div(U256::from(7u64), U256::one(), U256::from(2u64), Rounding::Up), Ok(U256::from(4u64)));
+    }
+
+    #[test]
+    fn test_u512_narrowing() {
+        let above = to_u512(U256::MAX) + U512::one();
+        assert_eq!(to_u256(above), Err(MathError::ConversionOverflow));
+        assert_eq!(to_u256(U512::MAX), Err(MathError::ConversionOverflow));
+        assert_eq!(checked_sub(U512::one(), U512::from(2u64)), Err(MathError::Underflow));
+    }
+
+    #[test]
+    fn test_empty_pool_prices_one_to_one() {
+        let amount = U256::from(10_000_000_000u64);
+        assert_eq!(assets_to_shares(amount, U256::zero(), U256::zero(), Rounding::Down), Ok(amount));
+        assert_eq!(share_price(U256::zero(), U256::zero()), Ok(U256::from(RATE_SCALE)));
+        assert_eq!(assets_to_shares(amount, U256::MAX, U256::zero(), Rounding::Down), Err(MathError::Overflow));
+    }
+
+    proptest! {
+        #[test]
+        fn prop_conversion_round_trips(value in any_u256()) {
+            prop_assert_eq!(to_u256(to_u512(value)), Ok(value));
+        }
+
+        #[test]
+        fn prop_mul_div_brackets_exact_result(a in any_u256(), b in any_u256(), d in nonzero_u256()) {
+            let product = to_u512(a) * to_u512(b);
+            let denominator = to_u512(d);
+            match (mul_div(a, b, d, Rounding::Down), mul_div(a, b, d, Rounding::Up)) {
+                (Ok(down), Ok(up)) => {
+                    prop_assert!(to_u512(down) * denominator <= product);
+                    prop_assert!(product < (to_u512(down) + U512::one()) * denominator);
+                    let exact = (product % denominator).is_zero();
+                    prop_assert_eq!(up, if exact { down } else { down + U256::one() });
+                }
+                (Ok(down), Err(MathError::ConversionOverflow)) => {
+                    // Only rounding up past the top of the range may fail alone
+                    prop_assert_eq!(down, U256::MAX);
+                }
+                (Err(MathError::ConversionOverflow), Err(MathError::ConversionOverflow)) => {
+                    prop_assert!(product / denominator > to_u512(U256::MAX));
+                }
+                other => prop_assert!(false, "unexpected result {:?}", other),
+            }
+        }
+
+        #[test]
+        fn prop_share_round_trip_never_creates_value(
+            assets in any::<u128>(),
+            total_assets in any::<u128>(),
+            total_shares in any::<u128>(),
+        ) {
+            let (assets, total_assets, total_shares) = (U256::from(assets), U256::from(total_assets), U256::from(total_shares));
+            let shares = assets_to_shares(assets, total_assets, total_shares, Rounding::Down).unwrap();
+            let back = shares_to_assets(shares, total_assets, total_shares, Rounding::Down).unwrap();
+            prop_assert!(back <= assets);
+
+            // Charging rounds the other way and never undercharges
+            let shares_needed = assets_to_shares(assets, total_assets, total_shares, Rounding::Up).unwrap();
+            prop_assert!(shares_to_assets(shares_needed, total_assets, total_shares, Rounding::Up).unwrap() >= assets);
+        }
+
+        #[test]
+        fn prop_share_price_never_overvalues_shares(
+            shares in any::<u128>(),
+            total_assets in any::<u128>(),
+            total_shares in any::<u128>(),
+        ) {
+            let (shares, total_assets, total_shares) = (U256::from(shares), U256::from(total_assets), U256::from(total_shares));
+            let price = share_price(total_assets, total_shares).unwrap();
+            let priced = mul_div(shares, price, U256::from(RATE_SCALE), Rounding::Down).unwrap();
+            prop_assert!(priced <= shares_to_assets(shares, total_assets, total_shares, Rounding::Down).unwrap());
+        }
+
+        #[test]
+        fn prop_deposit_then_redeem_never_profits(
+            assets in 1u128..=u128::MAX,
+            total_assets in any::<u128>(),
+            total_shares in any::<u128>(),
+        ) {
+            let (assets, total_assets, total_shares) = (U256::from(assets), U256::from(total_assets), U256::from(total_shares));
+            let shares = assets_to_shares(assets, total_assets, total_shares, Rounding::Down).unwrap();
+            let redeemed = shares_to_assets(shares, total_assets + assets, total_shares + shares, Rounding::Down).unwrap();
+            prop_assert!(redeemed <= assets);
+        }
+
+        #[test]
+        fn prop_bps_never_exceeds_amount(amount in any::<u128>(), bps in 0u32..=10_000) {
+            let amount = U512::from(amount);
+            prop_assert!(apply_bps(amount, bps, Rounding::Up).unwrap() <= amount);
+            prop_assert!(apply_bps(amount, bps, Rounding::Down).unwrap() <= apply_bps(amount, bps, Rounding::Up).unwrap());
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use odra::prelude::*;
+use odra::ContractRef;
+use odra::casper_types::{U512, U256};
+
+/// StakeFlow Legacy Migration
+///
+/// Bookkeeping for positions moved out of `StakeFlowVault` (v1) and `StakeFlowMinimal`:
+/// - Registry of upgraded legacy contracts that redeem positions and send the CSPR in
+/// - Attestation for copies deployed without upgrade support: the position is read from the
+///   legacy contract, the user withdraws it there, and only CSPR the position actually
+///   dropped by is accepted as migrated
+/// - CSPR migrated per (legacy contract, user), so later deposits can migrate too
+///
+/// Used as a `SubModule` by `StakeFlowVaultV3`; the parent mints the stCSPR.
+#[odra::module]
+pub struct LegacyMigration {
+    /// Legacy contracts allowed to migrate: address -> enabled
+    sources: Mapping<Address, bool>,
+    /// CSPR migrated: (legacy contract, user) -> amount
+    migrated: Mapping<(Address, Address), U512>,
+    /// Total CSPR received through migrations
+    total_migrated: Var<U512>,
+    /// Deployed legacy contracts positions can be attested on: address -> kind (`None` = disabled)
+    attested_sources: Mapping<Address, Option<LegacyKind>>,
+    /// Latest attestation: (legacy contract, user) -> position and CSPR migrated against it
+    attestations: Mapping<(Address, Address), LegacyAttestation>,
+}
+
+/// Kind of legacy contract, selecting the view a position is read with
+#[odra::odra_type]
+pub enum LegacyKind {
+    /// `StakeFlowMinimal`, read with `get_balance`
+    Minimal,
+    /// `StakeFlowVault` (v1), read with `get_user_value`
+    VaultV1,
+}
+
+/// A user's legacy position when attested, and CSPR migrated against it since
+#[odra::odra_type]
+pub struct LegacyAttestation {
+    /// CSPR value of the position when attested, plus CSPR migrated before then
+    pub attested: U512,
+    /// CSPR migrated against this and earlier attestations
+    pub migrated: U512,
+}
+
+#[odra::module]
+impl LegacyMigration {
+    /// Enable or disable a legacy contract as a migration source
+    pub fn set_source(&mut self, source: Address, enabled: bool) {
+        self.sources.set(&source, enabled);
+
+        self.env().emit_event(MigrationSourceUpdated {
+            source,
+            enabled,
+            timestamp: self.env().get_block_time(),
+        });
+    }
+
+    /// Enable a deployed legacy contract of `kind` for attested migrations, or disable it with `None`
+    pub fn set_attested_source(&mut self, source: Address, kind: Option<LegacyKind>) {
+        self.attested_sources.set(&source, kind.clone());
+
+        self.env().emit_event(AttestedSourceUpdated {
+            source,
+            kind,
+            timestamp: self.env().get_block_time(),
+        });
+    }
+
+    /// Record CSPR a registered legacy contract redeemed for `user` and sent in
+    pub fn record(&mut self, source: Address, user: Address, cspr_amount: U512) {
+        if !self.is_source(source) {
+            self.env().revert(MigrationError::NotMigrationSource);
+        }
+        self.add_migrated(source, user, cspr_amount);
+    }
+
+    /// Read `user`'s position on `source` and make it the user's attestation, replacing any earlier one
+    /// CSPR already migrated against earlier attestations stays counted
+    /// Returns the CSPR value attested
+    pub fn attest(&mut self, source: Address, user: Address) -> U512 {
+        let position = self.position_of(source, user);
+        let migrated = self.attestations.get(&(source, user))
+            .map(|attestation| attestation.migrated)
+            .unwrap_or_default();
+        self.attestations.set(&(source, user), LegacyAttestation { attested: position + migrated, migrated });
+
+        self.env().emit_event(LegacyPositionAttested {
+            source,
+            user,
+            cspr_amount: position,
+            timestamp: self.env().get_block_time(),
+        });
+
+        position
+    }
+
+    /// Record CSPR `user` brings in against their attestation on `source`
+    /// The position must have dropped since the attestation by at least everything migrated against it
+    pub fn record_attested(&mut self, source: Address, user: Address, cspr_amount: U512) {
+        let mut attestation = self.attestations.get(&(source, user))
+            .unwrap_or_revert_with(&self.env(), MigrationError::NotAttested);
+        let migrated = attestation.migrated + cspr_amount;
+        if migrated > attestation.attested {
+            self.env().revert(MigrationError::ExceedsAttestedPosition);
+        }
+        if self.position_of(source, user) + migrated > attestation.attested {
+            self.env().revert(MigrationError::LegacyPositionNotExited);
+        }
+
+        attestation.migrated = migrated;
+        self.attestations.set(&(source, user), attestation);
+        self.add_migrated(source, user, cspr_amount);
+    }
+
+    // ===== VIEW FUNCTIONS =====
+
+    /// Check if a legacy contract may migrate positions
+    pub fn is_source(&self, source: Address) -> bool {
+        self.sources.get(&source).unwrap_or(false)
+    }
+
+    /// Get the kind of a deployed legacy contract positions can be attested on, if enabled
+    pub fn attested_source_kind(&self, source: Address) -> Option<LegacyKind> {
+        self.attested_sources.get(&source).flatten()
+    }
+
+    /// Get a user's latest attestation on a legacy contract
+    pub fn attestation(&self, source: Address, user: Address) -> Option<LegacyAttestation> {
+        self.attestations.get(&(source, user))
+    }
+
+    /// Get CSPR a user migrated from a legacy contract
+    pub fn migrated_amount(&self, source: Address, user: Address) -> U512 {
+        self.migrated.get(&(source, user)).unwrap_or_default()
+    }
+
+    /// Get total CSPR received through migrations
+    pub fn total_migrated(&self) -> U512 {
+        self.total_migrated.get_or_default()
+    }
+
+    // ===== INTERNAL =====
+
+    fn add_migrated(&mut self, source: Address, user: Address, cspr_amount: U512) {
+        self.migrated.set(&(source, user), self.migrated_amount(source, user) + cspr_amount);
+        self.total_migrated.set(self.total_migrated.get_or_default() + cspr_amount);
+    }
+
+    /// CSPR value of `user`'s position on an attested source, read from the source itself
+    fn position_of(&self, source: Address, user: Address) -> U512 {
+        let kind = self.attested_source_kind(source)
+            .unwrap_or_revert_with(&self.env(), MigrationError::NotMigrationSource);
+        let legacy = LegacyPositionsContractRef::new(self.env(), source);
+        match kind {
+            LegacyKind::Minimal => legacy.get_balance(user),
+            LegacyKind::VaultV1 => legacy.get_user_value(user),
+        }
+    }
+}
+
+/// Entry point legacy contracts call on the V3 vault, attaching the redeemed CSPR
+#[odra::external_contract]
+pub trait MigrationTarget {
+    /// Mint stCSPR to `user` for the attached CSPR; returns stCSPR minted
+    fn migrate_deposit(&mut self, user: Address) -> U256;
+}
+
+/// Position views of the deployed legacy contracts
+#[odra::external_contract]
+pub trait LegacyPositions {
+    /// `StakeFlowMinimal`: CSPR deposited by `user`
+    fn get_balance(&self, user: Address) -> U512;
+    /// `StakeFlowVault` (v1): CSPR value of `user`'s shares
+    fn get_user_value(&self, user: Address) -> U512;
+}
+
+// ===== ERRORS =====
+
+#[odra::odra_error]
+pub enum MigrationError {
+    NotMigrationSource = 500,
+    ExceedsAttestedPosition = 501,
+    LegacyPositionNotExited = 502,
+    NotAttested = 503,
+}
+
+// ===== EVENTS =====
+
+#[odra::event]
+pub struct MigrationSourceUpdated {
+    pub source: Address,
+    pub enabled: bool,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct AttestedSourceUpdated {
+    pub source: Address,
+    pub kind: Option<LegacyKind>,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct LegacyPositionAttested {
+    pub source: Address,
+    pub user: Address,
+    pub cspr_amount: U512,
+    pub timestamp: u64,
+}
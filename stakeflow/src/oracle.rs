@@ -0,0 +1,175 @@
+use odra::prelude::*;
+use odra::casper_types::U512;
+
+/// Matching reports needed to finalize an era on a fresh deployment
+pub const DEFAULT_QUORUM: u32 = 1;
+
+/// StakeFlow Reward Oracle
+///
+/// Era reports from authorized reporters replace operator-supplied reward amounts:
+/// - Each reporter submits at most one report per era
+/// - Identical reports are tallied; the first to reach the quorum finalizes its era
+/// - Only ended eras can be reported, and eras finalize in increasing order
+///
+/// Used as a `SubModule` by the V3 vault; the parent checks the reporter's role
+/// and applies finalized reports to its exchange rate.
+#[odra::module]
+pub struct RewardOracle {
+    /// Matching reports needed to finalize an era
+    quorum: Var<u32>,
+    /// Most recent finalized era
+    last_finalized_era: Var<u64>,
+    /// Finalized reports: era -> report
+    finalized: Mapping<u64, EraReport>,
+    /// Submissions: (era, reporter) -> reported
+    reported: Mapping<(u64, Address), bool>,
+    /// Votes per distinct report: (era, report) -> reporters
+    tallies: Mapping<(u64, EraReport), u32>,
+}
+
+/// What the vault's validators did over one era
+#[odra::odra_type]
+pub struct EraReport {
+    /// Era the report covers
+    pub era: u64,
+    /// Stake held with validators at the end of the era
+    pub total_delegated: U512,
+    /// Staking rewards earned during the era
+    pub rewards: U512,
+    /// Stake lost to slashing during the era
+    pub slashed: U512,
+}
+
+#[odra::module]
+impl RewardOracle {
+    /// Set the number of matching reports needed to finalize an era
+    pub fn set_quorum(&mut self, quorum: u32) {
+        if quorum == 0 {
+            self.env().revert(OracleError::InvalidQuorum);
+        }
+        self.quorum.set(quorum);
+
+        self.env().emit_event(OracleQuorumUpdated {
+            quorum,
+            timestamp: self.env().get_block_time(),
+        });
+    }
+
+    /// Record `reporter`'s report for an ended era
+    /// Returns the report if this submission finalized it
+    pub fn submit(&mut self, reporter: Address, report: EraReport, current_era: u64) -> Option<EraReport> {
+        let quorum = self.quorum();
+        if quorum == 0 {
+            self.env().revert(OracleError::QuorumNotSet);
+        }
+        if report.era >= current_era {
+            self.env().revert(OracleError::EraNotEnded);
+        }
+        if self.last_finalized_era().is_some_and(|last| report.era <= last) {
+            self.env().revert(OracleError::EraAlreadyFinalized);
+        }
+        if self.has_reported(report.era, reporter) {
+            self.env().revert(OracleError::AlreadyReported);
+        }
+
+        self.reported.set(&(report.era, reporter), true);
+        let key = (report.era, report.clone());
+        let votes = self.tallies.get(&key).unwrap_or(0) + 1;
+        self.tallies.set(&key, votes);
+
+        self.env().emit_event(ReportSubmitted {
+            reporter,
+            era: report.era,
+            total_delegated: report.total_delegated,
+            rewards: report.rewards,
+            slashed: report.slashed,
+            votes,
+            timestamp: self.env().get_block_time(),
+        });
+
+        if votes < quorum {
+            return None;
+        }
+
+        self.finalized.set(&report.era, report.clone());
+        self.last_finalized_era.set(report.era);
+
+        self.env().emit_event(ReportFinalized {
+            era: report.era,
+            total_delegated: report.total_delegated,
+            rewards: report.rewards,
+            slashed: report.slashed,
+            votes,
+            timestamp: self.env().get_block_time(),
+        });
+
+        Some(report)
+    }
+
+    // ===== VIEW FUNCTIONS =====
+
+    /// Get the number of matching reports needed to finalize an era
+    pub fn quorum(&self) -> u32 {
+        self.quorum.get_or_default()
+    }
+
+    /// Get the most recent finalized era, if any
+    pub fn last_finalized_era(&self) -> Option<u64> {
+        self.last_finalized_era.get()
+    }
+
+    /// Get the finalized report of an era
+    pub fn finalized_report(&self, era: u64) -> Option<EraReport> {
+        self.finalized.get(&era)
+    }
+
+    /// Check if a reporter already reported an era
+    pub fn has_reported(&self, era: u64, reporter: Address) -> bool {
+        self.reported.get(&(era, reporter)).unwrap_or(false)
+    }
+
+    /// Get the votes cast for a report
+    pub fn votes(&self, report: EraReport) -> u32 {
+        self.tallies.get(&(report.era, report)).unwrap_or(0)
+    }
+}
+
+// ===== ERRORS =====
+
+#[odra::odra_error]
+pub enum OracleError {
+    InvalidQuorum = 800,
+    QuorumNotSet = 801,
+    EraNotEnded = 802,
+    EraAlreadyFinalized = 803,
+    AlreadyReported = 804,
+}
+
+// ===== EVENTS =====
+
+#[odra::event]
+pub struct OracleQuorumUpdated {
+    pub quorum: u32,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct ReportSubmitted {
+    pub reporter: Address,
+    pub era: u64,
+    pub total_delegated: U512,
+    pub rewards: U512,
+    pub slashed: U512,
+    pub votes: u32,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct ReportFinalized {
+    pub era: u64,
+    pub total_delegated: U512,
+    pub rewards: U512,
+    pub slashed: U512,
+    pub votes: u32,
+    pub timestamp: u64,
+}
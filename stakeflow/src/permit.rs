@@ -0,0 +1,68 @@
+use odra::prelude::*;
+use odra::casper_types::bytesrepr::{Bytes, ToBytes};
+use odra::casper_types::PublicKey;
+
+/// Prefix of every permit message
+const PERMIT_DOMAIN: &[u8] = b"stakeflow:permit";
+
+/// StakeFlow Permit Nonces
+///
+/// Signed approvals (EIP-2612 style) for contracts that approve on an owner's behalf:
+/// - Owners sign a message off-chain with their Casper account key; anyone submits it
+/// - Messages are bound to this contract, the owner, the parent's approval payload,
+///   the owner's next nonce and a deadline, so a signature is good for one use only
+///
+/// Used as a `SubModule`; the parent encodes what is approved and applies the approval.
+#[odra::module]
+pub struct PermitNonces {
+    /// Owner -> nonce their next permit must be signed with
+    nonces: Mapping<Address, u64>,
+}
+
+#[odra::module]
+impl PermitNonces {
+    /// Check `owner`'s signature over `payload` and consume their nonce
+    /// `public_key` must be the key of the owner's account
+    pub fn use_permit(&mut self, owner: Address, payload: Bytes, deadline: u64, signature: Bytes, public_key: PublicKey) {
+        if self.env().get_block_time() > deadline {
+            self.env().revert(PermitError::PermitExpired);
+        }
+        if Address::from(public_key.clone()) != owner {
+            self.env().revert(PermitError::PermitKeyMismatch);
+        }
+
+        let message = self.message(owner, payload, deadline);
+        if !self.env().verify_signature(&message, &signature, &public_key) {
+            self.env().revert(PermitError::InvalidPermitSignature);
+        }
+        self.nonces.set(&owner, self.nonce(owner) + 1);
+    }
+
+    // ===== VIEW FUNCTIONS =====
+
+    /// Get the nonce `owner`'s next permit must be signed with
+    pub fn nonce(&self, owner: Address) -> u64 {
+        self.nonces.get(&owner).unwrap_or(0)
+    }
+
+    /// Message `owner` signs to approve `payload` on this contract until `deadline`
+    pub fn message(&self, owner: Address, payload: Bytes, deadline: u64) -> Bytes {
+        let env = self.env();
+        let mut message = PERMIT_DOMAIN.to_vec();
+        message.extend(env.self_address().to_bytes().unwrap_or_revert(&env));
+        message.extend(owner.to_bytes().unwrap_or_revert(&env));
+        message.extend(payload.iter());
+        message.extend(self.nonce(owner).to_bytes().unwrap_or_revert(&env));
+        message.extend(deadline.to_bytes().unwrap_or_revert(&env));
+        Bytes::from(message)
+    }
+}
+
+// ===== ERRORS =====
+
+#[odra::odra_error]
+pub enum PermitError {
+    PermitExpired = 1300,
+    InvalidPermitSignature = 1301,
+    PermitKeyMismatch = 1302,
+}
@@ -0,0 +1,109 @@
+use odra::prelude::*;
+use odra::casper_types::U256;
+use crate::math::{self, Rounding};
+
+/// One year in milliseconds (block time units)
+pub const YEAR_MILLIS: u64 = 365 * 24 * 60 * 60 * 1000;
+
+/// Bounds on how far and how often reports may move the exchange rate
+#[odra::odra_type]
+pub struct RateLimits {
+    /// Largest increase, as an annualized rate over the time since the last report (1000 = 10% APR)
+    pub max_apr_bps: u32,
+    /// Largest decrease in a single report (500 = 5%)
+    pub max_decrease_bps: u32,
+    /// Shortest time between two applied reports (ms)
+    pub min_report_interval: u64,
+}
+
+impl RateLimits {
+    /// Reject limits that would block every report or allow any loss
+    pub fn validate(&self) -> Result<(), RateLimitError> {
+        if self.max_apr_bps == 0 || self.max_decrease_bps == 0 || self.max_decrease_bps > 10_000 {
+            return Err(RateLimitError::InvalidRateLimits);
+        }
+        Ok(())
+    }
+
+    /// Check a move from `old_rate` to `new_rate` made `elapsed` ms after the previous one
+    pub fn check(&self, old_rate: U256, new_rate: U256, elapsed: u64) -> Result<(), RateLimitError> {
+        if elapsed < self.min_report_interval {
+            return Err(RateLimitError::ReportTooSoon);
+        }
+
+        if new_rate > old_rate {
+            // Increase allowed: old_rate * max_apr * elapsed / year
+            let budget = math::mul_div(
+                old_rate,
+                U256::from(self.max_apr_bps) * U256::from(elapsed),
+                U256::from(math::BPS_DENOMINATOR) * U256::from(YEAR_MILLIS),
+                Rounding::Down,
+            ).map_err(|_| RateLimitError::RateIncreaseTooHigh)?;
+            if new_rate - old_rate > budget {
+                return Err(RateLimitError::RateIncreaseTooHigh);
+            }
+        } else {
+            let budget = math::mul_div(
+                old_rate,
+                U256::from(self.max_decrease_bps),
+                U256::from(math::BPS_DENOMINATOR),
+                Rounding::Down,
+            ).map_err(|_| RateLimitError::RateDecreaseTooHigh)?;
+            if old_rate - new_rate > budget {
+                return Err(RateLimitError::RateDecreaseTooHigh);
+            }
+        }
+        Ok(())
+    }
+}
+
+// ===== ERRORS =====
+
+#[odra::odra_error]
+#[derive(Debug, PartialEq, Eq)]
+pub enum RateLimitError {
+    InvalidRateLimits = 900,
+    ReportTooSoon = 901,
+    RateIncreaseTooHigh = 902,
+    RateDecreaseTooHigh = 903,
+}
+
+// ===== TESTS =====
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY_MILLIS: u64 = 24 * 60 * 60 * 1000;
+
+    fn limits() -> RateLimits {
+        RateLimits { max_apr_bps: 2000, max_decrease_bps: 500, min_report_interval: 60 * 60 * 1000 }
+    }
+
+    #[test]
+    fn test_increase_is_capped_by_elapsed_time() {
+        let rate = U256::from(math::RATE_SCALE);
+        // 20% APR over 365 days allows +20%, over one day about +0.0548%
+        assert_eq!(limits().check(rate, rate * 12 / 10, YEAR_MILLIS), Ok(()));
+        assert_eq!(limits().check(rate, rate + U256::from(547_000u64), DAY_MILLIS), Ok(()));
+        assert_eq!(
+            limits().check(rate, rate + U256::from(548_000u64), DAY_MILLIS),
+            Err(RateLimitError::RateIncreaseTooHigh)
+        );
+    }
+
+    #[test]
+    fn test_decrease_and_interval_limits() {
+        let rate = U256::from(math::RATE_SCALE);
+        assert_eq!(limits().check(rate, rate * 95 / 100, DAY_MILLIS), Ok(()));
+        assert_eq!(limits().check(rate, rate * 94 / 100, DAY_MILLIS), Err(RateLimitError::RateDecreaseTooHigh));
+        assert_eq!(limits().check(rate, rate, 60 * 1000), Err(RateLimitError::ReportTooSoon));
+    }
+
+    #[test]
+    fn test_validate_rejects_degenerate_limits() {
+        assert_eq!(limits().validate(), Ok(()));
+        assert_eq!(RateLimits { max_apr_bps: 0, ..limits() }.validate(), Err(RateLimitError::InvalidRateLimits));
+        assert_eq!(RateLimits { max_decrease_bps: 10_001, ..limits() }.validate(), Err(RateLimitError::InvalidRateLimits));
+    }
+}
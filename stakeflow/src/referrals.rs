@@ -0,0 +1,267 @@
+use odra::prelude::*;
+use odra::casper_types::{U256, U512};
+use crate::math::{self, Rounding};
+
+/// Highest share of the performance fee paid to referrers: 50%
+pub const MAX_REFERRAL_FEE_SHARE_BPS: u32 = 5_000;
+
+/// Fixed-point scale of the fee-per-share index
+const FEE_INDEX_SCALE: u128 = 1_000_000_000_000_000_000;
+
+/// StakeFlow Referral Book
+///
+/// Attributes deposits to the partner wallets and integrators that sent them:
+/// - A user is credited to the first referrer they deposit with; later referrals keep it
+/// - Per-referrer volume, referred users and active stake (shares still held by referred users;
+///   burning or transferring stCSPR away reduces it)
+/// - A configurable share of the fees, split between referrers by active stake through a
+///   fee-per-share index so referrers claim without the book iterating over them
+///
+/// Used as a `SubModule` by `StakeFlowVaultV3`; the parent checks roles and holds the fee shares
+/// until claimed. v1 charges no fee a referrer could be paid from, so it takes no referrals.
+#[odra::module]
+pub struct ReferralBook {
+    /// Referred user -> referrer credited with their deposits
+    referrer_of: Mapping<Address, Address>,
+    /// Referred user -> shares from referred deposits still held
+    user_shares: Mapping<Address, U256>,
+    /// Referrer -> active shares of their referred users
+    referred_shares: Mapping<Address, U256>,
+    /// Referrer -> CSPR deposited through them, all time
+    referred_volume: Mapping<Address, U512>,
+    /// Referrer -> number of users credited to them
+    referred_users: Mapping<Address, u32>,
+    /// Active shares across all referrers
+    total_referred_shares: Var<U256>,
+    /// Share of fees paid to referrers in basis points (1000 = 10%)
+    fee_share_bps: Var<u32>,
+    /// Fee shares paid per referred share, scaled by `FEE_INDEX_SCALE`
+    fee_index: Var<U256>,
+    /// Referrer -> fee index their owed fees are settled up to
+    referrer_index: Mapping<Address, U256>,
+    /// Referrer -> settled fee shares not yet claimed
+    owed_fees: Mapping<Address, U256>,
+}
+
+/// Referrer activity and earnings
+#[odra::odra_type]
+pub struct ReferrerStats {
+    /// CSPR deposited through the referrer, all time
+    pub referred_volume: U512,
+    /// Users credited to the referrer
+    pub referred_users: u32,
+    /// Shares referred users still hold from referred deposits
+    pub active_shares: U256,
+    /// Fee shares the referrer can claim
+    pub claimable: U256,
+}
+
+#[odra::module]
+impl ReferralBook {
+    /// Credit a deposit of `cspr_amount` minting `shares` to `user`'s referrer
+    /// Returns the referrer credited, which is `referrer` unless the user already has one
+    pub fn record(&mut self, user: Address, referrer: Address, cspr_amount: U512, shares: U256) -> Address {
+        if referrer == user {
+            self.env().revert(ReferralError::SelfReferral);
+        }
+        let referrer = match self.referrer_of.get(&user) {
+            Some(existing) => existing,
+            None => {
+                self.referrer_of.set(&user, referrer);
+                self.referred_users.set(&referrer, self.referred_users.get(&referrer).unwrap_or(0) + 1);
+                referrer
+            }
+        };
+
+        self.settle(referrer);
+        self.user_shares.set(&user, self.user_shares.get(&user).unwrap_or_default() + shares);
+        self.referred_shares.set(&referrer, self.active_shares(referrer) + shares);
+        self.total_referred_shares.set(self.total_referred_shares() + shares);
+        self.referred_volume.set(&referrer, self.referred_volume(referrer) + cspr_amount);
+
+        self.env().emit_event(ReferredDeposit {
+            referrer,
+            user,
+            cspr_amount,
+            shares,
+            timestamp: self.env().get_block_time(),
+        });
+
+        referrer
+    }
+
+    /// Stop counting shares `user` burned towards their referrer's active stake
+    pub fn release(&mut self, user: Address, shares: U256) {
+        let referrer = match self.referrer_of.get(&user) {
+            Some(referrer) => referrer,
+            None => return,
+        };
+        let held = self.user_shares.get(&user).unwrap_or_default();
+        let released = shares.min(held);
+        if released.is_zero() {
+            return;
+        }
+
+        self.settle(referrer);
+        self.user_shares.set(&user, held - released);
+        self.referred_shares.set(&referrer, self.active_shares(referrer) - released);
+        self.total_referred_shares.set(self.total_referred_shares() - released);
+    }
+
+    /// Cap `user`'s referred shares at their token `balance` after it dropped by a transfer
+    pub fn sync(&mut self, user: Address, balance: U256) {
+        let held = self.user_shares.get(&user).unwrap_or_default();
+        if held > balance {
+            self.release(user, held - balance);
+        }
+    }
+
+    /// Set aside the referrers' cut of `fee_shares`, given the share supply they were minted into
+    /// The cut is the fee share times the referred part of the supply; returns it
+    pub fn accrue(&mut self, fee_shares: U256, total_shares: U256) -> U256 {
+        let referred = self.total_referred_shares();
+        let fee_share_bps = self.fee_share_bps();
+        if fee_shares.is_zero() || referred.is_zero() || fee_share_bps == 0 || total_shares.is_zero() {
+            return U256::zero();
+        }
+
+        let cut = math::mul_div(
+            fee_shares,
+            U256::from(fee_share_bps) * referred.min(total_shares),
+            U256::from(math::BPS_DENOMINATOR) * total_shares,
+            Rounding::Down,
+        ).unwrap_or_revert(&self.env());
+        if cut.is_zero() {
+            return U256::zero();
+        }
+
+        let per_share = math::mul_div(cut, U256::from(FEE_INDEX_SCALE), referred, Rounding::Down)
+            .unwrap_or_revert(&self.env());
+        self.fee_index.set(self.fee_index.get_or_default() + per_share);
+
+        self.env().emit_event(ReferralFeesAccrued {
+            stcspr_amount: cut,
+            referred_shares: referred,
+            timestamp: self.env().get_block_time(),
+        });
+
+        cut
+    }
+
+    /// Take everything `referrer` is owed; the parent pays it out
+    pub fn claim(&mut self, referrer: Address) -> U256 {
+        self.settle(referrer);
+        let owed = self.owed_fees.get(&referrer).unwrap_or_default();
+        if owed.is_zero() {
+            self.env().revert(ReferralError::NothingToClaim);
+        }
+        self.owed_fees.set(&referrer, U256::zero());
+
+        self.env().emit_event(ReferralFeesClaimed {
+            referrer,
+            stcspr_amount: owed,
+            timestamp: self.env().get_block_time(),
+        });
+
+        owed
+    }
+
+    /// Set the share of fees paid to referrers (bps)
+    pub fn set_fee_share(&mut self, fee_share_bps: u32) {
+        self.fee_share_bps.set(fee_share_bps);
+    }
+
+    // ===== VIEW FUNCTIONS =====
+
+    /// Get the referrer a user is credited to, if any
+    pub fn referrer_of(&self, user: Address) -> Option<Address> {
+        self.referrer_of.get(&user)
+    }
+
+    /// Get CSPR deposited through a referrer, all time
+    pub fn referred_volume(&self, referrer: Address) -> U512 {
+        self.referred_volume.get(&referrer).unwrap_or_default()
+    }
+
+    /// Get shares referred users still hold from a referrer's deposits
+    pub fn active_shares(&self, referrer: Address) -> U256 {
+        self.referred_shares.get(&referrer).unwrap_or_default()
+    }
+
+    /// Get active shares across all referrers
+    pub fn total_referred_shares(&self) -> U256 {
+        self.total_referred_shares.get_or_default()
+    }
+
+    /// Get the share of fees paid to referrers (bps)
+    pub fn fee_share_bps(&self) -> u32 {
+        self.fee_share_bps.get_or_default()
+    }
+
+    /// Get fee shares a referrer can claim
+    pub fn claimable(&self, referrer: Address) -> U256 {
+        self.owed_fees.get(&referrer).unwrap_or_default() + self.unsettled(referrer)
+    }
+
+    /// Get a referrer's activity and earnings
+    pub fn stats(&self, referrer: Address) -> ReferrerStats {
+        ReferrerStats {
+            referred_volume: self.referred_volume(referrer),
+            referred_users: self.referred_users.get(&referrer).unwrap_or(0),
+            active_shares: self.active_shares(referrer),
+            claimable: self.claimable(referrer),
+        }
+    }
+
+    // ===== INTERNAL =====
+
+    /// Fees earned by a referrer's active shares since they were last settled
+    fn unsettled(&self, referrer: Address) -> U256 {
+        let index = self.fee_index.get_or_default();
+        let last = self.referrer_index.get(&referrer).unwrap_or_default();
+        math::mul_div(self.active_shares(referrer), index - last, U256::from(FEE_INDEX_SCALE), Rounding::Down)
+            .unwrap_or_revert(&self.env())
+    }
+
+    /// Book a referrer's unsettled fees before their active shares change
+    fn settle(&mut self, referrer: Address) {
+        let earned = self.unsettled(referrer);
+        if !earned.is_zero() {
+            self.owed_fees.set(&referrer, self.owed_fees.get(&referrer).unwrap_or_default() + earned);
+        }
+        self.referrer_index.set(&referrer, self.fee_index.get_or_default());
+    }
+}
+
+// ===== ERRORS =====
+
+#[odra::odra_error]
+pub enum ReferralError {
+    SelfReferral = 1200,
+    NothingToClaim = 1201,
+}
+
+// ===== EVENTS =====
+
+#[odra::event]
+pub struct ReferredDeposit {
+    pub referrer: Address,
+    pub user: Address,
+    pub cspr_amount: U512,
+    pub shares: U256,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct ReferralFeesAccrued {
+    pub stcspr_amount: U256,
+    pub referred_shares: U256,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct ReferralFeesClaimed {
+    pub referrer: Address,
+    pub stcspr_amount: U256,
+    pub timestamp: u64,
+}
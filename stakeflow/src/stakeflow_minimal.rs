@@ -1,34 +1,38 @@
 use odra::prelude::*;
-use odra::casper_types::U512;
+use odra::casper_types::{U512, U256};
+use crate::access_control::{AccessControl, Role};
+use odra::ContractRef;
+use crate::migration::MigrationTargetContractRef;
 
 /// Minimal StakeFlow contract optimized for low gas deployment
 #[odra::module]
 pub struct StakeFlowMinimal {
     total_deposits: Var<U512>,
     user_deposits: Mapping<Address, U512>,
-    contract_owner: Var<Address>,
+    access: SubModule<AccessControl>,
+    migration_target: Var<Address>,
 }
 
 #[odra::module]
 impl StakeFlowMinimal {
     pub fn init(&mut self) {
+        let caller = self.env().caller();
         self.total_deposits.set(U512::zero());
-        self.contract_owner.set(self.env().caller());
+        self.access.init_owner(caller);
+        self.access.setup_role(Role::Operator, caller);
     }
 
     /// Deposit CSPR and receive stCSPR (1:1 ratio for minimal version)
     #[odra(payable)]
     pub fn deposit(&mut self) {
         let caller = self.env().caller();
-        let amount = self.env().attached_value();
-        
-        assert!(amount > U512::zero(), "Amount must be greater than zero");
-        
-        let current = self.user_deposits.get(&caller).unwrap_or(U512::zero());
-        self.user_deposits.set(&caller, current + amount);
-        
-        let total = self.total_deposits.get_or_default();
-        self.total_deposits.set(total + amount);
+        self.credit_deposit(caller);
+    }
+
+    /// Deposit CSPR credited to `beneficiary`
+    #[odra(payable)]
+    pub fn deposit_for(&mut self, beneficiary: Address) {
+        self.credit_deposit(beneficiary);
     }
 
     /// Withdraw CSPR by burning stCSPR
@@ -36,7 +40,9 @@ impl StakeFlowMinimal {
         let caller = self.env().caller();
         let balance = self.user_deposits.get(&caller).unwrap_or(U512::zero());
         
-        assert!(balance >= amount, "Insufficient balance");
+        if balance < amount {
+            self.env().revert(MinimalError::InsufficientBalance);
+        }
         
         self.user_deposits.set(&caller, balance - amount);
         
@@ -46,6 +52,28 @@ impl StakeFlowMinimal {
         self.env().transfer_tokens(&caller, &amount);
     }
 
+    /// Set the V3 vault deposits migrate to (admin only)
+    pub fn set_migration_target(&mut self, vault: Address) {
+        self.access.check_role(Role::Admin, self.env().caller());
+        self.migration_target.set(vault);
+    }
+
+    /// Move the caller's deposit into stCSPR in the V3 vault
+    pub fn migrate_to_v3(&mut self) -> U256 {
+        let caller = self.env().caller();
+        self.migrate_position(caller)
+    }
+
+    /// Migrate a batch of users' deposits (operator only); empty positions are skipped
+    pub fn migrate_batch_to_v3(&mut self, users: Vec<Address>) {
+        self.access.check_role(Role::Operator, self.env().caller());
+        for user in users {
+            if !self.get_balance(user).is_zero() {
+                self.migrate_position(user);
+            }
+        }
+    }
+
     /// Get user's stCSPR balance
     pub fn get_balance(&self, user: Address) -> U512 {
         self.user_deposits.get(&user).unwrap_or(U512::zero())
@@ -55,6 +83,78 @@ impl StakeFlowMinimal {
     pub fn get_total_deposits(&self) -> U512 {
         self.total_deposits.get_or_default()
     }
+
+    delegate! {
+        to self.access {
+            fn grant_role(&mut self, role: Role, account: Address);
+            fn revoke_role(&mut self, role: Role, account: Address);
+            fn renounce_role(&mut self, role: Role, account: Address);
+            fn set_role_admin(&mut self, role: Role, admin_role: Role);
+            fn has_role(&self, role: Role, account: Address) -> bool;
+            fn transfer_ownership(&mut self, new_owner: Address);
+            fn accept_ownership(&mut self);
+            fn get_owner(&self) -> Address;
+            fn get_pending_owner(&self) -> Option<Address>;
+            fn get_role_admin(&self, role: Role) -> Role;
+        }
+    }
+
+    fn credit_deposit(&mut self, user: Address) {
+        let amount = self.env().attached_value();
+        if amount.is_zero() {
+            self.env().revert(MinimalError::ZeroAmount);
+        }
+
+        let current = self.user_deposits.get(&user).unwrap_or(U512::zero());
+        self.user_deposits.set(&user, current + amount);
+
+        let total = self.total_deposits.get_or_default();
+        self.total_deposits.set(total + amount);
+    }
+
+    fn migrate_position(&mut self, user: Address) -> U256 {
+        let vault = self.migration_target.get()
+            .unwrap_or_revert_with(&self.env(), MinimalError::MigrationTargetNotSet);
+        let amount = self.user_deposits.get(&user).unwrap_or(U512::zero());
+        if amount.is_zero() {
+            self.env().revert(MinimalError::NothingToMigrate);
+        }
+
+        self.user_deposits.set(&user, U512::zero());
+        let total = self.total_deposits.get_or_default();
+        self.total_deposits.set(total - amount);
+
+        let stcspr_amount = MigrationTargetContractRef::new(self.env(), vault)
+            .with_tokens(amount)
+            .migrate_deposit(user);
+
+        self.env().emit_event(MigratedToV3 {
+            user,
+            vault,
+            cspr_amount: amount,
+            stcspr_amount,
+            timestamp: self.env().get_block_time(),
+        });
+
+        stcspr_amount
+    }
+}
+
+#[odra::odra_error]
+pub enum MinimalError {
+    ZeroAmount = 4000,
+    InsufficientBalance = 4001,
+    MigrationTargetNotSet = 4002,
+    NothingToMigrate = 4003,
+}
+
+#[odra::event]
+pub struct MigratedToV3 {
+    pub user: Address,
+    pub vault: Address,
+    pub cspr_amount: U512,
+    pub stcspr_amount: U256,
+    pub timestamp: u64,
 }
 
 #[cfg(test)]
@@ -73,4 +173,30 @@ mod tests {
         
         assert_eq!(contract.get_total_deposits(), amount);
     }
+
+    #[test]
+    fn test_minimal_deposit_for() {
+        let env = odra_test::env();
+        use odra::host::NoArgs;
+        let contract = StakeFlowMinimal::deploy(&env, NoArgs);
+        let beneficiary = env.get_account(1);
+
+        contract.with_tokens(U512::from(1000)).deposit_for(beneficiary);
+        assert_eq!(contract.get_balance(beneficiary), U512::from(1000));
+        assert_eq!(contract.get_balance(env.get_account(0)), U512::zero());
+    }
+
+    #[test]
+    fn test_minimal_withdraw_insufficient_balance() {
+        let env = odra_test::env();
+        use odra::host::NoArgs;
+        let mut contract = StakeFlowMinimal::deploy(&env, NoArgs);
+
+        contract.with_tokens(U512::from(1000)).deposit();
+        assert_eq!(
+            contract.try_withdraw(U512::from(1001)),
+            Err(MinimalError::InsufficientBalance.into())
+        );
+        assert_eq!(contract.get_total_deposits(), U512::from(1000));
+    }
 }
@@ -1,5 +1,13 @@
 use odra::prelude::*;
 use odra::casper_types::U512;
+use odra::casper_types::U256;
+use odra::casper_types::PublicKey;
+use odra::casper_types::bytesrepr::Bytes;
+use crate::access_control::{AccessControl, Role};
+use crate::deposit_limits::DepositGuard;
+use crate::math::{self, Rounding};
+use odra::ContractRef;
+use crate::migration::MigrationTargetContractRef;
 
 /// StakeFlow Vault - Advanced Liquid Staking with Auto-Rebalancing
 ///
@@ -20,8 +28,8 @@ pub struct StakeFlowVault {
     user_shares: Mapping<Address, U512>,
     /// Total shares issued
     total_shares: Var<U512>,
-    /// Contract owner
-    owner: Var<Address>,
+    /// Role-based permissions
+    access: SubModule<AccessControl>,
     /// Vault active status
     is_active: Var<bool>,
     /// Active validators: List of validators we're staking with
@@ -38,6 +46,10 @@ pub struct StakeFlowVault {
     cross_chain_deposits: Mapping<String, CrossChainDeposit>,
     /// TVL per source chain
     chain_tvl: Mapping<String, U512>,
+    /// V3 vault positions migrate to
+    migration_target: Var<Address>,
+    /// Minimum deposit, TVL and per-account caps, allowlist
+    deposit_guard: SubModule<DepositGuard>,
 }
 
 /// Validator performance information
@@ -76,7 +88,10 @@ impl StakeFlowVault {
     /// Initialize the StakeFlow Vault
     pub fn init(&mut self) {
         let caller = self.env().caller();
-        self.owner.set(caller);
+        self.access.init_owner(caller);
+        self.access.setup_role(Role::Operator, caller);
+        self.access.setup_role(Role::Guardian, caller);
+        self.access.setup_role(Role::Relayer, caller);
         self.total_deposits.set(U512::zero());
         self.total_staked.set(U512::zero());
         self.total_shares.set(U512::zero());
@@ -95,32 +110,22 @@ impl StakeFlowVault {
     /// Returns the number of shares minted to the depositor
     #[odra(payable)]
     pub fn deposit(&mut self) -> U512 {
-        self.assert_vault_active();
-
         let caller = self.env().caller();
         let amount = self.env().attached_value();
+        self.deposit_from(caller, amount)
+    }
 
-        // Require minimum deposit of 10 CSPR
-        let min_deposit = U512::from(10_000_000_000u64); // 10 CSPR in motes
-        assert!(amount >= min_deposit, "Minimum deposit is 10 CSPR");
-
-        // Calculate shares to mint
-        let shares = self.calculate_shares_for_deposit(amount);
-
-        // Update user deposits
-        let current_deposit = self.user_deposits.get(&caller).unwrap_or(U512::zero());
-        self.user_deposits.set(&caller, current_deposit + amount);
-
-        // Update user shares
-        let current_shares = self.user_shares.get(&caller).unwrap_or(U512::zero());
-        self.user_shares.set(&caller, current_shares + shares);
-
-        // Update totals
-        self.total_deposits.set(self.total_deposits.get_or_default() + amount);
-        self.total_shares.set(self.total_shares.get_or_default() + shares);
+    /// Deposit CSPR and credit the shares to `beneficiary`
+    /// Limits and the allowlist apply to the beneficiary
+    #[odra(payable)]
+    pub fn deposit_for(&mut self, beneficiary: Address) -> U512 {
+        let depositor = self.env().caller();
+        let amount = self.env().attached_value();
+        let shares = self.deposit_from(beneficiary, amount);
 
-        self.env().emit_event(Deposited {
-            user: caller,
+        self.env().emit_event(DepositedFor {
+            depositor,
+            beneficiary,
             amount,
             shares,
             timestamp: self.env().get_block_time(),
@@ -136,8 +141,12 @@ impl StakeFlowVault {
         let caller = self.env().caller();
         let user_shares = self.user_shares.get(&caller).unwrap_or(U512::zero());
 
-        assert!(shares_to_burn > U512::zero(), "Must burn at least 1 share");
-        assert!(user_shares >= shares_to_burn, "Insufficient shares");
+        if shares_to_burn.is_zero() {
+            self.env().revert(StakeFlowVaultError::ZeroShares);
+        }
+        if user_shares < shares_to_burn {
+            self.env().revert(StakeFlowVaultError::InsufficientShares);
+        }
 
         // Calculate CSPR amount to return
         let cspr_amount = self.calculate_cspr_for_shares(shares_to_burn);
@@ -147,7 +156,9 @@ impl StakeFlowVault {
 
         // Update totals
         self.total_shares.set(self.total_shares.get_or_default() - shares_to_burn);
-        self.total_deposits.set(self.total_deposits.get_or_default() - cspr_amount);
+        let total_deposits = math::checked_sub(self.total_deposits.get_or_default(), cspr_amount)
+            .unwrap_or_revert(&self.env());
+        self.total_deposits.set(total_deposits);
 
         // Transfer CSPR to user
         self.env().transfer_tokens(&caller, &cspr_amount);
@@ -160,6 +171,39 @@ impl StakeFlowVault {
         });
     }
 
+    // ===== MIGRATION TO V3 =====
+
+    /// Set the V3 vault positions migrate to (admin only)
+    pub fn set_migration_target(&mut self, vault: Address) {
+        self.assert_role(Role::Admin);
+        self.migration_target.set(vault);
+    }
+
+    /// Redeem the caller's shares into stCSPR in the V3 vault
+    /// Returns stCSPR minted
+    pub fn migrate_to_v3(&mut self) -> U256 {
+        self.assert_vault_active();
+        let caller = self.env().caller();
+        self.migrate_position(caller)
+    }
+
+    /// Migrate a batch of users' positions (operator only)
+    /// Users without shares are skipped
+    pub fn migrate_batch_to_v3(&mut self, users: Vec<Address>) {
+        self.assert_vault_active();
+        self.assert_role(Role::Operator);
+        for user in users {
+            if !self.get_user_shares(user).is_zero() {
+                self.migrate_position(user);
+            }
+        }
+    }
+
+    /// Get V3 vault positions migrate to
+    pub fn get_migration_target(&self) -> Option<Address> {
+        self.migration_target.get()
+    }
+
     /// Get user's share balance
     pub fn get_user_shares(&self, user: Address) -> U512 {
         self.user_shares.get(&user).unwrap_or(U512::zero())
@@ -200,21 +244,124 @@ impl StakeFlowVault {
         self.is_active.get_or_default()
     }
 
+    // ===== PREVIEWS (ERC-4626 STYLE) =====
+
+    /// Shares a `deposit` of `amount` would mint right now
+    pub fn preview_deposit(&self, amount: U512) -> U512 {
+        if amount < self.deposit_guard.min_deposit() {
+            self.env().revert(StakeFlowVaultError::BelowMinimumDeposit);
+        }
+        self.calculate_shares_for_deposit(amount)
+    }
+
+    /// CSPR a `withdraw` of `shares` would pay out right now
+    pub fn preview_withdraw(&self, shares: U512) -> U512 {
+        if shares.is_zero() {
+            self.env().revert(StakeFlowVaultError::ZeroShares);
+        }
+        self.calculate_cspr_for_shares(shares)
+    }
+
+    /// Shares `amount` is worth at the current share price, ignoring limits
+    pub fn convert_to_shares(&self, amount: U512) -> U512 {
+        math::assets_to_shares_u512(
+            amount,
+            self.total_deposits.get_or_default(),
+            self.total_shares.get_or_default(),
+            Rounding::Down,
+        ).unwrap_or_revert(&self.env())
+    }
+
+    /// CSPR `shares` are worth at the current share price, ignoring limits
+    pub fn convert_to_assets(&self, shares: U512) -> U512 {
+        self.calculate_cspr_for_shares(shares)
+    }
+
+    /// Most CSPR `user` can deposit under the caps and allowlist; zero while the vault is paused
+    pub fn max_deposit(&self, user: Address) -> U512 {
+        if !self.is_active.get_or_default() {
+            return U512::zero();
+        }
+        self.deposit_guard.remaining(user, self.get_tvl(), self.get_user_value(user))
+    }
+
+    /// Most CSPR `owner` can withdraw; zero while the vault is paused
+    pub fn max_withdraw(&self, owner: Address) -> U512 {
+        if !self.is_active.get_or_default() {
+            return U512::zero();
+        }
+        self.get_user_value(owner)
+    }
+
+    // ===== DEPOSIT LIMITS =====
+
+    /// Join the allowlist with an attestation signed by the attestation signer
+    pub fn allowlist_with_attestation(&mut self, signature: Bytes) {
+        let caller = self.env().caller();
+        self.deposit_guard.attest(caller, signature);
+    }
+
+    /// Set the minimum deposit and the TVL and per-account caps (admin only)
+    pub fn set_deposit_limits(&mut self, min_deposit: U512, tvl_cap: Option<U512>, account_cap: Option<U512>) {
+        self.assert_role(Role::Admin);
+        self.deposit_guard.set_limits(min_deposit, tvl_cap, account_cap);
+    }
+
+    /// Turn allowlist mode on or off (admin only)
+    pub fn set_allowlist_enabled(&mut self, enabled: bool) {
+        self.assert_role(Role::Admin);
+        self.deposit_guard.set_allowlist_enabled(enabled);
+    }
+
+    /// Add or remove an account from the allowlist (admin only)
+    pub fn set_allowlisted(&mut self, account: Address, allowed: bool) {
+        self.assert_role(Role::Admin);
+        self.deposit_guard.set_allowlisted(account, allowed);
+    }
+
+    /// Set or clear the key whose attestations allowlist accounts (admin only)
+    pub fn set_attestation_signer(&mut self, signer: Option<PublicKey>) {
+        self.assert_role(Role::Admin);
+        self.deposit_guard.set_attestation_signer(signer);
+    }
+
+    /// Get the smallest accepted deposit
+    pub fn get_min_deposit(&self) -> U512 {
+        self.deposit_guard.min_deposit()
+    }
+
+    /// Check if an account is allowlisted
+    pub fn is_allowlisted(&self, account: Address) -> bool {
+        self.deposit_guard.is_allowlisted(account)
+    }
+
+    /// Get the epoch attestations for `account` are signed for; revoking the account starts a new one
+    pub fn get_attestation_epoch(&self, account: Address) -> u64 {
+        self.deposit_guard.attestation_epoch(account)
+    }
+
+    /// Get the message the attestation signer signs to allowlist `account` in its current epoch
+    pub fn get_attestation_message(&self, account: Address) -> Bytes {
+        self.deposit_guard.attestation_message(account)
+    }
+
     // ===== LIQUID STAKING FUNCTIONS =====
 
     /// Stake deposited CSPR to validators
-    /// Owner can trigger staking of idle funds
+    /// Operator can trigger staking of idle funds
     pub fn stake_to_validators(&mut self, amount: U512) {
-        self.assert_owner();
+        self.assert_role(Role::Operator);
         
-        let unstaked = self.total_deposits.get_or_default() - self.total_staked.get_or_default();
-        assert!(amount <= unstaked, "Insufficient unstaked funds");
+        let unstaked = self.total_deposits.get_or_default().saturating_sub(self.total_staked.get_or_default());
+        if amount > unstaked {
+            self.env().revert(StakeFlowVaultError::InsufficientUnstakedFunds);
+        }
         
         // Get best validators for diversification
         let validators = self.get_top_validators_for_staking();
         
         if validators.is_empty() {
-            panic!("No validators available");
+            self.env().revert(StakeFlowVaultError::NoValidatorsAvailable);
         }
         
         // Distribute stake among validators
@@ -233,26 +380,6 @@ impl StakeFlowVault {
         });
     }
 
-    /// Claim staking rewards from validators
-    pub fn claim_staking_rewards(&mut self) {
-        self.assert_owner();
-        
-        // Simulate claiming rewards (in production, this would call Casper auction contract)
-        let time_elapsed = self.env().get_block_time() - self.last_reward_claim.get_or_default();
-        let rewards = self.calculate_rewards(time_elapsed);
-        
-        if rewards > U512::zero() {
-            self.total_rewards.set(self.total_rewards.get_or_default() + rewards);
-            self.total_deposits.set(self.total_deposits.get_or_default() + rewards);
-            self.last_reward_claim.set(self.env().get_block_time());
-            
-            self.env().emit_event(RewardsCompounded {
-                amount: rewards,
-                timestamp: self.env().get_block_time(),
-            });
-        }
-    }
-
     /// Get total staked amount
     pub fn get_total_staked(&self) -> U512 {
         self.total_staked.get_or_default()
@@ -272,7 +399,7 @@ impl StakeFlowVault {
 
     /// Add a validator to the active set
     pub fn add_validator(&mut self, validator: Address, initial_score: u32) {
-        self.assert_owner();
+        self.assert_role(Role::Admin);
         
         let validator_info = ValidatorInfo {
             address: validator,
@@ -294,7 +421,7 @@ impl StakeFlowVault {
 
     /// Update validator performance score
     pub fn update_validator_score(&mut self, validator: Address, new_score: u32, uptime: u32) {
-        self.assert_owner();
+        self.assert_role(Role::Operator);
         
         if let Some(mut info) = self.active_validators.get(&validator) {
             info.performance_score = new_score;
@@ -313,7 +440,7 @@ impl StakeFlowVault {
 
     /// Rebalance stakes based on validator performance
     pub fn rebalance_validators(&mut self) {
-        self.assert_owner();
+        self.assert_role(Role::Operator);
         
         // Check all validators and rebalance if needed
         // In production, this would undelegate from low-performers
@@ -337,7 +464,7 @@ impl StakeFlowVault {
     // ===== CROSS-CHAIN INFRASTRUCTURE =====
 
     /// Register a cross-chain deposit
-    /// Owner/relayer registers deposits from other chains
+    /// Relayer registers deposits from other chains
     pub fn register_cross_chain_deposit(
         &mut self,
         source_chain: String,
@@ -345,8 +472,10 @@ impl StakeFlowVault {
         user: Address,
         amount: U512,
     ) {
-        self.assert_owner();
-        
+        self.assert_role(Role::Relayer);
+        // Bridged deposits count against the caps like local ones
+        self.deposit_guard.check_caps(amount, self.get_tvl(), self.get_user_value(user));
+
         let deposit = CrossChainDeposit {
             source_chain: source_chain.clone(),
             source_tx: source_tx.clone(),
@@ -425,9 +554,9 @@ impl StakeFlowVault {
         diversification_score + uptime_score + maturity_score
     }
 
-    /// Emergency pause (owner only)
+    /// Emergency pause (guardian only)
     pub fn pause(&mut self) {
-        self.assert_owner();
+        self.assert_role(Role::Guardian);
         self.is_active.set(false);
 
         self.env().emit_event(VaultPaused {
@@ -435,9 +564,9 @@ impl StakeFlowVault {
         });
     }
 
-    /// Unpause (owner only)
+    /// Unpause (guardian only)
     pub fn unpause(&mut self) {
-        self.assert_owner();
+        self.assert_role(Role::Guardian);
         self.is_active.set(true);
 
         self.env().emit_event(VaultUnpaused {
@@ -445,33 +574,118 @@ impl StakeFlowVault {
         });
     }
 
+    // ===== ACCESS CONTROL =====
+
+    delegate! {
+        to self.access {
+            fn grant_role(&mut self, role: Role, account: Address);
+            fn revoke_role(&mut self, role: Role, account: Address);
+            fn renounce_role(&mut self, role: Role, account: Address);
+            fn set_role_admin(&mut self, role: Role, admin_role: Role);
+            fn has_role(&self, role: Role, account: Address) -> bool;
+            fn transfer_ownership(&mut self, new_owner: Address);
+            fn accept_ownership(&mut self);
+            fn get_owner(&self) -> Address;
+            fn get_pending_owner(&self) -> Option<Address>;
+            fn get_role_admin(&self, role: Role) -> Role;
+        }
+    }
+
     // ===== INTERNAL FUNCTIONS =====
 
+    /// Take a deposit of `amount` and credit the shares to `user`
+    fn deposit_from(&mut self, user: Address, amount: U512) -> U512 {
+        self.assert_vault_active();
+
+        // Require the minimum deposit (10 CSPR unless configured)
+        if amount < self.deposit_guard.min_deposit() {
+            self.env().revert(StakeFlowVaultError::BelowMinimumDeposit);
+        }
+        self.deposit_guard.check(user, amount, self.get_tvl(), self.get_user_value(user));
+
+        // Calculate shares to mint
+        let shares = self.calculate_shares_for_deposit(amount);
+
+        // Update user deposits
+        let current_deposit = self.user_deposits.get(&user).unwrap_or(U512::zero());
+        self.user_deposits.set(&user, current_deposit + amount);
+
+        // Update user shares
+        let current_shares = self.user_shares.get(&user).unwrap_or(U512::zero());
+        self.user_shares.set(&user, current_shares + shares);
+
+        // Update totals
+        self.total_deposits.set(self.total_deposits.get_or_default() + amount);
+        self.total_shares.set(self.total_shares.get_or_default() + shares);
+
+        self.env().emit_event(Deposited {
+            user,
+            amount,
+            shares,
+            timestamp: self.env().get_block_time(),
+        });
+
+        shares
+    }
+
     /// Calculate shares for a deposit amount
-    /// If first deposit, 1:1 ratio, otherwise proportional to existing shares
+    /// Priced with virtual shares so a seeded or donated pool cannot round deposits to zero
     fn calculate_shares_for_deposit(&self, amount: U512) -> U512 {
-        let total_shares = self.total_shares.get_or_default();
-        let total_deposits = self.total_deposits.get_or_default();
-
-        if total_shares == U512::zero() || total_deposits == U512::zero() {
-            // First deposit: 1:1 ratio
-            amount
-        } else {
-            // Proportional to existing shares
-            (amount * total_shares) / total_deposits
+        let shares = math::assets_to_shares_u512(
+            amount,
+            self.total_deposits.get_or_default(),
+            self.total_shares.get_or_default(),
+            Rounding::Down,
+        ).unwrap_or_revert(&self.env());
+        if shares.is_zero() {
+            self.env().revert(StakeFlowVaultError::ZeroShares);
         }
+        shares
     }
 
-    /// Calculate CSPR amount for shares
+    /// Calculate CSPR amount for shares, rounded in favour of the vault
     fn calculate_cspr_for_shares(&self, shares: U512) -> U512 {
-        let total_shares = self.total_shares.get_or_default();
-        let total_deposits = self.total_deposits.get_or_default();
+        math::shares_to_assets_u512(
+            shares,
+            self.total_deposits.get_or_default(),
+            self.total_shares.get_or_default(),
+            Rounding::Down,
+        ).unwrap_or_revert(&self.env())
+    }
 
-        if total_shares == U512::zero() {
-            U512::zero()
-        } else {
-            (shares * total_deposits) / total_shares
+    /// Burn a user's shares and deposit their CSPR value into the V3 vault for them
+    fn migrate_position(&mut self, user: Address) -> U256 {
+        let vault = self.migration_target.get()
+            .unwrap_or_revert_with(&self.env(), StakeFlowVaultError::MigrationTargetNotSet);
+        let shares = self.user_shares.get(&user).unwrap_or(U512::zero());
+        if shares.is_zero() {
+            self.env().revert(StakeFlowVaultError::NothingToMigrate);
         }
+
+        let cspr_amount = self.calculate_cspr_for_shares(shares);
+
+        // Clear the legacy position
+        self.user_shares.set(&user, U512::zero());
+        self.user_deposits.set(&user, U512::zero());
+        self.total_shares.set(self.total_shares.get_or_default() - shares);
+        let total_deposits = math::checked_sub(self.total_deposits.get_or_default(), cspr_amount)
+            .unwrap_or_revert(&self.env());
+        self.total_deposits.set(total_deposits);
+
+        let stcspr_amount = MigrationTargetContractRef::new(self.env(), vault)
+            .with_tokens(cspr_amount)
+            .migrate_deposit(user);
+
+        self.env().emit_event(MigratedToV3 {
+            user,
+            vault,
+            cspr_amount,
+            shares_burned: shares,
+            stcspr_amount,
+            timestamp: self.env().get_block_time(),
+        });
+
+        stcspr_amount
     }
 
     /// Delegate CSPR to a validator
@@ -495,24 +709,6 @@ impl StakeFlowVault {
         vec![]
     }
 
-    /// Calculate rewards based on time elapsed
-    fn calculate_rewards(&self, time_elapsed: u64) -> U512 {
-        // APY of ~9.5% on staked amount
-        // Formula: (staked * apy * time) / (seconds_per_year * 100)
-        let staked = self.total_staked.get_or_default();
-        let apy = 950u64; // 9.5%
-        let seconds_per_year = 31536000u64;
-        
-        if staked == U512::zero() || time_elapsed == 0 {
-            return U512::zero();
-        }
-        
-        let rewards = (staked * U512::from(apy) * U512::from(time_elapsed))
-            / (U512::from(seconds_per_year) * U512::from(10000u64));
-        
-        rewards
-    }
-
     /// Calculate performance bonus for APY
     fn calculate_performance_bonus(&self) -> U512 {
         // Bonus APY based on validator performance
@@ -520,24 +716,45 @@ impl StakeFlowVault {
         U512::from(50u64) // +0.5% bonus
     }
 
-    /// Assert caller is owner
-    fn assert_owner(&self) {
-        let caller = self.env().caller();
-        if let Some(owner) = self.owner.get() {
-            assert!(caller == owner, "Only owner can call this function");
-        } else {
-            panic!("Owner not set");
-        }
+    /// Assert caller holds a role
+    fn assert_role(&self, role: Role) {
+        self.access.check_role(role, self.env().caller());
     }
 
     /// Assert vault is active
     fn assert_vault_active(&self) {
-        assert!(self.is_active.get_or_default(), "Vault is paused");
+        if !self.is_active.get_or_default() {
+            self.env().revert(StakeFlowVaultError::VaultPaused);
+        }
     }
 }
 
+// ===== ERRORS =====
+
+#[odra::odra_error]
+pub enum StakeFlowVaultError {
+    BelowMinimumDeposit = 3000,
+    ZeroShares = 3001,
+    InsufficientShares = 3002,
+    InsufficientUnstakedFunds = 3003,
+    NoValidatorsAvailable = 3004,
+    MigrationTargetNotSet = 3005,
+    NothingToMigrate = 3006,
+    VaultPaused = 3007,
+}
+
 // ===== EVENTS =====
 
+#[odra::event]
+pub struct MigratedToV3 {
+    pub user: Address,
+    pub vault: Address,
+    pub cspr_amount: U512,
+    pub shares_burned: U512,
+    pub stcspr_amount: U256,
+    pub timestamp: u64,
+}
+
 #[odra::event]
 pub struct VaultInitialized {
     pub owner: Address,
@@ -552,6 +769,15 @@ pub struct Deposited {
     pub timestamp: u64,
 }
 
+#[odra::event]
+pub struct DepositedFor {
+    pub depositor: Address,
+    pub beneficiary: Address,
+    pub amount: U512,
+    pub shares: U512,
+    pub timestamp: u64,
+}
+
 #[odra::event]
 pub struct Withdrawn {
     pub user: Address,
@@ -577,12 +803,6 @@ pub struct StakingInitiated {
     pub timestamp: u64,
 }
 
-#[odra::event]
-pub struct RewardsCompounded {
-    pub amount: U512,
-    pub timestamp: u64,
-}
-
 #[odra::event]
 pub struct ValidatorAdded {
     pub validator: Address,
@@ -617,6 +837,7 @@ pub struct CrossChainDepositProcessed {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::deposit_limits::DepositLimitError;
     use odra::host::{Deployer, HostRef, NoArgs};
 
     #[test]
@@ -663,6 +884,59 @@ mod tests {
         assert_eq!(contract.get_user_shares(env.get_account(1)), half_shares);
     }
 
+    #[test]
+    fn test_deposit_for_credits_beneficiary() {
+        let env = odra_test::env();
+        let contract = StakeFlowVault::deploy(&env, NoArgs);
+        let depositor = env.get_account(1);
+        let beneficiary = env.get_account(2);
+        let deposit_amount = U512::from(100_000_000_000u64); // 100 CSPR
+
+        env.set_caller(depositor);
+        let shares = contract.with_tokens(deposit_amount).deposit_for(beneficiary);
+        assert_eq!(contract.get_user_shares(beneficiary), shares);
+        assert_eq!(contract.get_user_deposit(beneficiary), deposit_amount);
+        assert_eq!(contract.get_user_shares(depositor), U512::zero());
+        assert!(env.emitted_event(&contract, DepositedFor {
+            depositor,
+            beneficiary,
+            amount: deposit_amount,
+            shares,
+            timestamp: env.block_time(),
+        }));
+    }
+
+    #[test]
+    fn test_previews_match_deposit_and_withdraw() {
+        let env = odra_test::env();
+        let mut contract = StakeFlowVault::deploy(&env, NoArgs);
+        let user = env.get_account(1);
+
+        let deposit_amount = U512::from(100_000_000_000u64); // 100 CSPR
+        env.set_caller(user);
+        let preview = contract.preview_deposit(deposit_amount);
+        assert_eq!(contract.with_tokens(deposit_amount).deposit(), preview);
+        assert_eq!(contract.max_withdraw(user), contract.get_user_value(user));
+
+        let shares = preview / U512::from(3u64);
+        let expected = contract.preview_withdraw(shares);
+        assert_eq!(contract.convert_to_assets(shares), expected);
+        let balance_before = env.balance_of(&contract);
+        contract.withdraw(shares);
+        assert_eq!(balance_before - env.balance_of(&contract), expected);
+
+        assert_eq!(
+            contract.try_preview_deposit(U512::from(1_000_000_000u64)),
+            Err(StakeFlowVaultError::BelowMinimumDeposit.into())
+        );
+
+        // Limits drop to zero while paused
+        env.set_caller(env.get_account(0));
+        contract.pause();
+        assert_eq!(contract.max_deposit(user), U512::zero());
+        assert_eq!(contract.max_withdraw(user), U512::zero());
+    }
+
     #[test]
     fn test_validator_management() {
         let env = odra_test::env();
@@ -748,6 +1022,32 @@ mod tests {
         assert_eq!(contract.get_user_deposit(user), amount);
     }
 
+    #[test]
+    fn test_deposit_caps_cover_cross_chain_deposits() {
+        let env = odra_test::env();
+        let mut contract = StakeFlowVault::deploy(&env, NoArgs);
+        let (alice, bob) = (env.get_account(1), env.get_account(2));
+        let cspr = |n: u64| U512::from(n * 1_000_000_000);
+
+        contract.set_deposit_limits(cspr(10), Some(cspr(150)), Some(cspr(100)));
+        env.set_caller(alice);
+        contract.with_tokens(cspr(100)).deposit();
+        assert_eq!(contract.max_deposit(alice), U512::zero());
+
+        // The relayer's mint path is capped too
+        env.set_caller(env.get_account(0));
+        assert_eq!(
+            contract.try_register_cross_chain_deposit("ethereum".to_string(), "0x01".to_string(), alice, cspr(10)),
+            Err(DepositLimitError::AccountCapExceeded.into())
+        );
+        assert_eq!(
+            contract.try_register_cross_chain_deposit("ethereum".to_string(), "0x02".to_string(), bob, cspr(60)),
+            Err(DepositLimitError::TvlCapExceeded.into())
+        );
+        contract.register_cross_chain_deposit("ethereum".to_string(), "0x03".to_string(), bob, cspr(50));
+        assert_eq!(contract.get_tvl(), cspr(150));
+    }
+
     #[test]
     fn test_apy_calculation() {
         let env = odra_test::env();
@@ -757,4 +1057,51 @@ mod tests {
         assert!(apy >= U512::from(900u64)); // At least 9% APY
         assert!(apy <= U512::from(1200u64)); // Max 12% APY
     }
+
+    #[test]
+    fn test_relayer_role() {
+        let env = odra_test::env();
+        let mut contract = StakeFlowVault::deploy(&env, NoArgs);
+
+        let relayer = env.get_account(3);
+        let user = env.get_account(1);
+        contract.grant_role(Role::Relayer, relayer);
+
+        // A relayer registers deposits but cannot pause the vault
+        env.set_caller(relayer);
+        let amount = U512::from(50_000_000_000u64);
+        contract.register_cross_chain_deposit(
+            "ethereum".to_string(),
+            "0xdef456".to_string(),
+            user,
+            amount,
+        );
+        assert_eq!(contract.get_user_deposit(user), amount);
+        assert!(!contract.has_role(Role::Guardian, relayer));
+    }
+
+    #[test]
+    fn test_withdraw_reverts_with_typed_errors() {
+        let env = odra_test::env();
+        let mut contract = StakeFlowVault::deploy(&env, NoArgs);
+
+        env.set_caller(env.get_account(1));
+        let shares = contract.with_tokens(U512::from(100_000_000_000u64)).deposit();
+
+        assert_eq!(contract.try_withdraw(U512::zero()), Err(StakeFlowVaultError::ZeroShares.into()));
+        assert_eq!(
+            contract.try_withdraw(shares + U512::one()),
+            Err(StakeFlowVaultError::InsufficientShares.into())
+        );
+
+        // Pausing blocks deposits with its own error
+        env.set_caller(env.get_account(0));
+        contract.pause();
+        env.set_caller(env.get_account(1));
+        assert_eq!(
+            contract.with_tokens(U512::from(100_000_000_000u64)).try_deposit(),
+            Err(StakeFlowVaultError::VaultPaused.into())
+        );
+        assert_eq!(contract.get_user_shares(env.get_account(1)), shares);
+    }
 }
@@ -1,14 +1,55 @@
 use odra::prelude::*;
-use odra::casper_types::{U512, U256};
+use odra::ContractRef;
+use odra::casper_types::{PublicKey, U512, U256};
+use odra::casper_types::bytesrepr::{Bytes, FromBytes, ToBytes};
+use crate::access_control::{AccessControl, Role};
+use crate::delegation::{self, DelegationManager, EraConfig};
+use crate::deposit_limits::{self, DepositGuard};
+use crate::math::{self, Rounding};
+use crate::migration::{LegacyAttestation, LegacyKind, LegacyMigration};
+use crate::oracle::{self, EraReport, RewardOracle};
+use crate::permit::PermitNonces;
+use crate::rate_limits::{self, RateLimitError, RateLimits};
+use crate::referrals::{self, ReferralBook, ReferrerStats};
+use crate::stcspr_token::StCSPRTokenContractRef;
+use crate::timelock::{self, Timelock, TimelockOperation};
+use crate::withdrawal_queue::{QueuePosition, WithdrawalQueue, WithdrawalQueueError, WithdrawalRequest};
+
+/// Storage layout version of this code; `upgrade` migrates older layouts up to it
+pub const STORAGE_VERSION: u32 = 3;
+
+/// Smallest accepted deposit until an admin sets one: 10 CSPR in motes
+pub const MIN_DEPOSIT: u64 = deposit_limits::DEFAULT_MIN_DEPOSIT;
+
+/// Highest annual management fee: 2%
+pub const MAX_MANAGEMENT_FEE_BPS: u32 = 200;
+
+/// Most recipients a fee can be split between
+pub const MAX_FEE_RECIPIENTS: usize = 8;
 
 /// StakeFlow Unified Liquid Staking Vault
 ///
 /// Complete liquid staking solution combining:
 /// - CSPR vault management
-/// - stCSPR liquid staking token (CEP-18 compatible)
-/// - Withdrawal queue with time-locks (unbonding period)
+/// - Minting and burning of the standalone stCSPR token (CEP-18), which holds
+///   balances, allowances and the exchange rate
+/// - FIFO withdrawal queue with time-locks (unbonding period) and partial fulfilment
+/// - Slashing losses shared pro rata between stakers and withdrawals still unbonding
+/// - Transferable withdrawal tickets (CEP-78-like claim receipts), approvable by signed permit
+/// - Instant unstake from an idle-CSPR buffer with a dynamic fee
+/// - Native delegation to validators through the Casper system auction
 /// - Performance-based validator selection
-/// - Auto-compounding rewards
+/// - Auto-compounding rewards from quorum-finalized oracle era reports
+/// - Performance and continuous management fees, minted as stCSPR and split between
+///   weighted recipients
+/// - Rate-of-change limits that hold out-of-bounds reports for guardian review
+/// - Deposit caps, a configurable minimum and an allowlist mode for guarded launches
+/// - Deposits and withdrawal requests on behalf of other accounts (custodians, smart wallets)
+/// - Referral-tagged deposits; referrers earn a share of the performance fee by active stake
+/// - Role-based access control (admin, operator, guardian, ...)
+/// - Two-step ownership transfer and a timelock on fee and treasury changes
+/// - Upgradable in place with versioned storage migrations
+/// - Migration of legacy `StakeFlowVault` and `StakeFlowMinimal` positions
 /// - Governance-ready architecture
 ///
 /// This is the production contract for StakeFlow liquid staking protocol.
@@ -17,47 +58,130 @@ pub struct StakeFlowVaultV3 {
     // ===== VAULT CORE =====
     /// Total CSPR in vault
     total_cspr: Var<U512>,
-    /// Total CSPR actively staked to validators
-    total_staked: Var<U512>,
-    /// Owner address
-    owner: Var<Address>,
-
-    // ===== stCSPR TOKEN (CEP-18) =====
-    /// User token balances: address -> stCSPR amount
-    balances: Mapping<Address, U256>,
-    /// Token allowances: (owner, spender) -> amount
-    allowances: Mapping<(Address, Address), U256>,
-    /// Total supply of stCSPR tokens
-    total_supply: Var<U256>,
-    /// Exchange rate: CSPR per stCSPR (scaled by 1e9)
-    exchange_rate: Var<U256>,
+    /// Validator set and delegations through the system auction
+    delegation: SubModule<DelegationManager>,
+    /// Role-based permissions
+    access: SubModule<AccessControl>,
+
+    // ===== LEGACY INLINE stCSPR =====
+    // Storage v1 kept stCSPR in the vault; the fields stay in place so later fields keep their keys
+    /// Inline stCSPR balances not yet moved to the token
+    legacy_balances: Mapping<Address, U256>,
+    /// Inline allowances; not carried over, holders approve again on the token
+    #[allow(dead_code)]
+    legacy_allowances: Mapping<(Address, Address), U256>,
+    /// Inline stCSPR not yet moved to the token
+    legacy_total_supply: Var<U256>,
+    /// Inline exchange rate, superseded by the token's
+    #[allow(dead_code)]
+    legacy_exchange_rate: Var<U256>,
 
     // ===== WITHDRAWAL QUEUE =====
-    /// Pending withdrawals: withdrawal_id -> WithdrawalRequest
-    pending_withdrawals: Mapping<u64, WithdrawalRequest>,
-    /// User's withdrawal IDs: user -> Vec<u64> (stored as mapping to index)
-    user_withdrawal_ids: Mapping<(Address, u64), u64>,
-    /// User withdrawal count: user -> count
-    user_withdrawal_count: Mapping<Address, u64>,
-    /// Next withdrawal ID
-    next_withdrawal_id: Var<u64>,
+    /// FIFO withdrawal queue with fulfilment cursor
+    withdrawal_queue: SubModule<WithdrawalQueue>,
 
     // ===== GOVERNANCE & FEES =====
-    /// Treasury address for protocol fees
+    /// Treasury address; performance fees are minted to it as stCSPR
     treasury: Var<Address>,
     /// Performance fee in basis points (500 = 5%)
     performance_fee_bps: Var<u32>,
+    /// Idle-CSPR buffer target and instant-exit fee curve
+    buffer_config: Var<BufferConfig>,
+    /// Delay queue for sensitive admin calls
+    timelock: SubModule<Timelock>,
+
+    // ===== UPGRADES =====
+    /// Storage layout version the contract state is at
+    storage_version: Var<u32>,
+    /// Positions migrated in from legacy contracts
+    migration: SubModule<LegacyMigration>,
+
+    // ===== stCSPR TOKEN =====
+    /// Standalone `StCSPRToken` this vault mints and burns
+    token: Var<Address>,
+
+    // ===== REWARD ORACLE =====
+    /// Quorum-finalized era reports, the only source of reward and slashing data
+    oracle: SubModule<RewardOracle>,
+    /// Bounds on how far and how often reports may move the rate (unset = unbounded)
+    rate_limits: Var<RateLimits>,
+    /// Block time the last report was applied (or limits were first set)
+    last_report_time: Var<u64>,
+    /// Finalized report that broke the rate limits, awaiting guardian review
+    held_report: Var<Option<EraReport>>,
+
+    // ===== FEE ACCRUAL =====
+    /// Annual management fee on pooled CSPR in basis points (100 = 1%)
+    management_fee_bps: Var<u32>,
+    /// Block time management fees were last charged up to
+    last_fee_accrual: Var<u64>,
+    /// Weighted recipients of fee shares (unset = all to the treasury)
+    fee_recipients: Var<Vec<FeeRecipient>>,
+
+    // ===== DEPOSIT LIMITS =====
+    /// Minimum deposit, TVL and per-account caps, allowlist
+    deposit_guard: SubModule<DepositGuard>,
+
+    // ===== REFERRALS =====
+    /// Referred deposits, referrer stats and referrers' cut of the performance fee
+    referrals: SubModule<ReferralBook>,
+
+    // ===== PERMITS =====
+    /// Nonces and signature checks for signed withdrawal-ticket approvals
+    permits: SubModule<PermitNonces>,
 }
 
-/// Withdrawal request with time-lock
+/// Admin calls that only take effect through the timelock
 #[odra::odra_type]
-pub struct WithdrawalRequest {
-    pub user: Address,
-    pub stcspr_amount: U256,
+pub enum VaultAdminCall {
+    /// Change performance fee (new_fee_bps)
+    SetPerformanceFee(u32),
+    /// Change treasury receiving protocol fees
+    SetTreasury(Address),
+    /// Change the timelock's minimum delay (ms)
+    SetTimelockDelay(u64),
+    /// Change the annual management fee (new_fee_bps)
+    SetManagementFee(u32),
+    /// Replace the weighted fee recipients
+    SetFeeRecipients(Vec<FeeRecipient>),
+    /// Change the share of the performance fee paid to referrers (fee_share_bps)
+    SetReferralFeeShare(u32),
+}
+
+/// Account receiving a weighted cut of every fee
+/// e.g. treasury, insurance fund, node-operator rewards, referrers
+#[odra::odra_type]
+pub struct FeeRecipient {
+    pub account: Address,
+    /// Share of fees relative to the other recipients' weights
+    pub weight: u32,
+}
+
+/// Liquidity buffer backing instant withdrawals
+///
+/// The instant-exit fee rises linearly from `min_fee_bps` (buffer at or above
+/// target after the exit) to `max_fee_bps` (buffer fully drained).
+#[odra::odra_type]
+pub struct BufferConfig {
+    /// Target idle CSPR as a share of TVL (1000 = 10%)
+    pub target_buffer_bps: u32,
+    /// Fee while the buffer stays at or above target
+    pub min_fee_bps: u32,
+    /// Fee when an exit drains the buffer completely
+    pub max_fee_bps: u32,
+}
+
+/// Price of exiting instantly with a given amount of stCSPR
+#[odra::odra_type]
+pub struct InstantWithdrawQuote {
+    /// CSPR paid to the user after the fee
     pub cspr_amount: U512,
-    pub request_time: u64,
-    pub unlock_time: u64,
-    pub is_claimed: bool,
+    /// CSPR left in the vault for remaining stCSPR holders
+    pub fee: U512,
+    /// Fee rate applied
+    pub fee_bps: u32,
+    /// Idle CSPR available for instant exits
+    pub available_liquidity: U512,
 }
 
 #[odra::module]
@@ -65,22 +189,31 @@ impl StakeFlowVaultV3 {
     /// Initialize the vault
     pub fn init(&mut self, treasury_address: Address, unbonding_days: u64) {
         let caller = self.env().caller();
-        self.owner.set(caller);
+        // The deployer starts as owner, operator and guardian
+        self.access.init_owner(caller);
+        self.access.setup_role(Role::Operator, caller);
+        self.access.setup_role(Role::Guardian, caller);
+        self.timelock.init_delay(timelock::DEFAULT_MIN_DELAY);
         self.treasury.set(treasury_address);
 
         // Vault initialization
         self.total_cspr.set(U512::zero());
-        self.total_staked.set(U512::zero());
-
-        // Token initialization
-        self.total_supply.set(U256::zero());
-        self.exchange_rate.set(U256::from(1_000_000_000u64)); // 1:1 initially
 
-        // Withdrawal queue
-        self.next_withdrawal_id.set(0);
+        // Unbonding follows the auction's era schedule
+        let era_duration = delegation::DEFAULT_ERA_DURATION;
+        self.delegation.init_eras(
+            era_duration,
+            delegation::unbonding_eras_for_days(unbonding_days, era_duration),
+        );
 
         // Governance
         self.performance_fee_bps.set(500); // 5% default
+        self.last_fee_accrual.set(self.env().get_block_time());
+        self.buffer_config.set(default_buffer_config());
+        self.oracle.set_quorum(oracle::DEFAULT_QUORUM);
+
+        // Fresh deployments start at the current layout
+        self.storage_version.set(STORAGE_VERSION);
 
         self.env().emit_event(VaultInitialized {
             owner: caller,
@@ -90,6 +223,28 @@ impl StakeFlowVaultV3 {
         });
     }
 
+    /// Called by the host when new code is installed over an upgradable deployment
+    /// Runs every pending storage migration exactly once (admin only)
+    pub fn upgrade(&mut self) {
+        self.assert_role(Role::Admin);
+
+        let from_version = self.storage_version.get_or_default();
+        if from_version > STORAGE_VERSION {
+            self.env().revert(VaultError::CannotDowngradeStorage);
+        }
+
+        for version in (from_version + 1)..=STORAGE_VERSION {
+            self.migrate_storage(version);
+        }
+        self.storage_version.set(STORAGE_VERSION);
+
+        self.env().emit_event(VaultUpgraded {
+            from_version,
+            to_version: STORAGE_VERSION,
+            timestamp: self.env().get_block_time(),
+        });
+    }
+
     // ===== CORE LIQUID STAKING FUNCTIONS =====
 
     /// Deposit CSPR and receive stCSPR tokens instantly
@@ -97,284 +252,364 @@ impl StakeFlowVaultV3 {
     pub fn deposit(&mut self) -> U256 {
         let caller = self.env().caller();
         let cspr_amount = self.env().attached_value();
+        self.deposit_from(caller, cspr_amount)
+    }
 
-        let min_deposit = U512::from(10_000_000_000u64); // 10 CSPR minimum
-        assert!(cspr_amount >= min_deposit, "Minimum deposit is 10 CSPR");
-
-        // Update vault CSPR
-        self.total_cspr.set(self.total_cspr.get_or_default() + cspr_amount);
-
-        // Calculate stCSPR to mint based on exchange rate
-        let cspr_u256 = U256::from(cspr_amount.as_u128());
-        let rate = self.exchange_rate.get_or_default();
-        let stcspr_amount = (cspr_u256 * U256::from(1_000_000_000u64)) / rate;
-
-        // Mint stCSPR tokens
-        let current_balance = self.balances.get(&caller).unwrap_or(U256::zero());
-        self.balances.set(&caller, current_balance + stcspr_amount);
-        self.total_supply.set(self.total_supply.get_or_default() + stcspr_amount);
+    /// Deposit CSPR and mint the stCSPR to `beneficiary`
+    /// Limits and the allowlist apply to the beneficiary
+    #[odra(payable)]
+    pub fn deposit_for(&mut self, beneficiary: Address) -> U256 {
+        let depositor = self.env().caller();
+        let cspr_amount = self.env().attached_value();
+        let stcspr_amount = self.deposit_from(beneficiary, cspr_amount);
 
-        self.env().emit_event(Deposited {
-            user: caller,
+        self.env().emit_event(DepositedFor {
+            depositor,
+            beneficiary,
             cspr_amount,
             stcspr_amount,
-            exchange_rate: rate,
             timestamp: self.env().get_block_time(),
         });
 
-        self.env().emit_event(Transfer {
-            from: None,
-            to: Some(caller),
-            amount: stcspr_amount,
-        });
-
         stcspr_amount
     }
 
-    /// Request withdrawal - initiates unbonding period
-    /// Returns withdrawal request ID
-    pub fn request_withdrawal(&mut self, stcspr_amount: U256) -> u64 {
+    /// Deposit CSPR crediting `referrer`, who earns a share of the performance fee on it
+    /// A user stays credited to the first referrer they deposit with
+    #[odra(payable)]
+    pub fn deposit_with_referral(&mut self, referrer: Address) -> U256 {
         let caller = self.env().caller();
-        assert!(stcspr_amount > U256::zero(), "Amount must be > 0");
+        let cspr_amount = self.env().attached_value();
+        let stcspr_amount = self.deposit_from(caller, cspr_amount);
+        self.referrals.record(caller, referrer, cspr_amount, stcspr_amount);
+        stcspr_amount
+    }
 
-        let balance = self.balances.get(&caller).unwrap_or(U256::zero());
-        assert!(balance >= stcspr_amount, "Insufficient stCSPR balance");
+    /// Receive a legacy position redeemed by a registered legacy contract
+    /// Mints stCSPR to `user` for the attached CSPR at the current exchange rate
+    #[odra(payable)]
+    pub fn migrate_deposit(&mut self, user: Address) -> U256 {
+        let source = self.env().caller();
+        let cspr_amount = self.env().attached_value();
+        if cspr_amount.is_zero() {
+            self.env().revert(VaultError::NothingToMigrate);
+        }
 
-        // Calculate CSPR to return based on exchange rate
-        let rate = self.exchange_rate.get_or_default();
-        let cspr_u256 = (stcspr_amount * rate) / U256::from(1_000_000_000u64);
-        let cspr_amount = U512::from(cspr_u256.as_u128());
+        self.migration.record(source, user, cspr_amount);
+        self.credit_migration(source, user, cspr_amount)
+    }
 
-        // Burn stCSPR tokens immediately
-        self.balances.set(&caller, balance - stcspr_amount);
-        self.total_supply.set(self.total_supply.get_or_default() - stcspr_amount);
+    /// Attest the caller's position on a deployed legacy contract, read from the contract itself
+    /// Withdraw it there afterwards and bring the CSPR in with `migrate_attested_position`
+    /// Returns the CSPR value attested
+    pub fn attest_legacy_position(&mut self, source: Address) -> U512 {
+        let caller = self.env().caller();
+        self.migration.attest(source, caller)
+    }
 
-        self.env().emit_event(Transfer {
-            from: Some(caller),
-            to: None,
-            amount: stcspr_amount,
-        });
+    /// Migrate CSPR withdrawn from an attested legacy position
+    /// Accepted up to what the position dropped by since the attestation; mints stCSPR to the caller
+    #[odra(payable)]
+    pub fn migrate_attested_position(&mut self, source: Address) -> U256 {
+        let caller = self.env().caller();
+        let cspr_amount = self.env().attached_value();
+        if cspr_amount.is_zero() {
+            self.env().revert(VaultError::NothingToMigrate);
+        }
 
-        // Create withdrawal request
-        let withdrawal_id = self.next_withdrawal_id.get_or_default();
-        let current_time = self.env().get_block_time();
-        let unbonding_period = 7 * 24 * 60 * 60; // 7 days in seconds
+        self.migration.record_attested(source, caller, cspr_amount);
+        self.credit_migration(source, caller, cspr_amount)
+    }
 
-        let withdrawal = WithdrawalRequest {
-            user: caller,
-            stcspr_amount,
-            cspr_amount,
-            request_time: current_time,
-            unlock_time: current_time + unbonding_period,
-            is_claimed: false,
-        };
+    /// Move holders' inline stCSPR from before the standalone token onto the token
+    /// Anyone may call; holders without an inline balance are skipped
+    pub fn migrate_legacy_stcspr(&mut self, holders: Vec<Address>) {
+        let mut token = self.token();
+        for holder in holders {
+            let stcspr_amount = self.get_legacy_stcspr_balance(holder);
+            if stcspr_amount.is_zero() {
+                continue;
+            }
+            self.legacy_balances.set(&holder, U256::zero());
+            self.legacy_total_supply.set(self.legacy_total_supply.get_or_default() - stcspr_amount);
 
-        self.pending_withdrawals.set(&withdrawal_id, withdrawal);
+            // Seeded to the vault when the token was linked
+            token.transfer(holder, stcspr_amount);
+
+            self.env().emit_event(LegacyStCSPRMigrated {
+                holder,
+                stcspr_amount,
+                timestamp: self.env().get_block_time(),
+            });
+        }
+    }
 
-        // Track user's withdrawal IDs
-        let user_count = self.user_withdrawal_count.get(&caller).unwrap_or(0);
-        self.user_withdrawal_ids.set(&(caller, user_count), withdrawal_id);
-        self.user_withdrawal_count.set(&caller, user_count + 1);
+    /// Request withdrawal - initiates unbonding period
+    /// Returns withdrawal request ID
+    pub fn request_withdrawal(&mut self, stcspr_amount: U256) -> u64 {
+        let caller = self.env().caller();
+        self.queue_withdrawal(caller, None, stcspr_amount)
+    }
 
-        self.next_withdrawal_id.set(withdrawal_id + 1);
+    /// Request withdrawal of `owner`'s stCSPR, spending the allowance `owner` granted the caller
+    /// The withdrawal ticket belongs to `owner`
+    /// Returns withdrawal request ID
+    pub fn request_withdrawal_for(&mut self, owner: Address, stcspr_amount: U256) -> u64 {
+        let spender = self.env().caller();
+        let withdrawal_id = self.queue_withdrawal(owner, Some(spender), stcspr_amount);
 
-        self.env().emit_event(WithdrawalRequested {
+        self.env().emit_event(WithdrawalRequestedFor {
             withdrawal_id,
-            user: caller,
+            owner,
+            spender,
             stcspr_amount,
-            cspr_amount,
-            unlock_time: current_time + unbonding_period,
-            timestamp: current_time,
+            timestamp: self.env().get_block_time(),
         });
 
         withdrawal_id
     }
 
     /// Claim withdrawal after unbonding period
+    /// Pays out whatever part of the request the queue has fulfilled so far
     pub fn claim_withdrawal(&mut self, withdrawal_id: u64) {
         let caller = self.env().caller();
 
-        let mut withdrawal = self.pending_withdrawals.get(&withdrawal_id)
-            .expect("Withdrawal request not found");
-
-        assert!(withdrawal.user == caller, "Not your withdrawal");
-        assert!(!withdrawal.is_claimed, "Already claimed");
-        assert!(
-            self.env().get_block_time() >= withdrawal.unlock_time,
-            "Unbonding period not complete"
-        );
-
-        // Check liquidity
-        let available = self.total_cspr.get_or_default() - self.total_staked.get_or_default();
-        assert!(withdrawal.cspr_amount <= available, "Insufficient liquidity");
+        let withdrawal = self.withdrawal_queue.get(withdrawal_id)
+            .unwrap_or_revert_with(&self.env(), WithdrawalQueueError::WithdrawalNotFound);
 
-        // Mark as claimed
-        withdrawal.is_claimed = true;
-        let cspr_amount = withdrawal.cspr_amount;
-        self.pending_withdrawals.set(&withdrawal_id, withdrawal);
+        // Whoever holds the ticket is paid
+        if self.withdrawal_queue.owner_of(withdrawal_id) != Some(caller) {
+            self.env().revert(VaultError::NotWithdrawalOwner);
+        }
+        if self.env().get_block_time() < withdrawal.unlock_time {
+            self.env().revert(VaultError::UnbondingNotComplete);
+        }
 
-        // Update vault total
-        self.total_cspr.set(self.total_cspr.get_or_default() - cspr_amount);
+        // Pick up liquidity that arrived since the last interaction
+        self.process_queue();
+        let cspr_amount = self.withdrawal_queue.claim(withdrawal_id);
+        let remaining_amount = self.withdrawal_queue.owed_amount(withdrawal_id) - withdrawal.claimed_amount - cspr_amount;
 
-        // Transfer CSPR to user
-        self.env().transfer_tokens(&caller, &cspr_amount);
+        // Transfer CSPR to user; a request wiped out by slashing closes with nothing to send
+        if !cspr_amount.is_zero() {
+            self.env().transfer_tokens(&caller, &cspr_amount);
+        }
 
         self.env().emit_event(WithdrawalClaimed {
             withdrawal_id,
             user: caller,
             cspr_amount,
+            remaining_amount,
             timestamp: self.env().get_block_time(),
         });
     }
 
-    // ===== CEP-18 TOKEN INTERFACE =====
-
-    /// Get token name
-    pub fn name(&self) -> String {
-        "Staked CSPR".to_string()
-    }
+    /// Exit immediately from the idle-CSPR buffer, paying a dynamic fee
+    /// The fee stays in the vault and raises the exchange rate for remaining holders
+    /// Returns CSPR paid out
+    pub fn instant_withdraw(&mut self, stcspr_amount: U256, min_cspr_out: U512) -> U512 {
+        let caller = self.env().caller();
+        if stcspr_amount.is_zero() {
+            self.env().revert(VaultError::ZeroAmount);
+        }
 
-    /// Get token symbol
-    pub fn symbol(&self) -> String {
-        "stCSPR".to_string()
-    }
+        self.accrue_management_fee();
+        let quote = self.instant_withdraw_quote(stcspr_amount);
+        let gross = quote.cspr_amount + quote.fee;
+        if gross > quote.available_liquidity {
+            self.env().revert(VaultError::InsufficientBufferLiquidity);
+        }
+        if quote.cspr_amount < min_cspr_out {
+            self.env().revert(VaultError::SlippageExceeded);
+        }
 
-    /// Get decimals
-    pub fn decimals(&self) -> u8 {
-        9 // Match CSPR
-    }
+        // Burn stCSPR tokens
+        self.token().burn(caller, stcspr_amount);
+        self.referrals.release(caller, stcspr_amount);
 
-    /// Get total supply
-    pub fn total_supply(&self) -> U256 {
-        self.total_supply.get_or_default()
-    }
+        // Only the payout leaves the pool; the fee accrues to remaining holders
+        let total_cspr = math::checked_sub(self.total_cspr.get_or_default(), quote.cspr_amount)
+            .unwrap_or_revert(&self.env());
+        self.total_cspr.set(total_cspr);
+        self.sync_exchange_rate();
 
-    /// Get balance of address
-    pub fn balance_of(&self, owner: Address) -> U256 {
-        self.balances.get(&owner).unwrap_or(U256::zero())
-    }
+        self.env().transfer_tokens(&caller, &quote.cspr_amount);
 
-    /// Transfer stCSPR tokens
-    pub fn transfer(&mut self, recipient: Address, amount: U256) {
-        let caller = self.env().caller();
-        assert!(amount > U256::zero(), "Amount must be > 0");
-        assert!(caller != recipient, "Cannot transfer to self");
+        self.env().emit_event(InstantWithdrawal {
+            user: caller,
+            stcspr_amount,
+            cspr_amount: quote.cspr_amount,
+            fee: quote.fee,
+            fee_bps: quote.fee_bps,
+            timestamp: self.env().get_block_time(),
+        });
 
-        let from_balance = self.balances.get(&caller).unwrap_or(U256::zero());
-        assert!(from_balance >= amount, "Insufficient balance");
+        quote.cspr_amount
+    }
 
-        let to_balance = self.balances.get(&recipient).unwrap_or(U256::zero());
+    /// Hand idle liquidity to the withdrawal queue in FIFO order
+    /// Returns the CSPR newly reserved for queued requests
+    pub fn process_withdrawal_queue(&mut self) -> U512 {
+        self.process_queue()
+    }
 
-        self.balances.set(&caller, from_balance - amount);
-        self.balances.set(&recipient, to_balance + amount);
+    // ===== WITHDRAWAL TICKETS =====
 
-        self.env().emit_event(Transfer {
-            from: Some(caller),
-            to: Some(recipient),
-            amount,
-        });
+    /// Transfer a withdrawal ticket; the new owner claims it
+    /// Caller must own the ticket, be approved for it, or be an operator of the owner
+    pub fn transfer_withdrawal(&mut self, from: Address, to: Address, withdrawal_id: u64) {
+        let caller = self.env().caller();
+        self.withdrawal_queue.transfer(caller, from, to, withdrawal_id);
     }
 
-    /// Approve spender
-    pub fn approve(&mut self, spender: Address, amount: U256) {
+    /// Approve an address to transfer one withdrawal ticket
+    pub fn approve_withdrawal(&mut self, spender: Address, withdrawal_id: u64) {
         let caller = self.env().caller();
-        assert!(caller != spender, "Cannot approve self");
+        self.withdrawal_queue.approve(caller, Some(spender), withdrawal_id);
+    }
 
-        self.allowances.set(&(caller, spender), amount);
+    /// Approve `spender` for one of `owner`'s withdrawal tickets with a signature `owner` made off-chain
+    /// Anyone may submit it before `deadline`; `public_key` must be the owner's account key
+    /// and the signature must cover `get_withdrawal_permit_message` for the owner's current nonce
+    pub fn permit_withdrawal(
+        &mut self,
+        owner: Address,
+        spender: Address,
+        withdrawal_id: u64,
+        deadline: u64,
+        signature: Bytes,
+        public_key: PublicKey,
+    ) {
+        let payload = self.withdrawal_permit_payload(spender, withdrawal_id);
+        self.permits.use_permit(owner, payload, deadline, signature, public_key);
+        self.withdrawal_queue.approve(owner, Some(spender), withdrawal_id);
+    }
 
-        self.env().emit_event(Approval {
-            owner: caller,
-            spender,
-            amount,
-        });
+    /// Get the nonce `owner`'s next withdrawal permit must be signed with
+    pub fn get_permit_nonce(&self, owner: Address) -> u64 {
+        self.permits.nonce(owner)
     }
 
-    /// Get allowance
-    pub fn allowance(&self, owner: Address, spender: Address) -> U256 {
-        self.allowances.get(&(owner, spender)).unwrap_or(U256::zero())
+    /// Message `owner` signs to permit `spender` to move a withdrawal ticket until `deadline`
+    pub fn get_withdrawal_permit_message(&self, owner: Address, spender: Address, withdrawal_id: u64, deadline: u64) -> Bytes {
+        self.permits.message(owner, self.withdrawal_permit_payload(spender, withdrawal_id), deadline)
     }
 
-    /// Transfer from (with allowance)
-    pub fn transfer_from(&mut self, owner: Address, recipient: Address, amount: U256) {
+    /// Clear the approval on a withdrawal ticket
+    pub fn revoke_withdrawal_approval(&mut self, withdrawal_id: u64) {
         let caller = self.env().caller();
-        assert!(amount > U256::zero(), "Amount must be > 0");
-        assert!(owner != recipient, "Cannot transfer to self");
+        self.withdrawal_queue.approve(caller, None, withdrawal_id);
+    }
 
-        // Check allowance
-        let current_allowance = self.allowances.get(&(owner, caller)).unwrap_or(U256::zero());
-        assert!(current_allowance >= amount, "Insufficient allowance");
+    /// Allow or revoke an operator for all of the caller's withdrawal tickets
+    pub fn set_withdrawal_operator(&mut self, operator: Address, approved: bool) {
+        let caller = self.env().caller();
+        self.withdrawal_queue.set_operator(caller, operator, approved);
+    }
 
-        // Update allowance
-        self.allowances.set(&(owner, caller), current_allowance - amount);
+    // ===== VALIDATOR DELEGATION =====
 
-        // Transfer
-        let from_balance = self.balances.get(&owner).unwrap_or(U256::zero());
-        assert!(from_balance >= amount, "Insufficient balance");
+    /// Register a validator for delegation (admin only)
+    pub fn add_validator(&mut self, validator: PublicKey) {
+        self.assert_role(Role::Admin);
+        self.delegation.add_validator(validator);
+    }
 
-        let to_balance = self.balances.get(&recipient).unwrap_or(U256::zero());
+    /// Delegate idle CSPR to a registered validator (operator only)
+    pub fn delegate_to_validator(&mut self, validator: PublicKey, amount: U512) {
+        self.assert_role(Role::Operator);
+        if amount > self.idle_cspr() {
+            self.env().revert(VaultError::InsufficientIdleCspr);
+        }
+        self.delegation.delegate(validator, amount);
+    }
 
-        self.balances.set(&owner, from_balance - amount);
-        self.balances.set(&recipient, to_balance + amount);
+    /// Undelegate CSPR from a validator (operator only)
+    /// Funds return to the vault after the auction's unbonding delay
+    pub fn undelegate_from_validator(&mut self, validator: PublicKey, amount: U512) {
+        self.assert_role(Role::Operator);
+        self.delegation.undelegate(validator, amount);
+    }
 
-        self.env().emit_event(Transfer {
-            from: Some(owner),
-            to: Some(recipient),
-            amount,
-        });
+    /// Book CSPR returned by the auction after unbonding
+    /// Returns the amount made available as liquidity
+    pub fn settle_unbonded(&mut self) -> U512 {
+        let booked_balance = self.booked_balance();
+        let settled = self.delegation.settle_unbonded(booked_balance);
+        self.process_queue();
+        settled
     }
 
     // ===== REWARD MANAGEMENT =====
 
-    /// Compound staking rewards (owner only)
-    /// Updates exchange rate to reflect accrued rewards
-    pub fn compound_rewards(&mut self, rewards_amount: U512) {
-        self.assert_owner();
-        assert!(rewards_amount > U512::zero(), "No rewards");
-
-        // Calculate performance fee
-        let fee_bps = self.performance_fee_bps.get_or_default();
-        let protocol_fee = (rewards_amount * U512::from(fee_bps)) / U512::from(10000u64);
-        let user_rewards = rewards_amount - protocol_fee;
-
-        // Update total CSPR
-        self.total_cspr.set(self.total_cspr.get_or_default() + user_rewards);
-
-        // Update exchange rate
-        let total_stcspr = self.total_supply.get_or_default();
-        if total_stcspr > U256::zero() {
-            let total_cspr_u256 = U256::from(self.total_cspr.get_or_default().as_u128());
-            let new_rate = (total_cspr_u256 * U256::from(1_000_000_000u64)) / total_stcspr;
-            let old_rate = self.exchange_rate.get_or_default();
-            self.exchange_rate.set(new_rate);
-
-            self.env().emit_event(ExchangeRateUpdated {
-                old_rate,
-                new_rate,
-                total_cspr: self.total_cspr.get_or_default(),
-                total_stcspr,
-                timestamp: self.env().get_block_time(),
-            });
+    /// Submit a report for an ended era (oracle only)
+    /// Rewards and slashing reach the exchange rate once a quorum of reporters agrees,
+    /// unless the report breaks the rate limits, in which case it is held for review
+    /// The reported delegation must match what the auction holds for the vault, and rewards
+    /// less slashing must be exactly how far it moved from the booked principal
+    pub fn submit_report(&mut self, report: EraReport) {
+        self.assert_role(Role::Oracle);
+        if self.held_report.get().flatten().is_some() {
+            self.env().revert(VaultError::ReportAwaitingReview);
         }
-
-        // Transfer fees to treasury
-        if protocol_fee > U512::zero() {
-            let treasury = self.treasury.get().unwrap();
-            self.env().transfer_tokens(&treasury, &protocol_fee);
+        self.assert_report_matches_auction(&report);
+        let reporter = self.env().caller();
+        let current_era = self.delegation.current_era();
+        if let Some(finalized) = self.oracle.submit(reporter, report, current_era) {
+            self.review_report(finalized);
         }
+    }
+
+    /// Apply the held report despite the rate limits (guardian only)
+    /// The token's per-update bound still applies, and the auction must not have moved since the report;
+    /// otherwise reject it and let the next report carry its amounts
+    pub fn release_held_report(&mut self) {
+        self.assert_role(Role::Guardian);
+        let report = self.take_held_report();
+        self.assert_report_matches_auction(&report);
 
-        self.env().emit_event(RewardsCompounded {
-            total_rewards: rewards_amount,
-            protocol_fee,
-            user_rewards,
+        self.env().emit_event(HeldReportReleased {
+            era: report.era,
+            guardian: self.env().caller(),
             timestamp: self.env().get_block_time(),
         });
+
+        self.apply_report(report);
+    }
+
+    /// Discard the held report without booking it (guardian only)
+    pub fn reject_held_report(&mut self) {
+        self.assert_role(Role::Guardian);
+        let report = self.take_held_report();
+
+        self.env().emit_event(HeldReportRejected {
+            era: report.era,
+            guardian: self.env().caller(),
+            timestamp: self.env().get_block_time(),
+        });
+    }
+
+    /// Charge the management fee accrued since the last interaction
+    /// Anyone can call; every deposit, withdrawal and report also does
+    pub fn accrue_fees(&mut self) {
+        self.accrue_management_fee();
     }
 
     // ===== VIEW FUNCTIONS =====
 
-    /// Get exchange rate
+    /// Get exchange rate (CSPR per stCSPR, scaled by 1e9), as held by the token
     pub fn get_exchange_rate(&self) -> U256 {
-        self.exchange_rate.get_or_default()
+        self.token().get_exchange_rate()
+    }
+
+    /// Get the stCSPR token this vault mints and burns
+    pub fn get_token(&self) -> Option<Address> {
+        self.token.get()
+    }
+
+    /// Get a holder's inline stCSPR from before the standalone token, not yet moved to it
+    pub fn get_legacy_stcspr_balance(&self, holder: Address) -> U256 {
+        self.legacy_balances.get(&holder).unwrap_or_default()
     }
 
     /// Get total value locked
@@ -382,250 +617,3744 @@ impl StakeFlowVaultV3 {
         self.total_cspr.get_or_default()
     }
 
+    /// Get total principal delegated to validators
+    pub fn get_total_staked(&self) -> U512 {
+        self.delegation.total_delegated()
+    }
+
+    /// Get CSPR delegated across all validators, including auction rewards
+    pub fn get_total_delegated_amount(&self) -> U512 {
+        self.delegation.total_delegated_amount()
+    }
+
+    /// Get principal undelegated and still unbonding
+    pub fn get_total_unbonding(&self) -> U512 {
+        self.delegation.total_unbonding()
+    }
+
+    /// Get idle CSPR available for delegation and withdrawals
+    pub fn get_idle_cspr(&self) -> U512 {
+        self.idle_cspr()
+    }
+
+    /// Get registered validators
+    pub fn get_validators(&self) -> Vec<PublicKey> {
+        self.delegation.validators()
+    }
+
+    /// Get amount delegated to a validator, including auction rewards
+    pub fn get_delegated_amount(&self, validator: PublicKey) -> U512 {
+        self.delegation.delegated_amount(validator)
+    }
+
+    /// Get the era schedule used for unbonding
+    pub fn get_era_config(&self) -> EraConfig {
+        self.delegation.era_config()
+    }
+
+    /// Get the current era
+    pub fn get_current_era(&self) -> u64 {
+        self.delegation.current_era()
+    }
+
+    /// Get principal scheduled to return from the auction in an era
+    pub fn get_unbonding_for_era(&self, era: u64) -> U512 {
+        self.delegation.unbonding_for_era(era)
+    }
+
     /// Get withdrawal request details
     pub fn get_withdrawal_request(&self, withdrawal_id: u64) -> Option<WithdrawalRequest> {
-        self.pending_withdrawals.get(&withdrawal_id)
+        self.withdrawal_queue.get(withdrawal_id)
     }
 
-    /// Get user's withdrawal request IDs
+    /// Get IDs of the withdrawal tickets a user currently holds
     pub fn get_user_withdrawals(&self, user: Address) -> Vec<u64> {
-        let count = self.user_withdrawal_count.get(&user).unwrap_or(0);
-        let mut ids = Vec::new();
-        for i in 0..count {
-            if let Some(id) = self.user_withdrawal_ids.get(&(user, i)) {
-                ids.push(id);
-            }
-        }
-        ids
+        self.withdrawal_queue.user_withdrawals(user)
     }
 
-    /// Convert stCSPR to CSPR value
-    pub fn stcspr_to_cspr(&self, stcspr_amount: U256) -> U512 {
-        let rate = self.exchange_rate.get_or_default();
-        let cspr_u256 = (stcspr_amount * rate) / U256::from(1_000_000_000u64);
-        U512::from(cspr_u256.as_u128())
+    /// Get current owner of a withdrawal ticket
+    pub fn get_withdrawal_owner(&self, withdrawal_id: u64) -> Option<Address> {
+        self.withdrawal_queue.owner_of(withdrawal_id)
     }
 
-    /// Get APY (basis points, 1000 = 10%)
-    pub fn get_apy(&self) -> u32 {
-        let base_apy = 950; // 9.5% base Casper staking APY
-        let fee = self.performance_fee_bps.get_or_default();
-        let net_apy = base_apy - (base_apy * fee / 10000);
-        net_apy
+    /// Get approved spender of a withdrawal ticket
+    pub fn get_withdrawal_approved(&self, withdrawal_id: u64) -> Option<Address> {
+        self.withdrawal_queue.approved_for(withdrawal_id)
     }
 
-    // ===== ADMIN FUNCTIONS =====
+    /// Check if an operator may transfer all of an owner's withdrawal tickets
+    pub fn is_withdrawal_operator(&self, owner: Address, operator: Address) -> bool {
+        self.withdrawal_queue.is_operator(owner, operator)
+    }
 
-    /// Set performance fee (owner only)
-    pub fn set_performance_fee(&mut self, new_fee_bps: u32) {
-        self.assert_owner();
-        assert!(new_fee_bps <= 1000, "Max 10% fee");
-        self.performance_fee_bps.set(new_fee_bps);
+    /// Get CSPR of a withdrawal that is fulfilled and not yet claimed
+    pub fn get_claimable_amount(&self, withdrawal_id: u64) -> U512 {
+        self.withdrawal_queue.claimable(withdrawal_id)
     }
 
-    // ===== INTERNAL =====
+    /// Get the CSPR a withdrawal pays out in total, after any slashing it shared
+    pub fn get_withdrawal_payout(&self, withdrawal_id: u64) -> U512 {
+        self.withdrawal_queue.owed_amount(withdrawal_id)
+    }
 
-    fn assert_owner(&self) {
-        let caller = self.env().caller();
-        let owner = self.owner.get().expect("Owner not set");
-        assert!(caller == owner, "Only owner");
+    /// Get CSPR requested for withdrawal and still waiting for liquidity
+    pub fn get_total_queued(&self) -> U512 {
+        self.withdrawal_queue.total_queued()
     }
-}
 
-// ===== EVENTS =====
+    /// Get CSPR fulfilled and held for withdrawal claims
+    pub fn get_total_reserved(&self) -> U512 {
+        self.withdrawal_queue.total_reserved()
+    }
 
-#[odra::event]
-pub struct VaultInitialized {
-    pub owner: Address,
-    pub treasury: Address,
-    pub unbonding_period_days: u64,
-    pub timestamp: u64,
-}
+    /// Get a withdrawal's place in the queue and its estimated fulfilment time
+    pub fn get_queue_position(&self, withdrawal_id: u64) -> QueuePosition {
+        let withdrawal = self.withdrawal_queue.get(withdrawal_id)
+            .unwrap_or_revert_with(&self.env(), WithdrawalQueueError::WithdrawalNotFound);
 
-#[odra::event]
-pub struct Deposited {
-    pub user: Address,
-    pub cspr_amount: U512,
-    pub stcspr_amount: U256,
-    pub exchange_rate: U256,
-    pub timestamp: u64,
-}
+        let cspr_ahead = self.withdrawal_queue.cspr_ahead(withdrawal_id);
+        let fulfilled_amount = self.withdrawal_queue.fulfilled_amount(withdrawal_id);
+        let remaining_amount = self.withdrawal_queue.owed_amount(withdrawal_id) - fulfilled_amount;
 
-#[odra::event]
-pub struct WithdrawalRequested {
-    pub withdrawal_id: u64,
-    pub user: Address,
-    pub stcspr_amount: U256,
-    pub cspr_amount: U512,
-    pub unlock_time: u64,
-    pub timestamp: u64,
-}
+        let estimated_fulfilment_time = if remaining_amount.is_zero() {
+            Some(withdrawal.unlock_time)
+        } else {
+            self.estimate_liquidity_time(cspr_ahead + remaining_amount)
+                .map(|time| if time > withdrawal.unlock_time { time } else { withdrawal.unlock_time })
+        };
 
-#[odra::event]
-pub struct WithdrawalClaimed {
-    pub withdrawal_id: u64,
-    pub user: Address,
-    pub cspr_amount: U512,
-    pub timestamp: u64,
-}
+        QueuePosition {
+            cspr_ahead,
+            fulfilled_amount,
+            remaining_amount,
+            estimated_fulfilment_time,
+        }
+    }
 
-#[odra::event]
-pub struct Transfer {
-    pub from: Option<Address>,
-    pub to: Option<Address>,
-    pub amount: U256,
-}
+    /// Get the current instant-exit quote for an amount of stCSPR
+    pub fn get_instant_withdraw_quote(&self, stcspr_amount: U256) -> InstantWithdrawQuote {
+        self.instant_withdraw_quote(stcspr_amount)
+    }
 
-#[odra::event]
-pub struct Approval {
-    pub owner: Address,
-    pub spender: Address,
-    pub amount: U256,
-}
+    /// Get buffer target and instant-exit fee curve
+    pub fn get_buffer_config(&self) -> BufferConfig {
+        self.buffer_config.get().unwrap_or_revert_with(&self.env(), VaultError::BufferConfigNotSet)
+    }
 
-#[odra::event]
-pub struct RewardsCompounded {
-    pub total_rewards: U512,
-    pub protocol_fee: U512,
-    pub user_rewards: U512,
-    pub timestamp: u64,
-}
+    /// Get idle CSPR the buffer aims to hold
+    pub fn get_buffer_target(&self) -> U512 {
+        let config = self.get_buffer_config();
+        math::apply_bps(self.total_cspr.get_or_default(), config.target_buffer_bps, Rounding::Down)
+            .unwrap_or_revert(&self.env())
+    }
 
-#[odra::event]
-pub struct ExchangeRateUpdated {
-    pub old_rate: U256,
-    pub new_rate: U256,
-    pub total_cspr: U512,
-    pub total_stcspr: U256,
-    pub timestamp: u64,
-}
+    /// Get CSPR a user migrated from a legacy contract
+    pub fn get_migrated_amount(&self, source: Address, user: Address) -> U512 {
+        self.migration.migrated_amount(source, user)
+    }
 
-// ===== TESTS =====
+    /// Get a user's latest attested position on a deployed legacy contract
+    pub fn get_legacy_attestation(&self, source: Address, user: Address) -> Option<LegacyAttestation> {
+        self.migration.attestation(source, user)
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use odra::host::{Deployer, HostRef, NoArgs};
+    /// Get total CSPR received from legacy contracts
+    pub fn get_total_migrated(&self) -> U512 {
+        self.migration.total_migrated()
+    }
 
-    #[test]
-    fn test_deposit_and_mint() {
-        let env = odra_test::env();
-        let treasury = env.get_account(9);
-        let mut vault = StakeFlowVaultV3::deploy(&env, StakeFlowVaultV3InitArgs {
-            treasury_address: treasury,
-            unbonding_days: 7,
-        });
+    /// Get storage layout version
+    pub fn get_storage_version(&self) -> u32 {
+        self.storage_version.get_or_default()
+    }
 
-        let amount = U512::from(100_000_000_000u64); // 100 CSPR
-        env.set_caller(env.get_account(1));
-        let stcspr = vault.with_tokens(amount).deposit();
+    /// Get treasury address
+    pub fn get_treasury(&self) -> Address {
+        self.treasury.get().unwrap_or_revert_with(&self.env(), VaultError::TreasuryNotSet)
+    }
 
-        assert_eq!(vault.get_tvl(), amount);
-        assert_eq!(vault.balance_of(env.get_account(1)), stcspr);
-        assert_eq!(vault.total_supply(), stcspr);
+    /// Get matching reports needed to finalize an era
+    pub fn get_oracle_quorum(&self) -> u32 {
+        self.oracle.quorum()
+    }
+
+    /// Get the most recent era with a finalized report
+    pub fn get_last_reported_era(&self) -> Option<u64> {
+        self.oracle.last_finalized_era()
+    }
+
+    /// Get the finalized report of an era
+    pub fn get_era_report(&self, era: u64) -> Option<EraReport> {
+        self.oracle.finalized_report(era)
+    }
+
+    /// Check if a reporter already reported an era
+    pub fn has_reported(&self, era: u64, reporter: Address) -> bool {
+        self.oracle.has_reported(era, reporter)
+    }
+
+    /// Get the rate limits applied to reports, if set
+    pub fn get_rate_limits(&self) -> Option<RateLimits> {
+        self.rate_limits.get()
+    }
+
+    /// Get the report held for guardian review, if any
+    pub fn get_held_report(&self) -> Option<EraReport> {
+        self.held_report.get().flatten()
+    }
+
+    /// Get stCSPR held by the treasury, including its cut of fees
+    pub fn get_treasury_shares(&self) -> U256 {
+        self.token().balance_of(self.get_treasury())
+    }
+
+    /// Get an account's delegated stCSPR voting power
+    pub fn get_votes(&self, account: Address) -> U256 {
+        self.token().get_votes(account)
+    }
+
+    /// Get an account's stCSPR voting power at the end of block time `timestamp` (must be in the past)
+    pub fn get_past_votes(&self, account: Address, timestamp: u64) -> U256 {
+        self.token().get_past_votes(account, timestamp)
+    }
+
+    /// Get stCSPR total supply at the end of block time `timestamp` (must be in the past)
+    pub fn get_past_total_supply(&self, timestamp: u64) -> U256 {
+        self.token().get_past_total_supply(timestamp)
+    }
+
+    /// Get performance fee (bps)
+    pub fn get_performance_fee(&self) -> u32 {
+        self.performance_fee_bps.get_or_default()
+    }
+
+    /// Get annual management fee (bps)
+    pub fn get_management_fee(&self) -> u32 {
+        self.management_fee_bps.get_or_default()
+    }
+
+    /// Get management fee accrued since the last charge, in CSPR
+    pub fn get_pending_management_fee(&self) -> U512 {
+        self.pending_management_fee()
+    }
+
+    /// Get the weighted fee recipients; the treasury alone if none are set
+    pub fn get_fee_recipients(&self) -> Vec<FeeRecipient> {
+        match self.fee_recipients.get() {
+            Some(recipients) if !recipients.is_empty() => recipients,
+            _ => vec![FeeRecipient { account: self.get_treasury(), weight: 1 }],
+        }
+    }
+
+    /// Convert stCSPR to CSPR value, after any pending management fee
+    pub fn stcspr_to_cspr(&self, stcspr_amount: U256) -> U512 {
+        let (total_cspr, total_stcspr) = self.accrued_totals();
+        let cspr = math::shares_to_assets(stcspr_amount, total_cspr, total_stcspr, Rounding::Down)
+            .unwrap_or_revert(&self.env());
+        math::to_u512(cspr)
+    }
+
+    /// Get APY (basis points, 1000 = 10%)
+    pub fn get_apy(&self) -> u32 {
+        let base_apy = 950; // 9.5% base Casper staking APY
+        let fee = self.performance_fee_bps.get_or_default();
+        let net_apy = base_apy - (base_apy * fee / 10000);
+        net_apy
+    }
+
+    // ===== PREVIEWS (ERC-4626 STYLE) =====
+
+    /// stCSPR a `deposit` of `cspr_amount` would mint right now
+    pub fn preview_deposit(&self, cspr_amount: U512) -> U256 {
+        if cspr_amount < self.deposit_guard.min_deposit() {
+            self.env().revert(VaultError::BelowMinimumDeposit);
+        }
+        self.convert_to_shares(cspr_amount)
+    }
+
+    /// CSPR a `request_withdrawal` of `stcspr_amount` would queue right now
+    pub fn preview_request_withdrawal(&self, stcspr_amount: U256) -> U512 {
+        if stcspr_amount.is_zero() {
+            self.env().revert(VaultError::ZeroAmount);
+        }
+        self.convert_to_assets(stcspr_amount)
+    }
+
+    /// stCSPR `cspr_amount` is worth at the current rate, ignoring limits
+    pub fn convert_to_shares(&self, cspr_amount: U512) -> U256 {
+        let cspr_amount = math::to_u256(cspr_amount).unwrap_or_revert(&self.env());
+        let (total_cspr, total_stcspr) = self.accrued_totals();
+        math::assets_to_shares(cspr_amount, total_cspr, total_stcspr, Rounding::Down)
+            .unwrap_or_revert(&self.env())
+    }
+
+    /// CSPR `stcspr_amount` is worth at the current rate, ignoring limits
+    pub fn convert_to_assets(&self, stcspr_amount: U256) -> U512 {
+        self.stcspr_to_cspr(stcspr_amount)
+    }
+
+    /// Most CSPR `user` can deposit under the caps and allowlist; zero while the token is paused
+    pub fn max_deposit(&self, user: Address) -> U512 {
+        if self.token().is_paused() {
+            return U512::zero();
+        }
+        self.deposit_guard.remaining(user, self.get_tvl(), self.holdings(user))
+    }
+
+    /// Most CSPR `owner` can request to withdraw; zero while the token is paused
+    pub fn max_withdraw(&self, owner: Address) -> U512 {
+        let token = self.token();
+        if token.is_paused() {
+            return U512::zero();
+        }
+        self.convert_to_assets(token.balance_of(owner))
+    }
+
+    // ===== DEPOSIT LIMITS =====
+
+    /// Join the allowlist with an attestation signed by the attestation signer
+    pub fn allowlist_with_attestation(&mut self, signature: Bytes) {
+        let caller = self.env().caller();
+        self.deposit_guard.attest(caller, signature);
+    }
+
+    /// Set the minimum deposit and the TVL and per-account caps (admin only)
+    pub fn set_deposit_limits(&mut self, min_deposit: U512, tvl_cap: Option<U512>, account_cap: Option<U512>) {
+        self.assert_role(Role::Admin);
+        self.deposit_guard.set_limits(min_deposit, tvl_cap, account_cap);
+    }
+
+    /// Turn allowlist mode on or off (admin only)
+    pub fn set_allowlist_enabled(&mut self, enabled: bool) {
+        self.assert_role(Role::Admin);
+        self.deposit_guard.set_allowlist_enabled(enabled);
+    }
+
+    /// Add or remove an account from the allowlist (admin only)
+    pub fn set_allowlisted(&mut self, account: Address, allowed: bool) {
+        self.assert_role(Role::Admin);
+        self.deposit_guard.set_allowlisted(account, allowed);
+    }
+
+    /// Set or clear the key whose attestations allowlist accounts (admin only)
+    pub fn set_attestation_signer(&mut self, signer: Option<PublicKey>) {
+        self.assert_role(Role::Admin);
+        self.deposit_guard.set_attestation_signer(signer);
+    }
+
+    /// Get the smallest accepted deposit
+    pub fn get_min_deposit(&self) -> U512 {
+        self.deposit_guard.min_deposit()
+    }
+
+    /// Get the TVL cap, if any
+    pub fn get_tvl_cap(&self) -> Option<U512> {
+        self.deposit_guard.tvl_cap()
+    }
+
+    /// Get the per-account cap, if any
+    pub fn get_account_cap(&self) -> Option<U512> {
+        self.deposit_guard.account_cap()
+    }
+
+    /// Check if allowlist mode is on
+    pub fn is_allowlist_enabled(&self) -> bool {
+        self.deposit_guard.allowlist_enabled()
+    }
+
+    /// Check if an account is allowlisted
+    pub fn is_allowlisted(&self, account: Address) -> bool {
+        self.deposit_guard.is_allowlisted(account)
+    }
+
+    /// Get the epoch attestations for `account` are signed for; revoking the account starts a new one
+    pub fn get_attestation_epoch(&self, account: Address) -> u64 {
+        self.deposit_guard.attestation_epoch(account)
+    }
+
+    /// Get the message the attestation signer signs to allowlist `account` in its current epoch
+    pub fn get_attestation_message(&self, account: Address) -> Bytes {
+        self.deposit_guard.attestation_message(account)
+    }
+
+    // ===== REFERRALS =====
+
+    /// Claim the caller's referral fees as stCSPR
+    /// Returns stCSPR paid out
+    pub fn claim_referral_fees(&mut self) -> U256 {
+        let referrer = self.env().caller();
+        let stcspr_amount = self.referrals.claim(referrer);
+        self.token().transfer(referrer, stcspr_amount);
+        stcspr_amount
+    }
+
+    /// Called by the token, once the vault is set as its transfer hook, when `from` transfers stCSPR
+    /// Caps their referred stake at `balance`
+    pub fn on_stcspr_transfer(&mut self, from: Address, balance: U256) {
+        if Some(self.env().caller()) != self.token.get() {
+            self.env().revert(VaultError::NotToken);
+        }
+        self.referrals.sync(from, balance);
+    }
+
+    /// Get the referrer a user's deposits are credited to, if any
+    pub fn get_referrer(&self, user: Address) -> Option<Address> {
+        self.referrals.referrer_of(user)
+    }
+
+    /// Get a referrer's volume, referred users, active stake and claimable fees
+    pub fn get_referrer_stats(&self, referrer: Address) -> ReferrerStats {
+        self.referrals.stats(referrer)
+    }
+
+    /// Get the CSPR value of a referrer's active stake
+    pub fn get_referred_stake(&self, referrer: Address) -> U512 {
+        self.stcspr_to_cspr(self.referrals.active_shares(referrer))
+    }
+
+    /// Get stCSPR a referrer can claim
+    pub fn get_claimable_referral_fees(&self, referrer: Address) -> U256 {
+        self.referrals.claimable(referrer)
+    }
+
+    /// Get the share of the performance fee paid to referrers (bps)
+    pub fn get_referral_fee_share(&self) -> u32 {
+        self.referrals.fee_share_bps()
+    }
+
+    // ===== ADMIN FUNCTIONS =====
+
+    /// Queue a fee, treasury or timelock change (admin only)
+    /// Returns timelock operation ID
+    pub fn schedule_admin_call(&mut self, call: VaultAdminCall) -> u64 {
+        self.assert_role(Role::Admin);
+        self.validate_admin_call(&call);
+        let bytes = call.to_bytes().unwrap_or_revert_with(&self.env(), VaultError::InvalidAdminCall);
+        self.timelock.schedule(Bytes::from(bytes))
+    }
+
+    /// Apply a queued admin call once its delay has passed (admin only)
+    pub fn execute_admin_call(&mut self, operation_id: u64) {
+        self.assert_role(Role::Admin);
+        let bytes = self.timelock.execute(operation_id);
+        let (call, _) = VaultAdminCall::from_bytes(bytes.as_slice())
+            .unwrap_or_revert_with(&self.env(), VaultError::InvalidAdminCall);
+        self.validate_admin_call(&call);
+
+        match call {
+            VaultAdminCall::SetPerformanceFee(new_fee_bps) => {
+                self.performance_fee_bps.set(new_fee_bps);
+            }
+            VaultAdminCall::SetTreasury(new_treasury) => {
+                self.treasury.set(new_treasury);
+            }
+            VaultAdminCall::SetTimelockDelay(min_delay) => {
+                self.timelock.set_min_delay(min_delay);
+            }
+            VaultAdminCall::SetManagementFee(new_fee_bps) => {
+                // Charge what accrued at the old rate first
+                self.accrue_management_fee();
+                self.management_fee_bps.set(new_fee_bps);
+            }
+            VaultAdminCall::SetFeeRecipients(recipients) => {
+                self.accrue_management_fee();
+                self.fee_recipients.set(recipients);
+            }
+            VaultAdminCall::SetReferralFeeShare(fee_share_bps) => {
+                self.referrals.set_fee_share(fee_share_bps);
+            }
+        }
+    }
+
+    /// Cancel a queued admin call (guardian only)
+    pub fn cancel_admin_call(&mut self, operation_id: u64) {
+        self.assert_role(Role::Guardian);
+        self.timelock.cancel(operation_id);
+    }
+
+    /// Get a queued admin call
+    pub fn get_admin_call(&self, operation_id: u64) -> Option<TimelockOperation> {
+        self.timelock.get_operation(operation_id)
+    }
+
+    /// Get the timelock's minimum delay (ms)
+    pub fn get_timelock_delay(&self) -> u64 {
+        self.timelock.min_delay()
+    }
+
+    /// Set buffer target and instant-exit fee curve (admin only)
+    pub fn set_buffer_config(&mut self, target_buffer_bps: u32, min_fee_bps: u32, max_fee_bps: u32) {
+        self.assert_role(Role::Admin);
+        if target_buffer_bps > 10000 {
+            self.env().revert(VaultError::BufferTargetTooHigh);
+        }
+        if min_fee_bps > max_fee_bps {
+            self.env().revert(VaultError::MinFeeAboveMaxFee);
+        }
+        if max_fee_bps > 1000 {
+            self.env().revert(VaultError::FeeTooHigh);
+        }
+
+        self.buffer_config.set(BufferConfig {
+            target_buffer_bps,
+            min_fee_bps,
+            max_fee_bps,
+        });
+    }
+
+    /// Link the standalone stCSPR token (admin only, once)
+    /// The token must have been deployed with this vault as its minter. Inline stCSPR left by
+    /// storage v1 is minted to the vault with the pool backing it, for holders to migrate
+    pub fn set_token(&mut self, token: Address) {
+        self.assert_role(Role::Admin);
+        if self.token.get().is_some() {
+            self.env().revert(VaultError::TokenAlreadySet);
+        }
+
+        let token_vault = StCSPRTokenContractRef::new(self.env(), token).get_vault();
+        if token_vault != self.env().self_address() {
+            self.env().revert(VaultError::ForeignToken);
+        }
+        self.token.set(token);
+
+        let legacy_supply = self.legacy_total_supply.get_or_default();
+        if !legacy_supply.is_zero() {
+            let backing = math::to_u256(self.total_cspr.get_or_default()).unwrap_or_revert(&self.env());
+            self.token().seed_supply(self.env().self_address(), legacy_supply, backing);
+        }
+
+        self.env().emit_event(TokenLinked {
+            token,
+            timestamp: self.env().get_block_time(),
+        });
+    }
+
+    /// Allow or stop a legacy contract migrating positions in (admin only)
+    pub fn set_migration_source(&mut self, source: Address, enabled: bool) {
+        self.assert_role(Role::Admin);
+        self.migration.set_source(source, enabled);
+    }
+
+    /// Allow attested migrations from a deployed legacy contract of `kind`, or stop them with `None` (admin only)
+    pub fn set_attested_migration_source(&mut self, source: Address, kind: Option<LegacyKind>) {
+        self.assert_role(Role::Admin);
+        self.migration.set_attested_source(source, kind);
+    }
+
+    /// Set the number of matching reports that finalize an era (admin only)
+    pub fn set_oracle_quorum(&mut self, quorum: u32) {
+        self.assert_role(Role::Admin);
+        self.oracle.set_quorum(quorum);
+    }
+
+    /// Set the rate limits finalized reports are checked against (admin only)
+    pub fn set_rate_limits(&mut self, max_apr_bps: u32, max_decrease_bps: u32, min_report_interval: u64) {
+        self.assert_role(Role::Admin);
+        let limits = RateLimits { max_apr_bps, max_decrease_bps, min_report_interval };
+        limits.validate().unwrap_or_revert(&self.env());
+        self.rate_limits.set(limits);
+        // The first increase is measured from when limits came into force
+        if self.last_report_time.get().is_none() {
+            self.last_report_time.set(self.env().get_block_time());
+        }
+
+        self.env().emit_event(RateLimitsUpdated {
+            max_apr_bps,
+            max_decrease_bps,
+            min_report_interval,
+            timestamp: self.env().get_block_time(),
+        });
+    }
+
+    /// Set the era length (ms) and number of unbonding eras (admin only)
+    /// Applies to new withdrawal requests; queued requests keep their unlock era
+    pub fn set_unbonding_config(&mut self, era_duration: u64, unbonding_eras: u64) {
+        self.assert_role(Role::Admin);
+        self.delegation.set_era_config(era_duration, unbonding_eras);
+    }
+
+    // ===== ACCESS CONTROL =====
+
+    delegate! {
+        to self.access {
+            fn grant_role(&mut self, role: Role, account: Address);
+            fn revoke_role(&mut self, role: Role, account: Address);
+            fn renounce_role(&mut self, role: Role, account: Address);
+            fn set_role_admin(&mut self, role: Role, admin_role: Role);
+            fn has_role(&self, role: Role, account: Address) -> bool;
+            fn transfer_ownership(&mut self, new_owner: Address);
+            fn accept_ownership(&mut self);
+            fn get_owner(&self) -> Address;
+            fn get_pending_owner(&self) -> Option<Address>;
+            fn get_role_admin(&self, role: Role) -> Role;
+        }
+    }
+
+    // ===== INTERNAL =====
+
+    /// Take a deposit of `cspr_amount` and mint stCSPR for it to `user`
+    fn deposit_from(&mut self, user: Address, cspr_amount: U512) -> U256 {
+        if cspr_amount < self.deposit_guard.min_deposit() {
+            self.env().revert(VaultError::BelowMinimumDeposit);
+        }
+
+        self.accrue_management_fee();
+        self.deposit_guard.check(user, cspr_amount, self.get_tvl(), self.holdings(user));
+        let stcspr_amount = self.mint_for_deposit(user, cspr_amount);
+
+        self.env().emit_event(Deposited {
+            user,
+            cspr_amount,
+            stcspr_amount,
+            exchange_rate: self.get_exchange_rate(),
+            timestamp: self.env().get_block_time(),
+        });
+
+        // New liquidity serves the withdrawal queue first
+        self.process_queue();
+
+        stcspr_amount
+    }
+
+    /// Burn `owner`'s stCSPR (through `spender`'s allowance if set) and queue its CSPR for `owner`
+    fn queue_withdrawal(&mut self, owner: Address, spender: Option<Address>, stcspr_amount: U256) -> u64 {
+        if stcspr_amount.is_zero() {
+            self.env().revert(VaultError::ZeroAmount);
+        }
+
+        // Burn stCSPR immediately; the token prices it at the current exchange rate
+        self.accrue_management_fee();
+        let cspr_amount = match spender {
+            Some(spender) => self.token().burn_from(owner, spender, stcspr_amount),
+            None => self.token().burn(owner, stcspr_amount),
+        };
+        let cspr_amount = math::to_u512(cspr_amount);
+        self.referrals.release(owner, stcspr_amount);
+
+        // The CSPR leaves the pool and becomes a queued liability
+        let total_cspr = math::checked_sub(self.total_cspr.get_or_default(), cspr_amount)
+            .unwrap_or_revert(&self.env());
+        self.total_cspr.set(total_cspr);
+
+        // Append to the withdrawal queue
+        let current_time = self.env().get_block_time();
+        let unlock_era = self.delegation.unlock_era();
+        let unlock_time = self.delegation.era_start_time(unlock_era);
+        let withdrawal_id = self.withdrawal_queue.enqueue(
+            owner,
+            stcspr_amount,
+            cspr_amount,
+            unlock_era,
+            unlock_time,
+        );
+
+        self.env().emit_event(WithdrawalRequested {
+            withdrawal_id,
+            user: owner,
+            stcspr_amount,
+            cspr_amount,
+            unlock_era,
+            unlock_time,
+            timestamp: current_time,
+        });
+
+        // Idle liquidity is handed out immediately, in queue order
+        self.process_queue();
+
+        withdrawal_id
+    }
+
+    /// Add CSPR to the pool and mint stCSPR for it at the current exchange rate
+    fn mint_for_deposit(&mut self, to: Address, cspr_amount: U512) -> U256 {
+        // Update vault CSPR
+        self.total_cspr.set(self.total_cspr.get_or_default() + cspr_amount);
+
+        // The token converts at its exchange rate
+        let cspr_amount = math::to_u256(cspr_amount).unwrap_or_revert(&self.env());
+        self.token().mint(to, cspr_amount)
+    }
+
+    /// CSPR value of an account's stCSPR, as counted against the per-account cap
+    fn holdings(&self, account: Address) -> U512 {
+        self.stcspr_to_cspr(self.token().balance_of(account))
+    }
+
+    /// Approval a withdrawal permit signs for: spender and ticket
+    fn withdrawal_permit_payload(&self, spender: Address, withdrawal_id: u64) -> Bytes {
+        let mut payload = spender.to_bytes().unwrap_or_revert(&self.env());
+        payload.extend(withdrawal_id.to_bytes().unwrap_or_revert(&self.env()));
+        Bytes::from(payload)
+    }
+
+    /// Reference to the linked stCSPR token
+    fn token(&self) -> StCSPRTokenContractRef {
+        let token = self.token.get().unwrap_or_revert_with(&self.env(), VaultError::TokenNotSet);
+        StCSPRTokenContractRef::new(self.env(), token)
+    }
+
+    /// Mint stCSPR to `user` for CSPR migrated in from `source`
+    fn credit_migration(&mut self, source: Address, user: Address, cspr_amount: U512) -> U256 {
+        self.accrue_management_fee();
+        // Migrated positions may be below the minimum, but the allowlist and caps still apply
+        self.deposit_guard.check(user, cspr_amount, self.get_tvl(), self.holdings(user));
+        let stcspr_amount = self.mint_for_deposit(user, cspr_amount);
+
+        self.env().emit_event(PositionMigrated {
+            source,
+            user,
+            cspr_amount,
+            stcspr_amount,
+            exchange_rate: self.get_exchange_rate(),
+            timestamp: self.env().get_block_time(),
+        });
+
+        self.process_queue();
+
+        stcspr_amount
+    }
+
+    /// Bring storage from `version - 1` to `version`
+    fn migrate_storage(&mut self, version: u32) {
+        self.env().emit_event(StorageMigrated {
+            version,
+            timestamp: self.env().get_block_time(),
+        });
+
+        match version {
+            // v1: versioning introduced; backfill state added by later features
+            1 => {
+                if self.buffer_config.get().is_none() {
+                    self.buffer_config.set(default_buffer_config());
+                }
+                if self.timelock.min_delay() == 0 {
+                    self.timelock.init_delay(timelock::DEFAULT_MIN_DELAY);
+                }
+            }
+            // v2: stCSPR moved to the standalone token, which is deployed and linked after the
+            // upgrade. Inline balances stay in the legacy fields until `set_token` seeds their
+            // supply into the token and `migrate_legacy_stcspr` credits each holder
+            2 => {}
+            // v3: rewards come from oracle reports; grant `Role::Oracle` to reporters
+            3 => {
+                if self.oracle.quorum() == 0 {
+                    self.oracle.set_quorum(oracle::DEFAULT_QUORUM);
+                }
+            }
+            _ => self.env().revert(VaultError::NoStorageMigration),
+        }
+    }
+
+    /// Pooled CSPR held by the vault itself (not delegated, not unbonding)
+    /// This is what can be delegated without starving the withdrawal queue
+    fn idle_cspr(&self) -> U512 {
+        let committed = self.delegation.total_delegated() + self.delegation.total_unbonding();
+        let total = self.total_cspr.get_or_default();
+        if total > committed { total - committed } else { U512::zero() }
+    }
+
+    /// CSPR the vault should physically hold: idle pool plus queued and reserved withdrawals
+    fn booked_balance(&self) -> U512 {
+        let committed = self.delegation.total_delegated() + self.delegation.total_unbonding();
+        let held = self.total_cspr.get_or_default()
+            + self.withdrawal_queue.total_queued()
+            + self.withdrawal_queue.total_reserved();
+        if held > committed { held - committed } else { U512::zero() }
+    }
+
+    /// Liquid CSPR not yet reserved for withdrawal claims
+    fn unreserved_liquidity(&self) -> U512 {
+        let reserved = self.withdrawal_queue.total_reserved();
+        let booked = self.booked_balance().saturating_sub(reserved);
+        let balance = self.env().self_balance();
+        let held = if balance > reserved { balance - reserved } else { U512::zero() };
+        if held < booked { held } else { booked }
+    }
+
+    /// Idle CSPR that can leave instantly without touching queued or reserved withdrawals
+    fn instant_liquidity(&self) -> U512 {
+        let idle = self.idle_cspr();
+        let liquid = self.unreserved_liquidity();
+        if idle < liquid { idle } else { liquid }
+    }
+
+    /// Price an instant exit against the buffer level left after it
+    fn instant_withdraw_quote(&self, stcspr_amount: U256) -> InstantWithdrawQuote {
+        let config = self.get_buffer_config();
+        let gross = self.stcspr_to_cspr(stcspr_amount);
+        let available_liquidity = self.instant_liquidity();
+        let target = self.get_buffer_target();
+
+        let remaining = if available_liquidity > gross { available_liquidity - gross } else { U512::zero() };
+        let fee_bps = if target.is_zero() || remaining >= target {
+            config.min_fee_bps
+        } else {
+            // Linear in the buffer shortfall after the exit
+            let spread = U512::from(config.max_fee_bps - config.min_fee_bps);
+            let shortfall = target - remaining;
+            let step = math::mul_div_u512(spread, shortfall, target, Rounding::Down)
+                .unwrap_or_revert(&self.env());
+            config.min_fee_bps + step.as_u32()
+        };
+
+        // Fees round up so an exit never pays less than its quoted rate
+        let fee = math::apply_bps(gross, fee_bps, Rounding::Up).unwrap_or_revert(&self.env());
+        InstantWithdrawQuote {
+            cspr_amount: gross - fee,
+            fee,
+            fee_bps,
+            available_liquidity,
+        }
+    }
+
+    /// Apply a finalized report, or hold it for guardian review if it breaks the rate limits
+    /// Reports arriving before the minimum interval are refused outright
+    fn review_report(&mut self, report: EraReport) {
+        // Judge the report against the rate after the management fee, as it will be applied
+        self.accrue_management_fee();
+        if let Some(limits) = self.rate_limits.get() {
+            let token = self.token();
+            let total_stcspr = token.total_supply();
+            if !total_stcspr.is_zero() {
+                let (_, _, new_total_cspr) = self.report_outcome(&report);
+                let new_total_cspr = math::to_u256(new_total_cspr).unwrap_or_revert(&self.env());
+                let current_rate = token.get_exchange_rate();
+                let proposed_rate = math::share_price(new_total_cspr, total_stcspr)
+                    .unwrap_or_revert(&self.env());
+                let now = self.env().get_block_time();
+                let elapsed = now.saturating_sub(self.last_report_time.get_or_default());
+
+                match limits.check(current_rate, proposed_rate, elapsed) {
+                    Ok(()) => {}
+                    // Nothing suspicious about the rate: the oracle reports again later
+                    Err(RateLimitError::ReportTooSoon) => self.env().revert(RateLimitError::ReportTooSoon),
+                    Err(_) => {
+                        self.env().emit_event(ReportHeld {
+                            era: report.era,
+                            current_rate,
+                            proposed_rate,
+                            elapsed,
+                            timestamp: now,
+                        });
+                        self.held_report.set(Some(report));
+                        return;
+                    }
+                }
+            }
+        }
+        self.apply_report(report);
+    }
+
+    /// Performance fee, queue share of the loss and resulting pooled CSPR if `report` were booked now
+    /// Slashing is split pro rata between the pool and withdrawals still waiting to unbond
+    fn report_outcome(&self, report: &EraReport) -> (U512, U512, U512) {
+        let fee_bps = self.performance_fee_bps.get_or_default();
+        let protocol_fee = math::apply_bps(report.rewards, fee_bps, Rounding::Down)
+            .unwrap_or_revert(&self.env());
+
+        let pooled = self.total_cspr.get_or_default();
+        let queued = self.withdrawal_queue.total_queued();
+        let queue_loss = if report.slashed.is_zero() || queued.is_zero() {
+            U512::zero()
+        } else {
+            math::mul_div_u512(report.slashed, queued, pooled + queued, Rounding::Down)
+                .unwrap_or_revert(&self.env())
+        };
+
+        let total_cspr = pooled + (report.rewards - protocol_fee);
+        let total_cspr = math::checked_sub(total_cspr, report.slashed - queue_loss)
+            .unwrap_or_revert(&self.env());
+        (protocol_fee, queue_loss, total_cspr)
+    }
+
+    /// Remove and return the held report
+    fn take_held_report(&mut self) -> EraReport {
+        let report = self.held_report.get().flatten()
+            .unwrap_or_revert_with(&self.env(), VaultError::NoHeldReport);
+        self.held_report.set(None);
+        report
+    }
+
+    /// Book a finalized report: rewards net of the performance fee raise the rate, slashing lowers it
+    /// The fee stays staked, minted to the treasury as stCSPR at the post-reward rate
+    /// Rewards are still held by the auction, so they are booked as delegated rather than idle
+    fn apply_report(&mut self, report: EraReport) {
+        self.accrue_management_fee();
+        let (protocol_fee, queue_share, _) = self.report_outcome(&report);
+        let user_rewards = report.rewards - protocol_fee;
+        let mut total_cspr = self.total_cspr.get_or_default() + user_rewards;
+
+        if !report.slashed.is_zero() {
+            // Rounding can leave the queue a few motes over its share; the pool is spared them
+            let queue_loss = self.withdrawal_queue.absorb_loss(queue_share);
+            let pool_loss = report.slashed.saturating_sub(queue_loss);
+            total_cspr = math::checked_sub(total_cspr, pool_loss).unwrap_or_revert(&self.env());
+
+            self.env().emit_event(LossSocialised {
+                era: report.era,
+                slashed: report.slashed,
+                pool_loss,
+                queue_loss,
+                timestamp: self.env().get_block_time(),
+            });
+        }
+
+        self.total_cspr.set(total_cspr);
+        self.delegation.book_auction_amounts();
+        self.last_report_time.set(self.env().get_block_time());
+        self.sync_exchange_rate();
+
+        if !report.rewards.is_zero() {
+            let fee_shares = self.mint_protocol_fee(protocol_fee);
+
+            self.env().emit_event(RewardsCompounded {
+                total_rewards: report.rewards,
+                protocol_fee,
+                user_rewards,
+                fee_shares,
+                timestamp: self.env().get_block_time(),
+            });
+        }
+
+        self.process_queue();
+    }
+
+    /// Revert unless `report` accounts for the auction exactly: its delegation is what the auction holds,
+    /// and rewards less slashing are what that holds above the booked principal
+    fn assert_report_matches_auction(&self, report: &EraReport) {
+        if report.total_delegated != self.delegation.total_delegated_amount() {
+            self.env().revert(VaultError::ReportDelegationMismatch);
+        }
+        if report.rewards + self.delegation.total_delegated() != report.total_delegated + report.slashed {
+            self.env().revert(VaultError::ReportAmountsMismatch);
+        }
+    }
+
+    /// Mint stCSPR worth `protocol_fee` at the current rate and split it between fee recipients
+    /// Referrers' cut stays with the vault until claimed; a fee too small for a single share is left to stakers
+    fn mint_protocol_fee(&mut self, protocol_fee: U512) -> U256 {
+        if protocol_fee.is_zero() {
+            return U256::zero();
+        }
+        let fee = math::to_u256(protocol_fee).unwrap_or_revert(&self.env());
+        if self.token().cspr_to_stcspr(fee).is_zero() {
+            self.total_cspr.set(self.total_cspr.get_or_default() + protocol_fee);
+            self.sync_exchange_rate();
+            return U256::zero();
+        }
+        let fee_shares = self.mint_for_deposit(self.env().self_address(), protocol_fee);
+        let total_shares = self.token().total_supply();
+        let referral_cut = self.referrals.accrue(fee_shares, total_shares);
+        self.distribute_fee_shares(fee_shares - referral_cut);
+        fee_shares
+    }
+
+    /// Management fee accrued on pooled CSPR since the last charge
+    fn pending_management_fee(&self) -> U512 {
+        let fee_bps = self.management_fee_bps.get_or_default();
+        let last = match self.last_fee_accrual.get() {
+            Some(last) if fee_bps > 0 => last,
+            _ => return U512::zero(),
+        };
+        let elapsed = self.env().get_block_time().saturating_sub(last);
+        math::mul_div_u512(
+            self.total_cspr.get_or_default(),
+            U512::from(fee_bps) * U512::from(elapsed),
+            U512::from(math::BPS_DENOMINATOR) * U512::from(rate_limits::YEAR_MILLIS),
+            Rounding::Down,
+        ).unwrap_or_revert(&self.env())
+    }
+
+    /// stCSPR the pending management fee mints, and the totals it leaves
+    /// The fee dilutes holders: its CSPR stays pooled, backing the newly minted shares
+    fn pending_fee_shares(&self) -> (U256, U256, U256) {
+        let token = self.token();
+        let total_cspr = token.get_total_cspr_backing();
+        let total_stcspr = token.total_supply();
+        let fee = math::to_u256(self.pending_management_fee()).unwrap_or_revert(&self.env());
+        if fee.is_zero() || total_stcspr.is_zero() || fee >= total_cspr {
+            return (U256::zero(), total_cspr, total_stcspr);
+        }
+        let fee_shares = math::assets_to_shares(fee, total_cspr - fee, total_stcspr, Rounding::Down)
+            .unwrap_or_revert(&self.env());
+        (fee_shares, total_cspr, total_stcspr + fee_shares)
+    }
+
+    /// Token backing and supply once the pending management fee is charged
+    fn accrued_totals(&self) -> (U256, U256) {
+        let (_, total_cspr, total_stcspr) = self.pending_fee_shares();
+        (total_cspr, total_stcspr)
+    }
+
+    /// Charge the management fee accrued since the last charge
+    /// Mints the fee shares and splits them between fee recipients
+    fn accrue_management_fee(&mut self) {
+        let now = self.env().get_block_time();
+        let elapsed = now.saturating_sub(self.last_fee_accrual.get().unwrap_or(now));
+        if self.management_fee_bps.get_or_default() == 0 || self.token().total_supply().is_zero() {
+            self.last_fee_accrual.set(now);
+            return;
+        }
+
+        // Leave the clock running until the fee is worth a share and the token can mint
+        let (fee_shares, _, _) = self.pending_fee_shares();
+        if fee_shares.is_zero() || self.token().is_paused() {
+            return;
+        }
+
+        // The fee's CSPR stays pooled; the shares dilute holders without a rate update in between
+        let fee = self.pending_management_fee();
+        self.token().mint_shares(self.env().self_address(), fee_shares);
+        self.last_fee_accrual.set(now);
+
+        self.env().emit_event(ManagementFeeAccrued {
+            cspr_amount: fee,
+            stcspr_amount: fee_shares,
+            elapsed,
+            timestamp: now,
+        });
+
+        self.distribute_fee_shares(fee_shares);
+    }
+
+    /// Send fee shares held by the vault to the fee recipients by weight
+    /// Rounding dust goes to the first recipient
+    fn distribute_fee_shares(&mut self, fee_shares: U256) {
+        let recipients = self.get_fee_recipients();
+        let total_weight: u64 = recipients.iter().map(|r| u64::from(r.weight)).sum();
+
+        let mut token = self.token();
+        let mut remaining = fee_shares;
+        for (i, recipient) in recipients.iter().enumerate().rev() {
+            let amount = if i == 0 {
+                remaining
+            } else {
+                math::mul_div(fee_shares, U256::from(recipient.weight), U256::from(total_weight), Rounding::Down)
+                    .unwrap_or_revert(&self.env())
+            };
+            if amount.is_zero() {
+                continue;
+            }
+            remaining -= amount;
+            token.transfer(recipient.account, amount);
+
+            self.env().emit_event(FeeDistributed {
+                recipient: recipient.account,
+                weight: recipient.weight,
+                stcspr_amount: amount,
+                timestamp: self.env().get_block_time(),
+            });
+        }
+    }
+
+    /// Push pooled CSPR to the token, which recomputes the exchange rate from its supply
+    fn sync_exchange_rate(&mut self) {
+        let total_cspr = math::to_u256(self.total_cspr.get_or_default()).unwrap_or_revert(&self.env());
+        self.token().update_exchange_rate(total_cspr);
+    }
+
+    /// Hand unreserved liquidity to the withdrawal queue
+    fn process_queue(&mut self) -> U512 {
+        let liquidity = self.unreserved_liquidity();
+        self.withdrawal_queue.fulfil(liquidity)
+    }
+
+    /// Estimate when `amount` of queued CSPR will be covered by liquidity
+    /// Walks the unbonding schedule era by era; `None` if it does not cover the amount
+    fn estimate_liquidity_time(&self, amount: U512) -> Option<u64> {
+        let liquid = self.unreserved_liquidity();
+        if amount <= liquid {
+            return Some(self.env().get_block_time());
+        }
+
+        let mut shortfall = amount - liquid;
+        let last_era = self.delegation.unlock_era();
+        for era in self.delegation.current_era()..=last_era {
+            let due = self.delegation.unbonding_for_era(era);
+            if due >= shortfall {
+                return Some(self.delegation.era_start_time(era));
+            }
+            shortfall -= due;
+        }
+        None
+    }
+
+    fn validate_admin_call(&self, call: &VaultAdminCall) {
+        match call {
+            VaultAdminCall::SetPerformanceFee(new_fee_bps) if *new_fee_bps > 1000 => {
+                self.env().revert(VaultError::FeeTooHigh);
+            }
+            VaultAdminCall::SetManagementFee(new_fee_bps) if *new_fee_bps > MAX_MANAGEMENT_FEE_BPS => {
+                self.env().revert(VaultError::FeeTooHigh);
+            }
+            VaultAdminCall::SetReferralFeeShare(fee_share_bps)
+                if *fee_share_bps > referrals::MAX_REFERRAL_FEE_SHARE_BPS =>
+            {
+                self.env().revert(VaultError::FeeTooHigh);
+            }
+            VaultAdminCall::SetFeeRecipients(recipients) => {
+                let vault = self.env().self_address();
+                if recipients.is_empty()
+                    || recipients.len() > MAX_FEE_RECIPIENTS
+                    || recipients.iter().any(|r| r.weight == 0 || r.account == vault)
+                {
+                    self.env().revert(VaultError::InvalidFeeRecipients);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn assert_role(&self, role: Role) {
+        self.access.check_role(role, self.env().caller());
+    }
+}
+
+/// Buffer settings for new deployments and storage backfills
+fn default_buffer_config() -> BufferConfig {
+    BufferConfig {
+        target_buffer_bps: 1000, // 10% of TVL
+        min_fee_bps: 10,         // 0.1%
+        max_fee_bps: 300,        // 3%
+    }
+}
+
+// ===== ERRORS =====
+
+#[odra::odra_error]
+pub enum VaultError {
+    BelowMinimumDeposit = 2000,
+    ZeroAmount = 2001,
+    NothingToMigrate = 2002,
+    NotWithdrawalOwner = 2003,
+    UnbondingNotComplete = 2004,
+    InsufficientBufferLiquidity = 2005,
+    SlippageExceeded = 2006,
+    InsufficientIdleCspr = 2007,
+    NoRewards = 2008,
+    FeeTooHigh = 2009,
+    BufferTargetTooHigh = 2010,
+    MinFeeAboveMaxFee = 2011,
+    InvalidAdminCall = 2012,
+    TokenAlreadySet = 2013,
+    ForeignToken = 2014,
+    TokenNotSet = 2015,
+    TreasuryNotSet = 2016,
+    BufferConfigNotSet = 2017,
+    CannotDowngradeStorage = 2018,
+    NoStorageMigration = 2019,
+    ReportDelegationMismatch = 2020,
+    ReportAmountsMismatch = 2021,
+    ReportAwaitingReview = 2022,
+    NoHeldReport = 2023,
+    InvalidFeeRecipients = 2024,
+    NotToken = 2025,
+}
+
+// ===== EVENTS =====
+
+#[odra::event]
+pub struct VaultInitialized {
+    pub owner: Address,
+    pub treasury: Address,
+    pub unbonding_period_days: u64,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct VaultUpgraded {
+    pub from_version: u32,
+    pub to_version: u32,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct StorageMigrated {
+    pub version: u32,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct Deposited {
+    pub user: Address,
+    pub cspr_amount: U512,
+    pub stcspr_amount: U256,
+    pub exchange_rate: U256,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct DepositedFor {
+    pub depositor: Address,
+    pub beneficiary: Address,
+    pub cspr_amount: U512,
+    pub stcspr_amount: U256,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct PositionMigrated {
+    pub source: Address,
+    pub user: Address,
+    pub cspr_amount: U512,
+    pub stcspr_amount: U256,
+    pub exchange_rate: U256,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct WithdrawalRequested {
+    pub withdrawal_id: u64,
+    pub user: Address,
+    pub stcspr_amount: U256,
+    pub cspr_amount: U512,
+    pub unlock_era: u64,
+    pub unlock_time: u64,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct WithdrawalRequestedFor {
+    pub withdrawal_id: u64,
+    pub owner: Address,
+    pub spender: Address,
+    pub stcspr_amount: U256,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct WithdrawalClaimed {
+    pub withdrawal_id: u64,
+    pub user: Address,
+    pub cspr_amount: U512,
+    pub remaining_amount: U512,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct InstantWithdrawal {
+    pub user: Address,
+    pub stcspr_amount: U256,
+    pub cspr_amount: U512,
+    pub fee: U512,
+    pub fee_bps: u32,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct RewardsCompounded {
+    pub total_rewards: U512,
+    pub protocol_fee: U512,
+    pub user_rewards: U512,
+    pub fee_shares: U256,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct ManagementFeeAccrued {
+    pub cspr_amount: U512,
+    pub stcspr_amount: U256,
+    pub elapsed: u64,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct FeeDistributed {
+    pub recipient: Address,
+    pub weight: u32,
+    pub stcspr_amount: U256,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct LossSocialised {
+    pub era: u64,
+    pub slashed: U512,
+    pub pool_loss: U512,
+    pub queue_loss: U512,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct RateLimitsUpdated {
+    pub max_apr_bps: u32,
+    pub max_decrease_bps: u32,
+    pub min_report_interval: u64,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct ReportHeld {
+    pub era: u64,
+    pub current_rate: U256,
+    pub proposed_rate: U256,
+    pub elapsed: u64,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct HeldReportReleased {
+    pub era: u64,
+    pub guardian: Address,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct HeldReportRejected {
+    pub era: u64,
+    pub guardian: Address,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct TokenLinked {
+    pub token: Address,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct LegacyStCSPRMigrated {
+    pub holder: Address,
+    pub stcspr_amount: U256,
+    pub timestamp: u64,
+}
+
+// ===== TESTS =====
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::access_control::AccessControlError;
+    use crate::delegation::DelegationError;
+    use crate::deposit_limits::DepositLimitError;
+    use crate::migration::MigrationError;
+    use crate::oracle::OracleError;
+    use crate::permit::PermitError;
+    use crate::referrals::{ReferralError, ReferredDeposit};
+    use crate::stcspr_token::{StCSPRToken, StCSPRTokenHostRef, StCSPRTokenInitArgs, TokenError};
+    use crate::timelock::TimelockError;
+    use odra::host::{Deployer, HostEnv, HostRef, InstallConfig, NoArgs};
+
+    /// Deploy a vault and its stCSPR token, linked
+    fn deploy_vault(
+        env: &HostEnv,
+        treasury: Address,
+        unbonding_days: u64,
+    ) -> (StakeFlowVaultV3HostRef, StCSPRTokenHostRef) {
+        let mut vault = StakeFlowVaultV3::deploy(env, StakeFlowVaultV3InitArgs {
+            treasury_address: treasury,
+            unbonding_days,
+        });
+        let token = link_token(env, &mut vault);
+        (vault, token)
+    }
+
+    /// Deploy an stCSPR token minted by `vault` and link it
+    fn link_token(env: &HostEnv, vault: &mut StakeFlowVaultV3HostRef) -> StCSPRTokenHostRef {
+        let mut token = StCSPRToken::deploy(env, StCSPRTokenInitArgs { vault: vault.address() });
+        token.set_transfer_hook(Some(vault.address()));
+        vault.set_token(token.address());
+        token
+    }
+
+    /// Let the auction pay an era of rewards and finalize a report of them, with account 0 as the sole oracle
+    /// Idle CSPR is delegated to the first validator if nothing is staked yet; returns the rewards reported
+    fn report_rewards(env: &HostEnv, vault: &mut StakeFlowVaultV3HostRef) -> U512 {
+        let reporter = env.get_account(0);
+        env.set_caller(reporter);
+        if !vault.has_role(Role::Oracle, reporter) {
+            vault.grant_role(Role::Oracle, reporter);
+        }
+        if vault.get_total_staked().is_zero() {
+            let validator = env.get_validator(0);
+            if !vault.get_validators().contains(&validator) {
+                vault.add_validator(validator.clone());
+            }
+            vault.delegate_to_validator(validator, vault.get_idle_cspr());
+        }
+
+        let era = vault.get_current_era();
+        env.advance_with_auctions(vault.get_era_config().era_duration);
+        let total_delegated = vault.get_total_delegated_amount();
+        let rewards = total_delegated - vault.get_total_staked();
+        vault.submit_report(EraReport { era, total_delegated, rewards, slashed: U512::zero() });
+        rewards
+    }
+
+    /// Schedule an admin call as account 0 and execute it once the timelock allows
+    fn apply_admin_call(env: &HostEnv, vault: &mut StakeFlowVaultV3HostRef, call: VaultAdminCall) {
+        env.set_caller(env.get_account(0));
+        let operation_id = vault.schedule_admin_call(call);
+        env.advance_block_time(vault.get_timelock_delay());
+        vault.execute_admin_call(operation_id);
+    }
+
+    #[test]
+    fn test_deposit_and_mint() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (vault, token) = deploy_vault(&env, treasury, 7);
+
+        let amount = U512::from(100_000_000_000u64); // 100 CSPR
+        env.set_caller(env.get_account(1));
+        let stcspr = vault.with_tokens(amount).deposit();
+
+        assert_eq!(vault.get_tvl(), amount);
+        assert_eq!(token.balance_of(env.get_account(1)), stcspr);
+        assert_eq!(token.total_supply(), stcspr);
+    }
+
+    #[test]
+    fn test_deposit_caps_and_minimum() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+        let (alice, bob, carol) = (env.get_account(1), env.get_account(2), env.get_account(3));
+        let cspr = |n: u64| U512::from(n * 1_000_000_000);
+
+        vault.set_deposit_limits(cspr(20), Some(cspr(1000)), Some(cspr(600)));
+        assert_eq!(vault.get_min_deposit(), cspr(20));
+
+        env.set_caller(alice);
+        assert_eq!(
+            vault.with_tokens(cspr(15)).try_deposit(),
+            Err(VaultError::BelowMinimumDeposit.into())
+        );
+
+        // Per-account cap counts what the account already holds
+        vault.with_tokens(cspr(500)).deposit();
+        assert_eq!(vault.max_deposit(alice), cspr(100));
+        assert_eq!(
+            vault.with_tokens(cspr(200)).try_deposit(),
+            Err(DepositLimitError::AccountCapExceeded.into())
+        );
+
+        // The TVL cap binds everyone
+        env.set_caller(bob);
+        vault.with_tokens(cspr(400)).deposit();
+        assert_eq!(vault.max_deposit(carol), cspr(100));
+        env.set_caller(carol);
+        assert_eq!(
+            vault.with_tokens(cspr(200)).try_deposit(),
+            Err(DepositLimitError::TvlCapExceeded.into())
+        );
+        vault.with_tokens(cspr(100)).deposit();
+        assert_eq!(vault.get_tvl(), cspr(1000));
+    }
+
+    #[test]
+    fn test_allowlist_mode_with_attestation() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+        let (alice, bob, carol) = (env.get_account(1), env.get_account(2), env.get_account(3));
+        let signer = env.get_account(5);
+        let amount = U512::from(100_000_000_000u64);
+
+        vault.set_allowlist_enabled(true);
+        vault.set_attestation_signer(Some(env.public_key(&signer)));
+
+        env.set_caller(alice);
+        assert_eq!(vault.max_deposit(alice), U512::zero());
+        assert_eq!(vault.with_tokens(amount).try_deposit(), Err(DepositLimitError::NotAllowlisted.into()));
+
+        // Allowlisted by an admin
+        env.set_caller(env.get_account(0));
+        vault.set_allowlisted(alice, true);
+        env.set_caller(alice);
+        vault.with_tokens(amount).deposit();
+
+        // Allowlisted by a signed attestation for this vault and account
+        let attestation = env.sign_message(&vault.get_attestation_message(bob), &signer);
+        env.set_caller(bob);
+        vault.allowlist_with_attestation(attestation.clone());
+        assert!(vault.is_allowlisted(bob));
+        vault.with_tokens(amount).deposit();
+
+        // Someone else's attestation does not work
+        env.set_caller(carol);
+        assert_eq!(
+            vault.try_allowlist_with_attestation(attestation.clone()),
+            Err(DepositLimitError::InvalidAttestation.into())
+        );
+        assert!(!vault.is_allowlisted(carol));
+
+        // Once revoked, the old attestation cannot re-add the account; a fresh one can
+        env.set_caller(env.get_account(0));
+        vault.set_allowlisted(bob, false);
+        assert_eq!(vault.get_attestation_epoch(bob), 1);
+        env.set_caller(bob);
+        assert_eq!(
+            vault.try_allowlist_with_attestation(attestation),
+            Err(DepositLimitError::InvalidAttestation.into())
+        );
+        assert!(!vault.is_allowlisted(bob));
+        let attestation = env.sign_message(&vault.get_attestation_message(bob), &signer);
+        vault.allowlist_with_attestation(attestation);
+        assert!(vault.is_allowlisted(bob));
+    }
+
+    #[test]
+    fn test_allowlist_mode_applies_to_migrations() {
+        use crate::stakeflow_minimal::StakeFlowMinimal;
+
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let alice = env.get_account(1);
+        let mut minimal = StakeFlowMinimal::deploy(&env, NoArgs);
+        let (mut vault, token) = deploy_vault(&env, treasury, 7);
+        vault.set_attested_migration_source(minimal.address(), Some(LegacyKind::Minimal));
+        vault.set_allowlist_enabled(true);
+
+        // A legacy position is no way around the allowlist
+        let amount = U512::from(100_000_000_000u64);
+        env.set_caller(alice);
+        minimal.with_tokens(amount).deposit();
+        vault.attest_legacy_position(minimal.address());
+        minimal.withdraw(amount);
+        assert_eq!(
+            vault.with_tokens(amount).try_migrate_attested_position(minimal.address()),
+            Err(DepositLimitError::NotAllowlisted.into())
+        );
+
+        env.set_caller(env.get_account(0));
+        vault.set_allowlisted(alice, true);
+        env.set_caller(alice);
+        let stcspr_amount = vault.with_tokens(amount).migrate_attested_position(minimal.address());
+        assert_eq!(token.balance_of(alice), stcspr_amount);
+    }
+
+    #[test]
+    fn test_withdrawal_queue() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, token) = deploy_vault(&env, treasury, 7);
+
+        // Deposit
+        let amount = U512::from(100_000_000_000u64);
+        env.set_caller(env.get_account(1));
+        let stcspr = vault.with_tokens(amount).deposit();
+
+        // Request withdrawal
+        let withdrawal_id = vault.request_withdrawal(stcspr);
+
+        // Check withdrawal request
+        let request = vault.get_withdrawal_request(withdrawal_id).unwrap();
+        assert_eq!(request.user, env.get_account(1));
+        assert!(!request.is_claimed);
+
+        // stCSPR should be burned
+        assert_eq!(token.balance_of(env.get_account(1)), U256::zero());
+    }
+
+    #[test]
+    fn test_rewards_compounding() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+
+        // User deposits
+        let deposit = U512::from(1000_000_000_000u64); // 1000 CSPR
+        env.set_caller(env.get_account(1));
+        let stcspr_before = vault.with_tokens(deposit).deposit();
+
+        // The oracle reports an era of auction rewards
+        let rewards = report_rewards(&env, &mut vault);
+        assert!(!rewards.is_zero());
+
+        // Exchange rate should increase
+        let rate = vault.get_exchange_rate();
+        assert!(rate > U256::from(1_000_000_000u64));
+
+        // User's stCSPR is now worth more CSPR
+        let cspr_value = vault.stcspr_to_cspr(stcspr_before);
+        assert!(cspr_value > deposit);
+    }
+
+    #[test]
+    fn test_rewards_need_oracle_quorum() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+        let reporters = [env.get_account(5), env.get_account(6), env.get_account(7)];
+        for reporter in reporters {
+            vault.grant_role(Role::Oracle, reporter);
+        }
+        vault.set_oracle_quorum(2);
+
+        env.set_caller(env.get_account(1));
+        vault.with_tokens(U512::from(1_000_000_000_000u64)).deposit();
+        env.set_caller(env.get_account(0));
+        let validator = env.get_validator(0);
+        vault.add_validator(validator.clone());
+        vault.delegate_to_validator(validator, U512::from(1_000_000_000_000u64));
+        let rate = vault.get_exchange_rate();
+
+        let era = vault.get_current_era();
+        env.advance_with_auctions(vault.get_era_config().era_duration);
+        let total_delegated = vault.get_total_delegated_amount();
+        let report = EraReport {
+            era,
+            total_delegated,
+            rewards: total_delegated - vault.get_total_staked(),
+            slashed: U512::zero(),
+        };
+
+        // One report does not move the rate, and one the auction does not back is refused
+        env.set_caller(reporters[0]);
+        vault.submit_report(report.clone());
+        env.set_caller(reporters[1]);
+        assert_eq!(
+            vault.try_submit_report(EraReport { rewards: report.rewards * 2, ..report.clone() }),
+            Err(VaultError::ReportAmountsMismatch.into())
+        );
+        assert!(vault.has_reported(era, reporters[0]));
+        assert!(!vault.has_reported(era, reporters[1]));
+        assert_eq!(vault.get_exchange_rate(), rate);
+        assert_eq!(vault.get_last_reported_era(), None);
+
+        // A second matching report finalizes the era
+        env.set_caller(reporters[2]);
+        vault.submit_report(report.clone());
+        assert!(vault.get_exchange_rate() > rate);
+        assert_eq!(vault.get_era_report(era), Some(report));
+        assert_eq!(vault.get_last_reported_era(), Some(era));
+    }
+
+    #[test]
+    fn test_oracle_report_guards() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+        let reporter = env.get_account(5);
+        vault.grant_role(Role::Oracle, reporter);
+
+        env.set_caller(env.get_account(1));
+        vault.with_tokens(U512::from(1_000_000_000_000u64)).deposit();
+        let report = EraReport {
+            era: vault.get_current_era(),
+            total_delegated: U512::zero(),
+            rewards: U512::zero(),
+            slashed: U512::zero(),
+        };
+
+        // Only oracles report, and only on ended eras
+        assert_eq!(
+            vault.try_submit_report(report.clone()),
+            Err(AccessControlError::MissingRole.into())
+        );
+        env.set_caller(reporter);
+        assert_eq!(vault.try_submit_report(report.clone()), Err(OracleError::EraNotEnded.into()));
+
+        // A finalized era cannot be reported again
+        env.advance_block_time(vault.get_era_config().era_duration);
+        vault.submit_report(report.clone());
+        assert_eq!(vault.try_submit_report(report.clone()), Err(OracleError::EraAlreadyFinalized.into()));
+
+        // Quorum is admin-only and at least one
+        assert_eq!(vault.try_set_oracle_quorum(2), Err(AccessControlError::MissingRole.into()));
+        env.set_caller(env.get_account(0));
+        assert_eq!(vault.try_set_oracle_quorum(0), Err(OracleError::InvalidQuorum.into()));
+        vault.set_oracle_quorum(2);
+        assert_eq!(vault.get_oracle_quorum(), 2);
+
+        // Each reporter counts once per era
+        let next = EraReport { era: report.era + 1, ..report };
+        env.advance_block_time(vault.get_era_config().era_duration);
+        env.set_caller(reporter);
+        vault.submit_report(next.clone());
+        assert_eq!(vault.try_submit_report(next), Err(OracleError::AlreadyReported.into()));
+    }
+
+    #[test]
+    fn test_slashing_report_lowers_rate() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+        vault.grant_role(Role::Oracle, env.get_account(0));
+
+        let deposit = U512::from(1_000_000_000_000u64);
+        env.set_caller(env.get_account(1));
+        let stcspr = vault.with_tokens(deposit).deposit();
+
+        // Half the pool sits with a validator that leaves the auction without returning it
+        env.set_caller(env.get_account(0));
+        for index in 0..2 {
+            let validator = env.get_validator(index);
+            vault.add_validator(validator.clone());
+            vault.delegate_to_validator(validator, U512::from(500_000_000_000u64));
+        }
+        let era = vault.get_current_era();
+        env.advance_block_time(vault.get_era_config().era_duration);
+        env.remove_validator(1);
+
+        // Slashing must be exactly what the auction lost
+        let report = EraReport {
+            era,
+            total_delegated: vault.get_total_delegated_amount(),
+            rewards: U512::zero(),
+            slashed: U512::from(500_000_000_000u64),
+        };
+        assert_eq!(
+            vault.try_submit_report(EraReport { slashed: U512::from(100_000_000_000u64), ..report.clone() }),
+            Err(VaultError::ReportAmountsMismatch.into())
+        );
+        vault.submit_report(report);
+
+        assert!(vault.get_exchange_rate() < U256::from(1_000_000_000u64));
+        assert_eq!(vault.get_tvl(), U512::from(500_000_000_000u64));
+        assert_eq!(vault.get_total_staked(), U512::from(500_000_000_000u64));
+        assert!(vault.stcspr_to_cspr(stcspr) < deposit);
+    }
+
+    #[test]
+    fn test_reported_rewards_are_booked_as_delegated() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+        vault.grant_role(Role::Oracle, env.get_account(0));
+
+        let deposit = U512::from(1_000_000_000_000u64);
+        let stake = U512::from(600_000_000_000u64);
+        env.set_caller(env.get_account(1));
+        vault.with_tokens(deposit).deposit();
+
+        env.set_caller(env.get_account(0));
+        let validator = env.get_validator(0);
+        vault.add_validator(validator.clone());
+        vault.delegate_to_validator(validator.clone(), stake);
+        let era = vault.get_current_era();
+        env.advance_with_auctions(env.auction_delay() * 3);
+        env.advance_block_time(vault.get_era_config().era_duration);
+        let delegated = vault.get_total_delegated_amount();
+        let rewards = delegated - stake;
+        assert!(!rewards.is_zero());
+
+        // A report that disagrees with the auction is rejected, as are rewards it does not hold
+        assert_eq!(
+            vault.try_submit_report(EraReport { era, total_delegated: stake, rewards, slashed: U512::zero() }),
+            Err(VaultError::ReportDelegationMismatch.into())
+        );
+        for rewards in [rewards + 1, rewards - 1] {
+            assert_eq!(
+                vault.try_submit_report(EraReport { era, total_delegated: delegated, rewards, slashed: U512::zero() }),
+                Err(VaultError::ReportAmountsMismatch.into())
+            );
+        }
+
+        // Rewards raise TVL but stay with the auction: delegated grows, idle does not
+        vault.submit_report(EraReport { era, total_delegated: delegated, rewards, slashed: U512::zero() });
+        assert_eq!(vault.get_tvl(), deposit + rewards);
+        assert_eq!(vault.get_total_staked(), delegated);
+        assert_eq!(vault.get_idle_cspr(), deposit - stake);
+    }
+
+    #[test]
+    fn test_performance_fee_minted_as_stcspr() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, token) = deploy_vault(&env, treasury, 7);
+        let treasury_cspr = env.balance_of(&treasury);
+
+        let deposit = U512::from(1_000_000_000_000u64); // 1000 CSPR
+        env.set_caller(env.get_account(1));
+        let stcspr = vault.with_tokens(deposit).deposit();
+
+        // An era of rewards at the default 5% fee
+        let rewards = report_rewards(&env, &mut vault);
+        let fee = rewards * 5 / 100;
+        let user_rewards = rewards - fee;
+
+        // No CSPR leaves the vault; the whole reward stays staked
+        assert_eq!(env.balance_of(&treasury), treasury_cspr);
+        assert_eq!(vault.get_total_staked(), deposit + rewards);
+        assert_eq!(vault.get_tvl(), deposit + fee + user_rewards);
+
+        // The treasury holds stCSPR worth the fee at the post-reward rate
+        let fee_shares = vault.get_treasury_shares();
+        assert_eq!(fee_shares, token.balance_of(treasury));
+        assert_eq!(token.total_supply(), stcspr + fee_shares);
+        let fee_value = vault.stcspr_to_cspr(fee_shares);
+        assert!(fee_value <= fee && fee - fee_value <= U512::one());
+
+        // Minting the fee does not dilute stakers: they keep the net rewards
+        let user_value = vault.stcspr_to_cspr(stcspr);
+        assert!(user_value <= deposit + user_rewards);
+        assert!(deposit + user_rewards - user_value < U512::from(math::VIRTUAL_ASSETS));
+        assert_eq!(
+            vault.get_exchange_rate(),
+            math::share_price(math::to_u256(vault.get_tvl()).unwrap(), token.total_supply()).unwrap()
+        );
+
+        // Fee shares earn on later rewards like any other stCSPR
+        report_rewards(&env, &mut vault);
+        assert!(vault.stcspr_to_cspr(fee_shares) > fee_value);
+    }
+
+    #[test]
+    fn test_management_fee_accrues_between_interactions() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, token) = deploy_vault(&env, treasury, 7);
+
+        let deposit = U512::from(1_000_000_000_000u64); // 1000 CSPR
+        env.set_caller(env.get_account(1));
+        let stcspr = vault.with_tokens(deposit).deposit();
+        apply_admin_call(&env, &mut vault, VaultAdminCall::SetManagementFee(100)); // 1% a year
+        assert_eq!(vault.get_management_fee(), 100);
+        assert_eq!(vault.get_treasury_shares(), U256::zero());
+
+        // A year later 1% of the pool is owed, and previews already price it in
+        env.advance_block_time(rate_limits::YEAR_MILLIS);
+        assert_eq!(vault.get_pending_management_fee(), U512::from(10_000_000_000u64));
+        let amount = U512::from(100_000_000_000u64);
+        let preview = vault.preview_deposit(amount);
+        env.set_caller(env.get_account(2));
+        assert_eq!(vault.with_tokens(amount).deposit(), preview);
+
+        // The fee dilutes holders without moving CSPR: the treasury holds 10 CSPR of stCSPR
+        assert_eq!(vault.get_pending_management_fee(), U512::zero());
+        assert_eq!(vault.get_tvl(), deposit + amount);
+        let fee_value = vault.stcspr_to_cspr(token.balance_of(treasury));
+        assert!(U512::from(10_000_000_000u64) - fee_value <= U512::one());
+        // The virtual shares pay their part of the dilution, so holders keep a sliver over 990
+        let user_value = vault.stcspr_to_cspr(stcspr);
+        assert!(user_value >= U512::from(990_000_000_000u64));
+        assert!(user_value - U512::from(990_000_000_000u64) < U512::from(math::VIRTUAL_ASSETS));
+
+        // Capped at 2% a year
+        env.set_caller(env.get_account(0));
+        assert_eq!(
+            vault.try_schedule_admin_call(VaultAdminCall::SetManagementFee(MAX_MANAGEMENT_FEE_BPS + 1)),
+            Err(VaultError::FeeTooHigh.into())
+        );
+    }
+
+    #[test]
+    fn test_management_fee_after_long_idle_is_not_rate_bounded() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, mut token) = deploy_vault(&env, treasury, 7);
+        token.set_max_rate_change(50); // 0.5% per rate update
+
+        env.set_caller(env.get_account(1));
+        vault.with_tokens(U512::from(1_000_000_000_000u64)).deposit();
+        apply_admin_call(&env, &mut vault, VaultAdminCall::SetManagementFee(MAX_MANAGEMENT_FEE_BPS));
+
+        // Half a year idle dilutes holders by 1%, twice the bound, and the vault still takes deposits
+        env.advance_block_time(rate_limits::YEAR_MILLIS / 2);
+        let rate = vault.get_exchange_rate();
+        env.set_caller(env.get_account(2));
+        vault.with_tokens(U512::from(100_000_000_000u64)).deposit();
+        assert_eq!(vault.get_pending_management_fee(), U512::zero());
+        assert!(vault.get_exchange_rate() < rate * U256::from(995u64) / U256::from(1000u64));
+        let fee_value = vault.stcspr_to_cspr(token.balance_of(treasury));
+        assert!(U512::from(10_000_000_000u64) - fee_value <= U512::one());
+    }
+
+    #[test]
+    fn test_fees_split_between_weighted_recipients() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let insurance = env.get_account(8);
+        let (mut vault, token) = deploy_vault(&env, treasury, 7);
+
+        apply_admin_call(&env, &mut vault, VaultAdminCall::SetFeeRecipients(vec![
+            FeeRecipient { account: treasury, weight: 3 },
+            FeeRecipient { account: insurance, weight: 1 },
+        ]));
+        assert_eq!(vault.get_fee_recipients().len(), 2);
+
+        env.set_caller(env.get_account(1));
+        vault.with_tokens(U512::from(1_000_000_000_000u64)).deposit();
+        report_rewards(&env, &mut vault);
+
+        // The fee shares split 3:1, dust to the first recipient
+        let insurance_shares = token.balance_of(insurance);
+        let treasury_shares = token.balance_of(treasury);
+        let fee_shares = insurance_shares + treasury_shares;
+        assert_eq!(insurance_shares, fee_shares / U256::from(4u64));
+        assert_eq!(token.balance_of(vault.address()), U256::zero());
+        assert!(env.emitted_event(&vault, FeeDistributed {
+            recipient: insurance,
+            weight: 1,
+            stcspr_amount: insurance_shares,
+            timestamp: env.block_time(),
+        }));
+        assert!(env.emitted_event(&vault, FeeDistributed {
+            recipient: treasury,
+            weight: 3,
+            stcspr_amount: treasury_shares,
+            timestamp: env.block_time(),
+        }));
+
+        // Recipient lists are validated when scheduled
+        assert_eq!(
+            vault.try_schedule_admin_call(VaultAdminCall::SetFeeRecipients(vec![])),
+            Err(VaultError::InvalidFeeRecipients.into())
+        );
+        assert_eq!(
+            vault.try_schedule_admin_call(VaultAdminCall::SetFeeRecipients(vec![
+                FeeRecipient { account: insurance, weight: 0 },
+            ])),
+            Err(VaultError::InvalidFeeRecipients.into())
+        );
+    }
+
+    #[test]
+    fn test_referred_deposits_earn_referrer_fees() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, mut token) = deploy_vault(&env, treasury, 7);
+        let user = env.get_account(1);
+        let referrer = env.get_account(2);
+        let deposit = U512::from(1_000_000_000_000u64);
+
+        apply_admin_call(&env, &mut vault, VaultAdminCall::SetReferralFeeShare(2000));
+        assert_eq!(vault.get_referral_fee_share(), 2000);
+
+        env.set_caller(user);
+        assert_eq!(
+            vault.with_tokens(deposit).try_deposit_with_referral(user),
+            Err(ReferralError::SelfReferral.into())
+        );
+        let shares = vault.with_tokens(deposit).deposit_with_referral(referrer);
+        assert!(env.emitted_event(&vault, ReferredDeposit {
+            referrer,
+            user,
+            cspr_amount: deposit,
+            shares,
+            timestamp: env.block_time(),
+        }));
+        env.set_caller(env.get_account(3));
+        vault.with_tokens(deposit).deposit();
+
+        // A later referral stays credited to the first referrer
+        env.set_caller(user);
+        vault.with_tokens(deposit).deposit_with_referral(env.get_account(4));
+        assert_eq!(vault.get_referrer(user), Some(referrer));
+        let stats = vault.get_referrer_stats(referrer);
+        assert_eq!(stats.referred_volume, deposit * 2);
+        assert_eq!(stats.referred_users, 1);
+        assert_eq!(stats.active_shares, token.balance_of(user));
+        assert_eq!(vault.get_referrer_stats(env.get_account(4)).referred_users, 0);
+
+        // Referrers take 20% of the fee shares, scaled by the referred part of the supply
+        report_rewards(&env, &mut vault);
+        let treasury_shares = token.balance_of(treasury);
+        let referral_shares = token.balance_of(vault.address());
+        let fee_shares = treasury_shares + referral_shares;
+        assert!(referral_shares > U256::zero());
+        let referred = vault.get_referrer_stats(referrer).active_shares;
+        assert_eq!(
+            referral_shares,
+            fee_shares * U256::from(2000u64) * referred / (U256::from(10_000u64) * token.total_supply())
+        );
+        let claimable = vault.get_claimable_referral_fees(referrer);
+        assert!(claimable <= referral_shares && referral_shares - claimable <= U256::one());
+
+        // Burned shares stop counting towards the referrer
+        env.set_caller(user);
+        vault.request_withdrawal(token.balance_of(user) / U256::from(2u64));
+        assert_eq!(vault.get_referrer_stats(referrer).active_shares, token.balance_of(user));
+        assert_eq!(vault.get_referred_stake(referrer), vault.stcspr_to_cspr(token.balance_of(user)));
+
+        // Transferred shares stop counting too; receiving stCSPR does not add to it
+        let active = vault.get_referrer_stats(referrer).active_shares;
+        let sent = active / U256::from(4u64);
+        token.transfer(env.get_account(3), sent);
+        assert_eq!(vault.get_referrer_stats(referrer).active_shares, active - sent);
+        env.set_caller(env.get_account(3));
+        token.transfer(user, sent);
+        assert_eq!(vault.get_referrer_stats(referrer).active_shares, active - sent);
+        assert_eq!(vault.try_on_stcspr_transfer(user, U256::zero()), Err(VaultError::NotToken.into()));
+
+        // Referrers claim as stCSPR, once
+        env.set_caller(referrer);
+        assert_eq!(vault.claim_referral_fees(), claimable);
+        assert_eq!(token.balance_of(referrer), claimable);
+        assert_eq!(vault.get_claimable_referral_fees(referrer), U256::zero());
+        assert_eq!(vault.try_claim_referral_fees(), Err(ReferralError::NothingToClaim.into()));
+
+        // The referrers' share of the fee is capped
+        env.set_caller(env.get_account(0));
+        assert_eq!(
+            vault.try_schedule_admin_call(VaultAdminCall::SetReferralFeeShare(5001)),
+            Err(VaultError::FeeTooHigh.into())
+        );
+    }
+
+    #[test]
+    fn test_slashing_is_shared_with_pending_withdrawals() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+        vault.grant_role(Role::Oracle, env.get_account(0));
+
+        let deposit = U512::from(2_000_000_000_000u64);
+        env.set_caller(env.get_account(1));
+        let stcspr = vault.with_tokens(deposit).deposit();
+
+        // 400 CSPR liquid: half the position exits, 400 filled and 600 still unbonding
+        env.set_caller(env.get_account(0));
+        for (index, stake) in [(0, 1_000_000_000_000u64), (1, 600_000_000_000u64)] {
+            let validator = env.get_validator(index);
+            vault.add_validator(validator.clone());
+            vault.delegate_to_validator(validator, U512::from(stake));
+        }
+        env.set_caller(env.get_account(1));
+        let withdrawal_id = vault.request_withdrawal(stcspr / U256::from(2u64));
+
+        // The second validator leaves the auction with its 600 CSPR: 1000 pooled and 600 queued split it
+        let era = vault.get_current_era();
+        env.advance_block_time(vault.get_era_config().era_duration);
+        env.remove_validator(1);
+        env.set_caller(env.get_account(0));
+        vault.submit_report(EraReport {
+            era,
+            total_delegated: vault.get_total_delegated_amount(),
+            rewards: U512::zero(),
+            slashed: U512::from(600_000_000_000u64),
+        });
+        assert!(env.emitted_event(&vault, LossSocialised {
+            era,
+            slashed: U512::from(600_000_000_000u64),
+            pool_loss: U512::from(375_000_000_000u64),
+            queue_loss: U512::from(225_000_000_000u64),
+            timestamp: env.block_time(),
+        }));
+
+        // Both sides lost 37.5%; the already-filled 400 CSPR is untouched
+        assert_eq!(vault.get_tvl(), U512::from(625_000_000_000u64));
+        assert_eq!(vault.get_total_queued(), U512::from(375_000_000_000u64));
+        assert_eq!(vault.get_withdrawal_payout(withdrawal_id), U512::from(775_000_000_000u64));
+        assert_eq!(vault.get_claimable_amount(withdrawal_id), U512::from(400_000_000_000u64));
+        assert_eq!(vault.get_queue_position(withdrawal_id).remaining_amount, U512::from(375_000_000_000u64));
+
+        // A request made after the loss is owed what it was quoted
+        env.set_caller(env.get_account(1));
+        let later_id = vault.request_withdrawal(stcspr / U256::from(4u64));
+        let later = vault.get_withdrawal_request(later_id).unwrap();
+        assert!(later.cspr_amount - vault.get_withdrawal_payout(later_id) <= U512::one());
+
+        // Fresh liquidity fulfils the reduced amount and the request closes
+        env.set_caller(env.get_account(2));
+        vault.with_tokens(U512::from(1_000_000_000_000u64)).deposit();
+        env.advance_block_time(later.unlock_time);
+        env.set_caller(env.get_account(1));
+        vault.claim_withdrawal(withdrawal_id);
+        let request = vault.get_withdrawal_request(withdrawal_id).unwrap();
+        assert_eq!(request.claimed_amount, U512::from(775_000_000_000u64));
+        assert!(request.is_claimed);
+    }
+
+    #[test]
+    fn test_withdrawal_wiped_out_by_slashing_can_be_closed() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let user = env.get_account(1);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+        vault.grant_role(Role::Oracle, env.get_account(0));
+
+        let deposit = U512::from(1_000_000_000_000u64);
+        env.set_caller(user);
+        let stcspr = vault.with_tokens(deposit).deposit();
+
+        // Everything is delegated, so the whole position waits in the queue
+        env.set_caller(env.get_account(0));
+        let validator = env.get_validator(0);
+        vault.add_validator(validator.clone());
+        vault.delegate_to_validator(validator, deposit);
+        env.set_caller(user);
+        let withdrawal_id = vault.request_withdrawal(stcspr);
+        let unlock_time = vault.get_withdrawal_request(withdrawal_id).unwrap().unlock_time;
+
+        // The validator leaves the auction with the whole stake
+        let era = vault.get_current_era();
+        env.advance_block_time(vault.get_era_config().era_duration);
+        env.remove_validator(0);
+        env.set_caller(env.get_account(0));
+        vault.submit_report(EraReport {
+            era,
+            total_delegated: U512::zero(),
+            rewards: U512::zero(),
+            slashed: deposit,
+        });
+        assert_eq!(vault.get_total_queued(), U512::zero());
+        assert_eq!(vault.get_withdrawal_payout(withdrawal_id), U512::zero());
+        assert_eq!(vault.get_claimable_amount(withdrawal_id), U512::zero());
+
+        // Claiming closes the ticket without paying anything
+        env.advance_block_time(unlock_time);
+        env.set_caller(user);
+        let balance = env.balance_of(&user);
+        vault.claim_withdrawal(withdrawal_id);
+        let request = vault.get_withdrawal_request(withdrawal_id).unwrap();
+        assert!(request.is_claimed);
+        assert_eq!(request.claimed_amount, U512::zero());
+        assert_eq!(env.balance_of(&user), balance);
+        assert!(env.emitted_event(&vault, WithdrawalClaimed {
+            withdrawal_id,
+            user,
+            cspr_amount: U512::zero(),
+            remaining_amount: U512::zero(),
+            timestamp: env.block_time(),
+        }));
+        assert_eq!(vault.try_claim_withdrawal(withdrawal_id), Err(WithdrawalQueueError::AlreadyClaimed.into()));
+    }
+
+    #[test]
+    fn test_out_of_bounds_report_is_held_for_review() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+        let era_duration = vault.get_era_config().era_duration;
+
+        env.set_caller(env.get_account(1));
+        vault.with_tokens(U512::from(1_000_000_000_000u64)).deposit(); // 1000 CSPR
+        env.set_caller(env.get_account(0));
+        vault.set_rate_limits(2000, 500, era_duration); // 20% APR, 5% loss, one report per era
+
+        // The test auction pays about 7.7% APR: applied
+        report_rewards(&env, &mut vault);
+        let rate = vault.get_exchange_rate();
+        assert!(rate > U256::from(1_000_000_000u64));
+        assert!(vault.get_held_report().is_none());
+
+        // Capped at 1% APR, the next era is held and leaves the rate alone
+        vault.set_rate_limits(100, 500, era_duration);
+        let rewards = report_rewards(&env, &mut vault);
+        let held = vault.get_held_report().unwrap();
+        assert_eq!(held.rewards, rewards);
+        assert_eq!(vault.get_exchange_rate(), rate);
+
+        // Further reports wait for the review
+        env.advance_block_time(era_duration);
+        assert_eq!(
+            vault.try_submit_report(EraReport {
+                era: held.era + 1,
+                total_delegated: U512::zero(),
+                rewards: U512::zero(),
+                slashed: U512::zero(),
+            }),
+            Err(VaultError::ReportAwaitingReview.into())
+        );
+
+        // Only a guardian resolves it; the deployer is one
+        env.set_caller(env.get_account(1));
+        assert_eq!(vault.try_release_held_report(), Err(AccessControlError::MissingRole.into()));
+        env.set_caller(env.get_account(0));
+        vault.release_held_report();
+        assert!(vault.get_held_report().is_none());
+        assert!(vault.get_exchange_rate() > rate);
+        assert_eq!(vault.try_reject_held_report(), Err(VaultError::NoHeldReport.into()));
+
+        // Once the auction moves on, a held report no longer matches it and can only be rejected;
+        // the next report carries its rewards
+        let rewards = report_rewards(&env, &mut vault);
+        env.advance_with_auctions(env.auction_delay());
+        assert_eq!(vault.try_release_held_report(), Err(VaultError::ReportDelegationMismatch.into()));
+        vault.reject_held_report();
+        vault.set_rate_limits(2000, 500, era_duration);
+        assert!(report_rewards(&env, &mut vault) > rewards);
+        assert!(vault.get_held_report().is_none());
+    }
+
+    #[test]
+    fn test_report_before_min_interval_is_refused() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+        vault.grant_role(Role::Oracle, env.get_account(0));
+        let era_duration = vault.get_era_config().era_duration;
+
+        env.set_caller(env.get_account(1));
+        vault.with_tokens(U512::from(1_000_000_000_000u64)).deposit();
+        env.set_caller(env.get_account(0));
+        vault.set_rate_limits(2000, 500, 2 * era_duration); // one report every other era
+
+        let report = |era| EraReport {
+            era,
+            total_delegated: U512::zero(),
+            rewards: U512::zero(),
+            slashed: U512::zero(),
+        };
+        let era = vault.get_current_era();
+        env.advance_block_time(2 * era_duration);
+        vault.submit_report(report(era));
+
+        // Too soon is refused rather than held, so the oracle is not blocked on a review
+        env.advance_block_time(era_duration);
+        assert_eq!(
+            vault.try_submit_report(report(era + 1)),
+            Err(RateLimitError::ReportTooSoon.into())
+        );
+        assert!(vault.get_held_report().is_none());
+        assert_eq!(vault.get_last_reported_era(), Some(era));
+
+        env.advance_block_time(era_duration);
+        vault.submit_report(report(era + 1));
+        assert_eq!(vault.get_last_reported_era(), Some(era + 1));
+    }
+
+    #[test]
+    fn test_rejected_slashing_report_is_not_booked() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+        vault.grant_role(Role::Oracle, env.get_account(0));
+        let era_duration = vault.get_era_config().era_duration;
+
+        let deposit = U512::from(5_000_000_000_000u64);
+        env.set_caller(env.get_account(1));
+        vault.with_tokens(deposit).deposit();
+        env.set_caller(env.get_account(0));
+        vault.set_rate_limits(2000, 500, era_duration);
+        for (index, stake) in [(0, 4_500_000_000_000u64), (1, 500_000_000_000u64)] {
+            let validator = env.get_validator(index);
+            vault.add_validator(validator.clone());
+            vault.delegate_to_validator(validator, U512::from(stake));
+        }
+
+        // Losing the second validator's stake, 10% of the pool, breaks the 5% bound
+        let era = vault.get_current_era();
+        env.advance_block_time(era_duration);
+        env.remove_validator(1);
+        let report = EraReport {
+            era,
+            total_delegated: vault.get_total_delegated_amount(),
+            rewards: U512::zero(),
+            slashed: U512::from(500_000_000_000u64),
+        };
+        vault.submit_report(report.clone());
+        assert!(vault.get_held_report().is_some());
+
+        vault.reject_held_report();
+        assert!(vault.get_held_report().is_none());
+        assert_eq!(vault.get_tvl(), deposit);
+        assert_eq!(vault.get_last_reported_era(), Some(era));
+
+        // The loss is still unbooked, so later reports must carry it
+        env.advance_block_time(era_duration);
+        assert_eq!(
+            vault.try_submit_report(EraReport { era: era + 1, slashed: U512::zero(), ..report.clone() }),
+            Err(VaultError::ReportAmountsMismatch.into())
+        );
+        vault.submit_report(EraReport { era: era + 1, ..report });
+        assert!(vault.get_held_report().is_some());
+
+        // Limits themselves are sanity-checked
+        assert_eq!(
+            vault.try_set_rate_limits(0, 500, era_duration),
+            Err(RateLimitError::InvalidRateLimits.into())
+        );
+    }
+
+    #[test]
+    fn test_previews_match_state_changing_calls() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, mut token) = deploy_vault(&env, treasury, 7);
+        let user = env.get_account(1);
+
+        // Move the rate off 1:1 so rounding matters
+        env.set_caller(env.get_account(2));
+        vault.with_tokens(U512::from(600_000_000_000u64)).deposit();
+        report_rewards(&env, &mut vault);
+
+        let deposit = U512::from(123_456_789_012u64);
+        let preview = vault.preview_deposit(deposit);
+        assert_eq!(vault.convert_to_shares(deposit), preview);
+        env.set_caller(user);
+        let minted = vault.with_tokens(deposit).deposit();
+        assert_eq!(minted, preview);
+
+        let max = vault.max_withdraw(user);
+        assert_eq!(max, vault.convert_to_assets(token.balance_of(user)));
+
+        let stcspr = minted / U256::from(3u64);
+        let expected = vault.preview_request_withdrawal(stcspr);
+        assert_eq!(vault.convert_to_assets(stcspr), expected);
+        let withdrawal_id = vault.request_withdrawal(stcspr);
+        assert_eq!(vault.get_withdrawal_request(withdrawal_id).unwrap().cspr_amount, expected);
+
+        assert_eq!(
+            vault.try_preview_deposit(U512::from(1_000_000_000u64)),
+            Err(VaultError::BelowMinimumDeposit.into())
+        );
+        assert_eq!(vault.try_preview_request_withdrawal(U256::zero()), Err(VaultError::ZeroAmount.into()));
+
+        // Limits drop to zero while the token is paused
+        assert_eq!(vault.max_deposit(user), U512::MAX);
+        env.set_caller(env.get_account(0));
+        token.pause();
+        assert_eq!(vault.max_deposit(user), U512::zero());
+        assert_eq!(vault.max_withdraw(user), U512::zero());
+    }
+
+    #[test]
+    fn test_inflated_exchange_rate_does_not_steal_deposits() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, token) = deploy_vault(&env, treasury, 7);
+        let (attacker, victim) = (env.get_account(1), env.get_account(2));
+        let min_deposit = U512::from(10_000_000_000u64);
+
+        // Attacker stakes, then exits all but one unit of stCSPR while the stake is still delegated
+        env.set_caller(attacker);
+        let stcspr = vault.with_tokens(U512::from(1_000_000_000_000u64)).deposit();
+        env.set_caller(env.get_account(0));
+        let validator = env.get_validator(0);
+        vault.add_validator(validator.clone());
+        vault.delegate_to_validator(validator, U512::from(1_000_000_000_000u64));
+        env.set_caller(attacker);
+        vault.request_withdrawal(stcspr - U256::one());
+        assert_eq!(token.total_supply(), U256::one());
+
+        // Rewards on the whole stake land on the near-empty pool and inflate the rate
+        report_rewards(&env, &mut vault);
+
+        // The next depositor still receives their share
+        env.set_caller(victim);
+        let minted = vault.with_tokens(min_deposit).deposit();
+        assert!(!minted.is_zero());
+        let victim_value = vault.stcspr_to_cspr(minted);
+        assert!(victim_value >= min_deposit * 99 / 100);
+        assert!(vault.stcspr_to_cspr(U256::one()) < U512::from(1_000_000_000u64));
+    }
+
+    #[test]
+    fn test_token_transfer() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (vault, mut token) = deploy_vault(&env, treasury, 7);
+
+        // User 1 deposits
+        let amount = U512::from(100_000_000_000u64);
+        env.set_caller(env.get_account(1));
+        let stcspr = vault.with_tokens(amount).deposit();
+
+        // Transfer to user 2
+        let user2 = env.get_account(2);
+        let transfer_amount = stcspr / U256::from(2u64);
+        token.transfer(user2, transfer_amount);
+
+        assert_eq!(token.balance_of(env.get_account(1)), stcspr - transfer_amount);
+        assert_eq!(token.balance_of(user2), transfer_amount);
+    }
+
+    #[test]
+    fn test_approve_and_transfer_from() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (vault, mut token) = deploy_vault(&env, treasury, 7);
+
+        // User 1 deposits
+        let amount = U512::from(100_000_000_000u64);
+        env.set_caller(env.get_account(1));
+        let stcspr = vault.with_tokens(amount).deposit();
+
+        // Approve user 2 as spender
+        let user2 = env.get_account(2);
+        token.approve(user2, stcspr);
+        assert_eq!(token.allowance(env.get_account(1), user2), stcspr);
+
+        // User 2 transfers on behalf of user 1
+        env.set_caller(user2);
+        let user3 = env.get_account(3);
+        token.transfer_from(env.get_account(1), user3, stcspr / U256::from(2u64));
+
+        assert_eq!(token.balance_of(user3), stcspr / U256::from(2u64));
+    }
+
+    #[test]
+    fn test_deposit_for_and_request_withdrawal_for() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, mut token) = deploy_vault(&env, treasury, 7);
+        let custodian = env.get_account(1);
+        let owner = env.get_account(2);
+        let amount = U512::from(100_000_000_000u64);
+
+        // The custodian pays, the beneficiary receives the stCSPR
+        env.set_caller(custodian);
+        let stcspr = vault.with_tokens(amount).deposit_for(owner);
+        assert_eq!(token.balance_of(owner), stcspr);
+        assert_eq!(token.balance_of(custodian), U256::zero());
+        assert!(env.emitted_event(&vault, Deposited {
+            user: owner,
+            cspr_amount: amount,
+            stcspr_amount: stcspr,
+            exchange_rate: vault.get_exchange_rate(),
+            timestamp: env.block_time(),
+        }));
+        assert!(env.emitted_event(&vault, DepositedFor {
+            depositor: custodian,
+            beneficiary: owner,
+            cspr_amount: amount,
+            stcspr_amount: stcspr,
+            timestamp: env.block_time(),
+        }));
+
+        // Withdrawing for the owner needs their allowance
+        let half = stcspr / U256::from(2u64);
+        assert_eq!(
+            vault.try_request_withdrawal_for(owner, half),
+            Err(TokenError::InsufficientAllowance.into())
+        );
+        env.set_caller(owner);
+        token.approve(custodian, half);
+
+        env.set_caller(custodian);
+        let withdrawal_id = vault.request_withdrawal_for(owner, half);
+        assert_eq!(token.allowance(owner, custodian), U256::zero());
+        assert_eq!(token.balance_of(owner), stcspr - half);
+        assert_eq!(vault.get_withdrawal_owner(withdrawal_id), Some(owner));
+        assert!(env.emitted_event(&vault, WithdrawalRequestedFor {
+            withdrawal_id,
+            owner,
+            spender: custodian,
+            stcspr_amount: half,
+            timestamp: env.block_time(),
+        }));
+
+        // The allowance is spent
+        assert_eq!(
+            vault.try_request_withdrawal_for(owner, U256::one()),
+            Err(TokenError::InsufficientAllowance.into())
+        );
+    }
+
+    #[test]
+    fn test_delegate_to_validator() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+
+        // User deposits 1000 CSPR
+        let deposit = U512::from(1_000_000_000_000u64);
+        env.set_caller(env.get_account(1));
+        vault.with_tokens(deposit).deposit();
+
+        // Owner registers a validator and delegates 600 CSPR
+        env.set_caller(env.get_account(0));
+        let validator = env.get_validator(0);
+        vault.add_validator(validator.clone());
+        let stake = U512::from(600_000_000_000u64);
+        vault.delegate_to_validator(validator.clone(), stake);
+
+        // CSPR actually left the vault and sits with the auction
+        assert_eq!(vault.get_delegated_amount(validator.clone()), stake);
+        assert_eq!(env.delegated_amount(vault.address(), validator), stake);
+        assert_eq!(env.balance_of(&vault), deposit - stake);
+        assert_eq!(vault.get_total_staked(), stake);
+        assert_eq!(vault.get_idle_cspr(), deposit - stake);
+        assert_eq!(vault.get_tvl(), deposit);
+    }
+
+    #[test]
+    fn test_delegation_below_auction_minimum() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+
+        env.set_caller(env.get_account(1));
+        vault.with_tokens(U512::from(1_000_000_000_000u64)).deposit();
+
+        env.set_caller(env.get_account(0));
+        let validator = env.get_validator(0);
+        vault.add_validator(validator.clone());
+
+        // The MockVM auction requires at least 500 CSPR per delegation
+        assert_eq!(
+            vault.try_delegate_to_validator(validator, U512::from(100_000_000_000u64)),
+            Err(DelegationError::BelowAuctionMinimum.into())
+        );
+        assert_eq!(vault.get_total_staked(), U512::zero());
+    }
+
+    #[test]
+    fn test_undelegation_cannot_leave_dust() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+
+        env.set_caller(env.get_account(1));
+        vault.with_tokens(U512::from(1_000_000_000_000u64)).deposit();
+
+        env.set_caller(env.get_account(0));
+        let validator = env.get_validator(0);
+        vault.add_validator(validator.clone());
+        vault.delegate_to_validator(validator.clone(), U512::from(800_000_000_000u64));
+
+        // 300 CSPR left behind is below the auction minimum
+        assert_eq!(
+            vault.try_undelegate_from_validator(validator, U512::from(500_000_000_000u64)),
+            Err(DelegationError::BelowAuctionMinimum.into())
+        );
+        assert_eq!(vault.get_total_unbonding(), U512::zero());
+    }
+
+    #[test]
+    fn test_undelegate_and_unbonding() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+
+        let deposit = U512::from(1_000_000_000_000u64);
+        env.set_caller(env.get_account(1));
+        vault.with_tokens(deposit).deposit();
+
+        env.set_caller(env.get_account(0));
+        let validator = env.get_validator(0);
+        vault.add_validator(validator.clone());
+        vault.delegate_to_validator(validator.clone(), deposit);
+        assert_eq!(vault.get_idle_cspr(), U512::zero());
+
+        // Undelegate everything - funds are unbonding, not yet back
+        vault.undelegate_from_validator(validator.clone(), deposit);
+        assert_eq!(vault.get_delegated_amount(validator), U512::zero());
+        assert_eq!(vault.get_total_staked(), U512::zero());
+        assert_eq!(vault.get_total_unbonding(), deposit);
+        assert_eq!(env.balance_of(&vault), U512::zero());
+        assert_eq!(vault.settle_unbonded(), U512::zero());
+
+        // After the unbonding delay the auction returns the CSPR
+        env.advance_with_auctions(env.unbonding_delay());
+        assert_eq!(env.balance_of(&vault), deposit);
+        assert_eq!(vault.settle_unbonded(), deposit);
+        assert_eq!(vault.get_total_unbonding(), U512::zero());
+        assert_eq!(vault.get_idle_cspr(), deposit);
+    }
+
+    #[test]
+    fn test_auction_rewards_read_back() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+
+        let deposit = U512::from(1_000_000_000_000u64);
+        env.set_caller(env.get_account(1));
+        vault.with_tokens(deposit).deposit();
+
+        env.set_caller(env.get_account(0));
+        let validator = env.get_validator(0);
+        vault.add_validator(validator.clone());
+        vault.delegate_to_validator(validator.clone(), deposit);
+
+        // Auctions pay rewards on top of the delegated principal
+        env.advance_with_auctions(env.auction_delay() * 3);
+        assert!(vault.get_delegated_amount(validator) > deposit);
+        assert_eq!(vault.get_total_staked(), deposit);
+    }
+
+    #[test]
+    fn test_unbonding_days_honored() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 14);
+
+        // 14 days of 2-hour eras
+        let config = vault.get_era_config();
+        assert_eq!(config.era_duration, delegation::DEFAULT_ERA_DURATION);
+        assert_eq!(config.unbonding_eras, 168);
+
+        env.set_caller(env.get_account(1));
+        let stcspr = vault.with_tokens(U512::from(100_000_000_000u64)).deposit();
+        env.advance_block_time(config.era_duration * 3 + 1_000);
+        let withdrawal_id = vault.request_withdrawal(stcspr);
+
+        // Requested during era 3: effective from era 4, unbonded by era 4 + 168
+        let request = vault.get_withdrawal_request(withdrawal_id).unwrap();
+        assert_eq!(vault.get_current_era(), 3);
+        assert_eq!(request.unlock_era, 172);
+        assert_eq!(request.unlock_time, 172 * config.era_duration);
+    }
+
+    #[test]
+    fn test_unbonding_config_keeps_queued_terms() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+
+        // Match the MockVM auction: one era per auction, 7 eras of unbonding
+        let era = env.auction_delay();
+        vault.set_unbonding_config(era, 7);
+
+        env.set_caller(env.get_account(1));
+        let stcspr = vault.with_tokens(U512::from(100_000_000_000u64)).deposit();
+        let first_id = vault.request_withdrawal(stcspr / U256::from(2u64));
+        let first = vault.get_withdrawal_request(first_id).unwrap();
+        assert_eq!(first.unlock_era, 8);
+
+        // Governance lengthens unbonding mid-queue
+        env.advance_block_time(era + 1);
+        env.set_caller(env.get_account(0));
+        vault.set_unbonding_config(era * 2, 14);
+
+        env.set_caller(env.get_account(1));
+        let second_id = vault.request_withdrawal(stcspr / U256::from(2u64));
+
+        // The queued request keeps its original terms
+        assert_eq!(vault.get_withdrawal_request(first_id).unwrap(), first);
+        let second = vault.get_withdrawal_request(second_id).unwrap();
+        assert_eq!(second.unlock_era, 1 + 1 + 14);
+        assert_eq!(second.unlock_time, era + 15 * era * 2);
+
+        // First request is claimable on its original schedule
+        env.advance_block_time(first.unlock_time - env.block_time());
+        vault.claim_withdrawal(first_id);
+        assert!(vault.get_withdrawal_request(first_id).unwrap().is_claimed);
+    }
+
+    #[test]
+    fn test_unbonding_tracked_per_era() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+
+        let era = env.auction_delay();
+        vault.set_unbonding_config(era, 7);
+
+        let deposit = U512::from(1_000_000_000_000u64);
+        env.set_caller(env.get_account(1));
+        let stcspr = vault.with_tokens(deposit).deposit();
+
+        env.set_caller(env.get_account(0));
+        let validator = env.get_validator(0);
+        vault.add_validator(validator.clone());
+        vault.delegate_to_validator(validator.clone(), deposit);
+
+        // Withdrawal and undelegation in the same era share the unlock era
+        env.set_caller(env.get_account(1));
+        let withdrawal_id = vault.request_withdrawal(stcspr);
+        env.set_caller(env.get_account(0));
+        vault.undelegate_from_validator(validator, deposit);
+
+        let request = vault.get_withdrawal_request(withdrawal_id).unwrap();
+        assert_eq!(vault.get_unbonding_for_era(request.unlock_era), deposit);
+
+        // By the unlock time the auction has paid the CSPR back
+        env.advance_with_auctions(request.unlock_time);
+        assert_eq!(vault.settle_unbonded(), deposit);
+
+        env.set_caller(env.get_account(1));
+        vault.claim_withdrawal(withdrawal_id);
+        assert!(vault.get_withdrawal_request(withdrawal_id).unwrap().is_claimed);
+    }
+
+    #[test]
+    fn test_unbonding_config_owner_only() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+
+        env.set_caller(env.get_account(1));
+        assert_eq!(
+            vault.try_set_unbonding_config(env.auction_delay(), 1),
+            Err(AccessControlError::MissingRole.into())
+        );
+    }
+
+    #[test]
+    fn test_withdrawal_partially_fulfilled() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+
+        let deposit = U512::from(1_000_000_000_000u64);
+        env.set_caller(env.get_account(1));
+        let stcspr = vault.with_tokens(deposit).deposit();
+
+        // Most of the pool is staked, only 200 CSPR stays liquid
+        env.set_caller(env.get_account(0));
+        let validator = env.get_validator(0);
+        vault.add_validator(validator.clone());
+        vault.delegate_to_validator(validator, U512::from(800_000_000_000u64));
+
+        env.set_caller(env.get_account(1));
+        let withdrawal_id = vault.request_withdrawal(stcspr / U256::from(2u64));
+        assert_eq!(vault.get_claimable_amount(withdrawal_id), U512::from(200_000_000_000u64));
+        assert_eq!(vault.get_total_queued(), U512::from(300_000_000_000u64));
+
+        // Claim the filled part, the rest stays queued
+        let request = vault.get_withdrawal_request(withdrawal_id).unwrap();
+        env.advance_block_time(request.unlock_time);
+        vault.claim_withdrawal(withdrawal_id);
+        let request = vault.get_withdrawal_request(withdrawal_id).unwrap();
+        assert_eq!(request.claimed_amount, U512::from(200_000_000_000u64));
+        assert!(!request.is_claimed);
+
+        // A later deposit finalizes the remainder
+        env.set_caller(env.get_account(2));
+        vault.with_tokens(U512::from(400_000_000_000u64)).deposit();
+        assert_eq!(vault.get_total_queued(), U512::zero());
+        assert_eq!(vault.get_idle_cspr(), U512::from(100_000_000_000u64));
+
+        env.set_caller(env.get_account(1));
+        vault.claim_withdrawal(withdrawal_id);
+        let request = vault.get_withdrawal_request(withdrawal_id).unwrap();
+        assert_eq!(request.claimed_amount, request.cspr_amount);
+        assert!(request.is_claimed);
+        assert_eq!(vault.get_total_reserved(), U512::zero());
+    }
+
+    #[test]
+    fn test_withdrawal_queue_is_fifo() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+
+        env.set_caller(env.get_account(1));
+        let small = vault.with_tokens(U512::from(100_000_000_000u64)).deposit();
+        env.set_caller(env.get_account(2));
+        let whale = vault.with_tokens(U512::from(2_000_000_000_000u64)).deposit();
+
+        env.set_caller(env.get_account(0));
+        let validator = env.get_validator(0);
+        vault.add_validator(validator.clone());
+        vault.delegate_to_validator(validator, U512::from(2_000_000_000_000u64));
+
+        // The small holder queues first; the whale cannot jump ahead of them
+        env.set_caller(env.get_account(1));
+        let small_id = vault.request_withdrawal(small);
+        env.set_caller(env.get_account(2));
+        let whale_id = vault.request_withdrawal(whale);
+
+        assert_eq!(vault.get_claimable_amount(small_id), U512::from(100_000_000_000u64));
+        assert_eq!(vault.get_claimable_amount(whale_id), U512::zero());
+
+        let position = vault.get_queue_position(whale_id);
+        assert_eq!(position.cspr_ahead, U512::zero());
+        assert_eq!(position.fulfilled_amount, U512::zero());
+        assert_eq!(position.remaining_amount, U512::from(2_000_000_000_000u64));
+
+        // Whale's claim does not touch liquidity reserved for the earlier request
+        let request = vault.get_withdrawal_request(whale_id).unwrap();
+        env.advance_block_time(request.unlock_time);
+        assert_eq!(
+            vault.try_claim_withdrawal(whale_id),
+            Err(WithdrawalQueueError::NothingToClaim.into())
+        );
+    }
+
+    #[test]
+    fn test_queue_position_estimates_fulfilment() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+
+        let era = env.auction_delay();
+        vault.set_unbonding_config(era, 7);
+
+        let deposit = U512::from(1_000_000_000_000u64);
+        env.set_caller(env.get_account(1));
+        let stcspr = vault.with_tokens(deposit).deposit();
+
+        env.set_caller(env.get_account(0));
+        let validator = env.get_validator(0);
+        vault.add_validator(validator.clone());
+        vault.delegate_to_validator(validator.clone(), deposit);
+
+        env.set_caller(env.get_account(1));
+        let first_id = vault.request_withdrawal(stcspr / U256::from(2u64));
+        let second_id = vault.request_withdrawal(stcspr / U256::from(2u64));
+
+        // Nothing liquid and nothing unbonding: no estimate yet
+        let position = vault.get_queue_position(second_id);
+        assert_eq!(position.cspr_ahead, U512::from(500_000_000_000u64));
+        assert_eq!(position.estimated_fulfilment_time, None);
+
+        // Undelegating covers the queue once it unbonds
+        env.set_caller(env.get_account(0));
+        vault.undelegate_from_validator(validator, deposit);
+        let request = vault.get_withdrawal_request(second_id).unwrap();
+        let position = vault.get_queue_position(second_id);
+        assert_eq!(position.estimated_fulfilment_time, Some(request.unlock_time));
+
+        // Returned CSPR finalizes both requests in order
+        env.advance_with_auctions(request.unlock_time);
+        vault.settle_unbonded();
+        assert_eq!(vault.get_total_queued(), U512::zero());
+        assert_eq!(vault.get_queue_position(first_id).remaining_amount, U512::zero());
+
+        env.set_caller(env.get_account(1));
+        vault.claim_withdrawal(first_id);
+        vault.claim_withdrawal(second_id);
+        assert_eq!(env.balance_of(&vault), U512::zero());
+    }
+
+    #[test]
+    fn test_withdrawal_ticket_transfer() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+
+        let seller = env.get_account(1);
+        let buyer = env.get_account(2);
+        env.set_caller(seller);
+        let stcspr = vault.with_tokens(U512::from(100_000_000_000u64)).deposit();
+        let first_id = vault.request_withdrawal(stcspr / U256::from(2u64));
+        let second_id = vault.request_withdrawal(stcspr / U256::from(2u64));
+
+        // Sell the first ticket before it unlocks
+        vault.transfer_withdrawal(seller, buyer, first_id);
+        assert_eq!(vault.get_withdrawal_owner(first_id), Some(buyer));
+        assert_eq!(vault.get_user_withdrawals(seller), vec![second_id]);
+        assert_eq!(vault.get_user_withdrawals(buyer), vec![first_id]);
+        assert_eq!(vault.get_withdrawal_request(first_id).unwrap().user, seller);
+
+        // The buyer is paid on claim
+        let request = vault.get_withdrawal_request(first_id).unwrap();
+        env.advance_block_time(request.unlock_time);
+        env.set_caller(buyer);
+        let balance_before = env.balance_of(&buyer);
+        vault.claim_withdrawal(first_id);
+        assert_eq!(env.balance_of(&buyer), balance_before + request.cspr_amount);
+    }
+
+    #[test]
+    fn test_withdrawal_ticket_approvals() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+
+        let owner = env.get_account(1);
+        let spender = env.get_account(2);
+        let operator = env.get_account(3);
+        let lender = env.get_account(4);
+        env.set_caller(owner);
+        let stcspr = vault.with_tokens(U512::from(100_000_000_000u64)).deposit();
+        let first_id = vault.request_withdrawal(stcspr / U256::from(2u64));
+        let second_id = vault.request_withdrawal(stcspr / U256::from(2u64));
+
+        // Single-ticket approval is consumed by the transfer
+        vault.approve_withdrawal(spender, first_id);
+        assert_eq!(vault.get_withdrawal_approved(first_id), Some(spender));
+        env.set_caller(spender);
+        vault.transfer_withdrawal(owner, lender, first_id);
+        assert_eq!(vault.get_withdrawal_owner(first_id), Some(lender));
+        assert_eq!(vault.get_withdrawal_approved(first_id), None);
+
+        // Operators may move any of the owner's tickets
+        env.set_caller(owner);
+        vault.set_withdrawal_operator(operator, true);
+        assert!(vault.is_withdrawal_operator(owner, operator));
+        env.set_caller(operator);
+        vault.transfer_withdrawal(owner, lender, second_id);
+        assert_eq!(vault.get_user_withdrawals(lender), vec![first_id, second_id]);
+        assert!(vault.get_user_withdrawals(owner).is_empty());
+    }
+
+    #[test]
+    fn test_withdrawal_ticket_permit() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+
+        let owner = env.get_account(1);
+        let spender = env.get_account(2);
+        env.set_caller(owner);
+        let stcspr = vault.with_tokens(U512::from(100_000_000_000u64)).deposit();
+        let withdrawal_id = vault.request_withdrawal(stcspr);
+        let deadline = env.block_time() + 60_000;
+
+        // The owner signs off-chain; the spender submits and moves the ticket
+        let message = vault.get_withdrawal_permit_message(owner, spender, withdrawal_id, deadline);
+        let signature = env.sign_message(&message, &owner);
+        env.set_caller(spender);
+        vault.permit_withdrawal(owner, spender, withdrawal_id, deadline, signature.clone(), env.public_key(&owner));
+        assert_eq!(vault.get_withdrawal_approved(withdrawal_id), Some(spender));
+        assert_eq!(vault.get_permit_nonce(owner), 1);
+        assert_eq!(
+            vault.try_permit_withdrawal(owner, spender, withdrawal_id, deadline, signature, env.public_key(&owner)),
+            Err(PermitError::InvalidPermitSignature.into())
+        );
+        vault.transfer_withdrawal(owner, spender, withdrawal_id);
+        assert_eq!(vault.get_withdrawal_owner(withdrawal_id), Some(spender));
+
+        // A valid signature still needs the signer to own the ticket
+        let message = vault.get_withdrawal_permit_message(owner, env.get_account(3), withdrawal_id, deadline);
+        let signature = env.sign_message(&message, &owner);
+        assert_eq!(
+            vault.try_permit_withdrawal(owner, env.get_account(3), withdrawal_id, deadline, signature, env.public_key(&owner)),
+            Err(WithdrawalQueueError::NotTicketOwner.into())
+        );
+    }
+
+    #[test]
+    fn test_withdrawal_ticket_transfer_requires_approval() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+
+        env.set_caller(env.get_account(1));
+        let stcspr = vault.with_tokens(U512::from(100_000_000_000u64)).deposit();
+        let withdrawal_id = vault.request_withdrawal(stcspr);
+
+        env.set_caller(env.get_account(2));
+        assert_eq!(
+            vault.try_transfer_withdrawal(env.get_account(1), env.get_account(2), withdrawal_id),
+            Err(WithdrawalQueueError::NotApprovedForTicket.into())
+        );
+        assert_eq!(vault.get_withdrawal_owner(withdrawal_id), Some(env.get_account(1)));
+    }
+
+    #[test]
+    fn test_sold_ticket_cannot_be_claimed_by_requester() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+
+        env.set_caller(env.get_account(1));
+        let stcspr = vault.with_tokens(U512::from(100_000_000_000u64)).deposit();
+        let withdrawal_id = vault.request_withdrawal(stcspr);
+        vault.transfer_withdrawal(env.get_account(1), env.get_account(2), withdrawal_id);
+
+        let request = vault.get_withdrawal_request(withdrawal_id).unwrap();
+        env.advance_block_time(request.unlock_time);
+        assert_eq!(
+            vault.try_claim_withdrawal(withdrawal_id),
+            Err(VaultError::NotWithdrawalOwner.into())
+        );
+
+        env.set_caller(env.get_account(2));
+        vault.claim_withdrawal(withdrawal_id);
+        assert!(vault.get_withdrawal_request(withdrawal_id).unwrap().is_claimed);
+    }
+
+    #[test]
+    fn test_instant_withdraw_fee_accrues_to_holders() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+
+        let deposit = U512::from(1_000_000_000_000u64);
+        env.set_caller(env.get_account(1));
+        let stcspr = vault.with_tokens(deposit).deposit();
+        env.set_caller(env.get_account(2));
+        vault.with_tokens(deposit).deposit();
+
+        // 100 CSPR out of a 2000 CSPR idle pool keeps the buffer above target
+        env.set_caller(env.get_account(1));
+        let amount = stcspr / U256::from(10u64);
+        let quote = vault.get_instant_withdraw_quote(amount);
+        assert_eq!(quote.fee_bps, 10);
+        assert_eq!(quote.fee, U512::from(100_000_000u64));
+        assert_eq!(quote.available_liquidity, deposit * 2);
+
+        let balance_before = env.balance_of(&env.get_account(1));
+        let rate_before = vault.get_exchange_rate();
+        let paid = vault.instant_withdraw(amount, quote.cspr_amount);
+        assert_eq!(paid, quote.cspr_amount);
+        assert_eq!(env.balance_of(&env.get_account(1)), balance_before + paid);
+
+        // The fee stays in the pool for remaining holders
+        assert_eq!(vault.get_tvl(), deposit * 2 - paid);
+        assert!(vault.get_exchange_rate() > rate_before);
+    }
+
+    #[test]
+    fn test_instant_withdraw_fee_rises_as_buffer_drains() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+
+        let deposit = U512::from(1_000_000_000_000u64);
+        env.set_caller(env.get_account(1));
+        let stcspr = vault.with_tokens(deposit).deposit();
+
+        // Stake 900 CSPR, leaving exactly the 10% buffer target idle
+        env.set_caller(env.get_account(0));
+        let validator = env.get_validator(0);
+        vault.add_validator(validator.clone());
+        vault.delegate_to_validator(validator, U512::from(900_000_000_000u64));
+        assert_eq!(vault.get_buffer_target(), U512::from(100_000_000_000u64));
+
+        // Draining half the buffer sits halfway up the curve
+        let half = vault.get_instant_withdraw_quote(stcspr / U256::from(20u64));
+        assert_eq!(half.fee_bps, 10 + (300 - 10) / 2);
+
+        // Draining it completely costs the maximum fee
+        let full = vault.get_instant_withdraw_quote(stcspr / U256::from(10u64));
+        assert_eq!(full.fee_bps, 300);
+        assert_eq!(full.available_liquidity, U512::from(100_000_000_000u64));
+
+        env.set_caller(env.get_account(1));
+        vault.instant_withdraw(stcspr / U256::from(10u64), U512::zero());
+        assert_eq!(vault.get_instant_withdraw_quote(U256::from(1u64)).available_liquidity, full.fee);
     }
 
     #[test]
-    fn test_withdrawal_queue() {
+    fn test_instant_withdraw_limited_to_buffer() {
         let env = odra_test::env();
         let treasury = env.get_account(9);
-        let mut vault = StakeFlowVaultV3::deploy(&env, StakeFlowVaultV3InitArgs {
-            treasury_address: treasury,
-            unbonding_days: 7,
-        });
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
 
-        // Deposit
-        let amount = U512::from(100_000_000_000u64);
+        let deposit = U512::from(1_000_000_000_000u64);
         env.set_caller(env.get_account(1));
-        let stcspr = vault.with_tokens(amount).deposit();
+        let stcspr = vault.with_tokens(deposit).deposit();
 
-        // Request withdrawal
-        let withdrawal_id = vault.request_withdrawal(stcspr);
+        env.set_caller(env.get_account(0));
+        let validator = env.get_validator(0);
+        vault.add_validator(validator.clone());
+        vault.delegate_to_validator(validator, U512::from(900_000_000_000u64));
 
-        // Check withdrawal request
-        let request = vault.get_withdrawal_request(withdrawal_id).unwrap();
-        assert_eq!(request.user, env.get_account(1));
-        assert!(!request.is_claimed);
+        env.set_caller(env.get_account(1));
+        assert_eq!(
+            vault.try_instant_withdraw(stcspr / U256::from(5u64), U512::zero()),
+            Err(VaultError::InsufficientBufferLiquidity.into())
+        );
+    }
 
-        // stCSPR should be burned
-        assert_eq!(vault.balance_of(env.get_account(1)), U256::zero());
+    #[test]
+    fn test_instant_withdraw_slippage_guard() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+
+        let deposit = U512::from(1_000_000_000_000u64);
+        env.set_caller(env.get_account(1));
+        let stcspr = vault.with_tokens(deposit).deposit();
+
+        // Asking for the full value ignores the fee
+        assert_eq!(
+            vault.try_instant_withdraw(stcspr, deposit),
+            Err(VaultError::SlippageExceeded.into())
+        );
+        assert_eq!(vault.get_tvl(), deposit);
     }
 
     #[test]
-    fn test_rewards_compounding() {
+    fn test_operator_role_runs_keeper_tasks() {
         let env = odra_test::env();
         let treasury = env.get_account(9);
-        let mut vault = StakeFlowVaultV3::deploy(&env, StakeFlowVaultV3InitArgs {
-            treasury_address: treasury,
-            unbonding_days: 7,
-        });
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+
+        let admin = env.get_account(0);
+        let keeper = env.get_account(5);
+        assert!(vault.has_role(Role::Admin, admin));
+        assert_eq!(vault.get_role_admin(Role::Operator), Role::Admin);
+
+        vault.grant_role(Role::Operator, keeper);
+        assert!(vault.has_role(Role::Operator, keeper));
 
-        // User deposits
-        let deposit = U512::from(1000_000_000_000u64); // 1000 CSPR
         env.set_caller(env.get_account(1));
-        let stcspr_before = vault.with_tokens(deposit).deposit();
+        vault.with_tokens(U512::from(1_000_000_000_000u64)).deposit();
+
+        // The keeper delegates without admin rights
+        let validator = env.get_validator(0);
+        env.set_caller(admin);
+        vault.add_validator(validator.clone());
+        env.set_caller(keeper);
+        vault.delegate_to_validator(validator, U512::from(500_000_000_000u64));
+        assert!(!vault.has_role(Role::Admin, keeper));
+
+        // Admin revokes; the keeper can also renounce
+        env.set_caller(admin);
+        vault.revoke_role(Role::Operator, keeper);
+        assert!(!vault.has_role(Role::Operator, keeper));
+        vault.renounce_role(Role::Operator, admin);
+        assert!(!vault.has_role(Role::Operator, admin));
+    }
+
+    #[test]
+    fn test_operator_cannot_change_fees() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+
+        let keeper = env.get_account(5);
+        vault.grant_role(Role::Operator, keeper);
+
+        env.set_caller(keeper);
+        assert_eq!(
+            vault.try_schedule_admin_call(VaultAdminCall::SetPerformanceFee(1000)),
+            Err(AccessControlError::MissingRole.into())
+        );
+    }
+
+    #[test]
+    fn test_role_admin_hierarchy() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+
+        // Guardians manage the operator set
+        let guardian = env.get_account(6);
+        let keeper = env.get_account(5);
+        vault.grant_role(Role::Guardian, guardian);
+        vault.set_role_admin(Role::Operator, Role::Guardian);
+        assert_eq!(vault.get_role_admin(Role::Operator), Role::Guardian);
+
+        env.set_caller(guardian);
+        vault.grant_role(Role::Operator, keeper);
+        assert!(vault.has_role(Role::Operator, keeper));
+
+        // Without the guardian role the admin no longer administers operators
+        let admin = env.get_account(0);
+        env.set_caller(admin);
+        vault.renounce_role(Role::Guardian, admin);
+        assert_eq!(
+            vault.try_revoke_role(Role::Operator, keeper),
+            Err(AccessControlError::MissingRole.into())
+        );
+        assert!(vault.has_role(Role::Operator, keeper));
+    }
+
+    #[test]
+    fn test_timelocked_admin_calls() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+
+        let new_treasury = env.get_account(8);
+        let fee_id = vault.schedule_admin_call(VaultAdminCall::SetPerformanceFee(1000));
+        let treasury_id = vault.schedule_admin_call(VaultAdminCall::SetTreasury(new_treasury));
+
+        // Nothing changes until the delay passes
+        assert_eq!(vault.get_performance_fee(), 500);
+        let operation = vault.get_admin_call(fee_id).unwrap();
+        assert_eq!(operation.eta, env.block_time() + timelock::DEFAULT_MIN_DELAY);
+
+        env.advance_block_time(vault.get_timelock_delay());
+        vault.execute_admin_call(fee_id);
+        vault.execute_admin_call(treasury_id);
+        assert_eq!(vault.get_performance_fee(), 1000);
+        assert_eq!(vault.get_treasury(), new_treasury);
+        assert!(vault.get_admin_call(fee_id).unwrap().executed);
+    }
+
+    #[test]
+    fn test_admin_call_waits_for_delay() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+
+        let operation_id = vault.schedule_admin_call(VaultAdminCall::SetPerformanceFee(1000));
+        env.advance_block_time(vault.get_timelock_delay() - 1);
+        assert_eq!(
+            vault.try_execute_admin_call(operation_id),
+            Err(TimelockError::TimelockNotExpired.into())
+        );
+
+        env.advance_block_time(1);
+        vault.execute_admin_call(operation_id);
+        assert_eq!(vault.get_performance_fee(), 1000);
+    }
+
+    #[test]
+    fn test_guardian_cancels_admin_call() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+
+        let guardian = env.get_account(6);
+        vault.grant_role(Role::Guardian, guardian);
+        let operation_id = vault.schedule_admin_call(VaultAdminCall::SetTreasury(env.get_account(8)));
+
+        env.set_caller(guardian);
+        vault.cancel_admin_call(operation_id);
+        assert!(vault.get_admin_call(operation_id).unwrap().cancelled);
 
-        // Owner compounds rewards
+        // A cancelled call can never execute
         env.set_caller(env.get_account(0));
-        let rewards = U512::from(100_000_000_000u64); // 100 CSPR rewards
-        vault.compound_rewards(rewards);
+        env.advance_block_time(vault.get_timelock_delay());
+        assert_eq!(
+            vault.try_execute_admin_call(operation_id),
+            Err(TimelockError::OperationCancelled.into())
+        );
+        assert_eq!(vault.get_treasury(), treasury);
+    }
 
-        // Exchange rate should increase
+    #[test]
+    fn test_two_step_ownership_transfer() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+
+        let old_owner = env.get_account(0);
+        let new_owner = env.get_account(7);
+        vault.transfer_ownership(new_owner);
+
+        // Until accepted the old owner keeps control
+        assert_eq!(vault.get_owner(), old_owner);
+        assert_eq!(vault.get_pending_owner(), Some(new_owner));
+        assert!(!vault.has_role(Role::Admin, new_owner));
+
+        env.set_caller(new_owner);
+        vault.accept_ownership();
+        assert_eq!(vault.get_owner(), new_owner);
+        assert_eq!(vault.get_pending_owner(), None);
+        assert!(vault.has_role(Role::Admin, new_owner));
+        assert!(!vault.has_role(Role::Admin, old_owner));
+    }
+
+    #[test]
+    fn test_only_pending_owner_accepts() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+
+        vault.transfer_ownership(env.get_account(7));
+        env.set_caller(env.get_account(8));
+        assert_eq!(
+            vault.try_accept_ownership(),
+            Err(AccessControlError::NotPendingOwner.into())
+        );
+        assert_eq!(vault.get_owner(), env.get_account(0));
+    }
+
+    /// V3 as installed at storage v1 and before, with stCSPR held inline
+    /// Its fields mirror that layout so the current code can be upgraded over it
+    #[odra::module]
+    pub struct StakeFlowVaultV3StorageV1 {
+        #[allow(dead_code)]
+        total_cspr: Var<U512>,
+        delegation: SubModule<DelegationManager>,
+        access: SubModule<AccessControl>,
+        balances: Mapping<Address, U256>,
+        #[allow(dead_code)]
+        allowances: Mapping<(Address, Address), U256>,
+        total_supply: Var<U256>,
+        exchange_rate: Var<U256>,
+        #[allow(dead_code)]
+        withdrawal_queue: SubModule<WithdrawalQueue>,
+        treasury: Var<Address>,
+        performance_fee_bps: Var<u32>,
+        buffer_config: Var<BufferConfig>,
+        timelock: SubModule<Timelock>,
+        storage_version: Var<u32>,
+    }
+
+    #[odra::module]
+    impl StakeFlowVaultV3StorageV1 {
+        /// Version 0 is a deployment from before storage versioning
+        pub fn init(&mut self, treasury_address: Address, storage_version: u32) {
+            let caller = self.env().caller();
+            self.access.init_owner(caller);
+            self.access.setup_role(Role::Operator, caller);
+            self.treasury.set(treasury_address);
+            self.performance_fee_bps.set(500);
+            self.exchange_rate.set(U256::from(math::RATE_SCALE));
+            let era_duration = delegation::DEFAULT_ERA_DURATION;
+            self.delegation.init_eras(era_duration, delegation::unbonding_eras_for_days(7, era_duration));
+            if storage_version > 0 {
+                self.buffer_config.set(default_buffer_config());
+                self.timelock.init_delay(timelock::DEFAULT_MIN_DELAY);
+                self.storage_version.set(storage_version);
+            }
+        }
+
+        /// Mint inline stCSPR at the inline exchange rate
+        #[odra(payable)]
+        pub fn deposit(&mut self) -> U256 {
+            let caller = self.env().caller();
+            let cspr_amount = self.env().attached_value();
+            let stcspr_amount = U256::from(cspr_amount.as_u128()) * U256::from(math::RATE_SCALE)
+                / self.exchange_rate.get_or_default();
+            self.balances.set(&caller, self.balances.get(&caller).unwrap_or_default() + stcspr_amount);
+            self.total_supply.set(self.total_supply.get_or_default() + stcspr_amount);
+            self.total_cspr.set(self.total_cspr.get_or_default() + cspr_amount);
+            stcspr_amount
+        }
+
+        /// Pool rewards, raising the inline exchange rate
+        #[odra(payable)]
+        pub fn compound_rewards(&mut self) {
+            let total_cspr = self.total_cspr.get_or_default() + self.env().attached_value();
+            self.total_cspr.set(total_cspr);
+            self.exchange_rate.set(
+                U256::from(total_cspr.as_u128()) * U256::from(math::RATE_SCALE) / self.total_supply.get_or_default()
+            );
+        }
+    }
+
+    /// Install the storage-v1 vault at `storage_version`, upgradable into the current code
+    fn deploy_storage_v1_vault(env: &HostEnv, storage_version: u32) -> StakeFlowVaultV3StorageV1HostRef {
+        StakeFlowVaultV3StorageV1::deploy_with_cfg(
+            env,
+            StakeFlowVaultV3StorageV1InitArgs {
+                treasury_address: env.get_account(9),
+                storage_version,
+            },
+            InstallConfig::upgradable::<StakeFlowVaultV3StorageV1>(),
+        )
+    }
+
+    /// Number of storage migration steps a vault has run
+    fn storage_migrations(env: &HostEnv, vault: &StakeFlowVaultV3HostRef) -> usize {
+        env.event_names(vault).iter().filter(|name| name.ends_with("StorageMigrated")).count()
+    }
+
+    #[test]
+    fn test_upgrade_runs_each_pending_migration_once() {
+        for from_version in 0..STORAGE_VERSION {
+            let env = odra_test::env();
+            let legacy = deploy_storage_v1_vault(&env, from_version);
+
+            let vault = StakeFlowVaultV3::try_upgrade(&env, legacy.address(), NoArgs).unwrap();
+            assert_eq!(vault.get_storage_version(), STORAGE_VERSION);
+            assert_eq!(storage_migrations(&env, &vault), (STORAGE_VERSION - from_version) as usize);
+            for version in (from_version + 1)..=STORAGE_VERSION {
+                assert!(env.emitted_event(&vault, StorageMigrated { version, timestamp: env.block_time() }));
+            }
+            assert!(env.emitted_event(&vault, VaultUpgraded {
+                from_version,
+                to_version: STORAGE_VERSION,
+                timestamp: env.block_time(),
+            }));
+
+            // State later versions introduced is backfilled
+            assert_eq!(vault.get_buffer_config(), default_buffer_config());
+            assert_eq!(vault.get_timelock_delay(), timelock::DEFAULT_MIN_DELAY);
+            assert_eq!(vault.get_oracle_quorum(), oracle::DEFAULT_QUORUM);
+
+            // Upgrading again at the current version runs nothing
+            let vault = StakeFlowVaultV3::try_upgrade(&env, vault.address(), NoArgs).unwrap();
+            assert_eq!(storage_migrations(&env, &vault), (STORAGE_VERSION - from_version) as usize);
+            assert_eq!(vault.get_storage_version(), STORAGE_VERSION);
+        }
+    }
+
+    #[test]
+    fn test_upgrade_rejects_newer_storage() {
+        let env = odra_test::env();
+        let legacy = deploy_storage_v1_vault(&env, STORAGE_VERSION + 1);
+        assert_eq!(
+            StakeFlowVaultV3::try_upgrade(&env, legacy.address(), NoArgs).err(),
+            Some(VaultError::CannotDowngradeStorage.into())
+        );
+    }
+
+    #[test]
+    fn test_unknown_storage_version_has_no_migration() {
+        let env = odra_test::env();
+        let mut vault = StakeFlowVaultV3::new(Rc::new(env.contract_env()));
+
+        let reverted = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            vault.migrate_storage(STORAGE_VERSION + 1)
+        }));
+        let message = *reverted.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.contains(&format!("{:?}", OdraError::from(VaultError::NoStorageMigration))));
+    }
+
+    #[test]
+    fn test_upgrade_moves_inline_stcspr_to_the_token() {
+        let env = odra_test::env();
+        let (alice, bob, carol) = (env.get_account(1), env.get_account(2), env.get_account(3));
+        let legacy = deploy_storage_v1_vault(&env, 1);
+
+        env.set_caller(alice);
+        let alice_stcspr = legacy.with_tokens(U512::from(1_000_000_000_000u64)).deposit();
+        env.set_caller(env.get_account(0));
+        legacy.with_tokens(U512::from(50_000_000_000u64)).compound_rewards();
+        env.set_caller(bob);
+        let bob_stcspr = legacy.with_tokens(U512::from(525_000_000_000u64)).deposit();
+        assert!(bob_stcspr < U256::from(525_000_000_000u64));
+
+        env.set_caller(env.get_account(0));
+        let mut vault = StakeFlowVaultV3::try_upgrade(&env, legacy.address(), NoArgs).unwrap();
+        assert_eq!(vault.get_legacy_stcspr_balance(alice), alice_stcspr);
+        assert_eq!(vault.get_legacy_stcspr_balance(bob), bob_stcspr);
+        let tvl = vault.get_tvl();
+        assert_eq!(tvl, U512::from(1_575_000_000_000u64));
+
+        // Linking the token seeds it with the inline supply and the pool backing it
+        let mut token = link_token(&env, &mut vault);
+        assert_eq!(token.total_supply(), alice_stcspr + bob_stcspr);
+        assert_eq!(token.balance_of(vault.address()), alice_stcspr + bob_stcspr);
+        assert_eq!(token.get_total_cspr_backing(), U256::from(1_575_000_000_000u64));
+
+        // Anyone can move holders over; holders without inline stCSPR are skipped
+        env.set_caller(carol);
+        vault.migrate_legacy_stcspr(vec![alice, bob, carol]);
+        assert_eq!(token.balance_of(alice), alice_stcspr);
+        assert_eq!(token.balance_of(bob), bob_stcspr);
+        assert_eq!(token.balance_of(carol), U256::zero());
+        assert_eq!(token.balance_of(vault.address()), U256::zero());
+        assert_eq!(vault.get_legacy_stcspr_balance(alice), U256::zero());
+        assert!(env.emitted_event(&vault, LegacyStCSPRMigrated {
+            holder: bob,
+            stcspr_amount: bob_stcspr,
+            timestamp: env.block_time(),
+        }));
+
+        // Migrated stCSPR keeps its value and works like any other
+        assert_eq!(vault.get_tvl(), tvl);
+        let alice_value = vault.stcspr_to_cspr(alice_stcspr);
+        assert!(alice_value > U512::from(1_049_000_000_000u64) && alice_value <= U512::from(1_050_000_000_000u64));
+        env.set_caller(bob);
+        token.transfer(carol, bob_stcspr / U256::from(2u64));
+        let withdrawal_id = vault.request_withdrawal(bob_stcspr / U256::from(2u64));
+        assert_eq!(vault.get_user_withdrawals(bob), vec![withdrawal_id]);
+    }
+
+    #[test]
+    fn test_upgrade_preserves_state() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let mut vault = StakeFlowVaultV3::deploy_with_cfg(
+            &env,
+            StakeFlowVaultV3InitArgs {
+                treasury_address: treasury,
+                unbonding_days: 7,
+            },
+            InstallConfig::upgradable::<StakeFlowVaultV3>(),
+        );
+        let token = link_token(&env, &mut vault);
+        assert_eq!(vault.get_storage_version(), STORAGE_VERSION);
+
+        let alice = env.get_account(1);
+        let bob = env.get_account(2);
+        env.set_caller(alice);
+        let alice_stcspr = vault.with_tokens(U512::from(1_000_000_000_000u64)).deposit();
+        env.set_caller(bob);
+        let bob_stcspr = vault.with_tokens(U512::from(500_000_000_000u64)).deposit();
+        let withdrawal_id = vault.request_withdrawal(bob_stcspr / U256::from(2u64));
+
+        report_rewards(&env, &mut vault);
+
+        let rate = vault.get_exchange_rate();
+        let tvl = vault.get_tvl();
+        let request = vault.get_withdrawal_request(withdrawal_id).unwrap();
+
+        // Install the new code over the same address
+        let mut vault = StakeFlowVaultV3::try_upgrade(
+            &env,
+            vault.address(),
+            NoArgs,
+        ).unwrap();
+
+        assert_eq!(vault.get_storage_version(), STORAGE_VERSION);
+        assert_eq!(token.balance_of(alice), alice_stcspr);
+        assert_eq!(token.balance_of(bob), bob_stcspr / U256::from(2u64));
+        assert_eq!(vault.get_exchange_rate(), rate);
+        assert_eq!(vault.get_tvl(), tvl);
+        assert_eq!(vault.get_withdrawal_request(withdrawal_id).unwrap(), request);
+        assert_eq!(vault.get_user_withdrawals(bob), vec![withdrawal_id]);
+
+        // The queued withdrawal is still claimable on its original terms
+        env.advance_block_time(request.unlock_time);
+        env.set_caller(bob);
+        vault.claim_withdrawal(withdrawal_id);
+        assert!(vault.get_withdrawal_request(withdrawal_id).unwrap().is_claimed);
+    }
+
+    #[test]
+    fn test_upgrade_requires_admin() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let vault = StakeFlowVaultV3::deploy_with_cfg(
+            &env,
+            StakeFlowVaultV3InitArgs {
+                treasury_address: treasury,
+                unbonding_days: 7,
+            },
+            InstallConfig::upgradable::<StakeFlowVaultV3>(),
+        );
+
+        env.set_caller(env.get_account(1));
+        let result = StakeFlowVaultV3::try_upgrade(
+            &env,
+            vault.address(),
+            NoArgs,
+        );
+        assert_eq!(result.err(), Some(AccessControlError::MissingRole.into()));
+    }
+
+    #[test]
+    fn test_migrate_legacy_positions() {
+        use crate::stakeflow_vault::StakeFlowVault;
+        use crate::stakeflow_minimal::StakeFlowMinimal;
+
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (alice, bob, carol) = (env.get_account(1), env.get_account(2), env.get_account(3));
+
+        let mut legacy = StakeFlowVault::deploy(&env, NoArgs);
+        let mut minimal = StakeFlowMinimal::deploy(&env, NoArgs);
+        let (mut vault, token) = deploy_vault(&env, treasury, 7);
+
+        // Rewards move the V3 rate away from 1:1
+        env.set_caller(carol);
+        vault.with_tokens(U512::from(1_000_000_000_000u64)).deposit();
+        report_rewards(&env, &mut vault);
         let rate = vault.get_exchange_rate();
         assert!(rate > U256::from(1_000_000_000u64));
 
-        // User's stCSPR is now worth more CSPR
-        let cspr_value = vault.stcspr_to_cspr(stcspr_before);
-        assert!(cspr_value > deposit);
+        vault.set_migration_source(legacy.address(), true);
+        vault.set_migration_source(minimal.address(), true);
+        legacy.set_migration_target(vault.address());
+        minimal.set_migration_target(vault.address());
+
+        let amount = U512::from(50_000_000_000u64);
+        env.set_caller(alice);
+        legacy.with_tokens(amount).deposit();
+        minimal.with_tokens(amount).deposit();
+        env.set_caller(bob);
+        minimal.with_tokens(amount).deposit();
+
+        // Alice migrates her v1 position herself
+        env.set_caller(alice);
+        let expected = token.cspr_to_stcspr(U256::from(amount.as_u128()));
+        let from_v1 = legacy.migrate_to_v3();
+        assert_eq!(from_v1, expected);
+        assert_eq!(legacy.get_user_shares(alice), U512::zero());
+        assert_eq!(legacy.get_total_shares(), U512::zero());
+
+        // An operator migrates Minimal depositors in a batch
+        env.set_caller(env.get_account(0));
+        minimal.migrate_batch_to_v3(vec![alice, bob, carol]);
+        assert_eq!(minimal.get_balance(alice), U512::zero());
+        assert_eq!(minimal.get_balance(bob), U512::zero());
+        assert_eq!(minimal.get_total_deposits(), U512::zero());
+
+        assert_eq!(token.balance_of(alice), expected * 2);
+        assert_eq!(token.balance_of(bob), expected);
+        assert_eq!(vault.get_migrated_amount(legacy.address(), alice), amount);
+        assert_eq!(vault.get_migrated_amount(minimal.address(), bob), amount);
+        assert_eq!(vault.get_migrated_amount(legacy.address(), bob), U512::zero());
+        assert_eq!(vault.get_total_migrated(), amount * 3);
+        assert_eq!(vault.get_exchange_rate(), rate);
     }
 
     #[test]
-    fn test_token_transfer() {
+    fn test_migration_repeats_for_later_deposits() {
+        use crate::stakeflow_minimal::StakeFlowMinimal;
+
         let env = odra_test::env();
         let treasury = env.get_account(9);
-        let mut vault = StakeFlowVaultV3::deploy(&env, StakeFlowVaultV3InitArgs {
-            treasury_address: treasury,
-            unbonding_days: 7,
-        });
+        let user = env.get_account(1);
+        let mut minimal = StakeFlowMinimal::deploy(&env, NoArgs);
+        let (mut vault, token) = deploy_vault(&env, treasury, 7);
+        vault.set_migration_source(minimal.address(), true);
+        minimal.set_migration_target(vault.address());
+
+        env.set_caller(user);
+        minimal.with_tokens(U512::from(50_000_000_000u64)).deposit();
+        let first = minimal.migrate_to_v3();
+
+        // Depositing into the legacy contract again after migrating can be migrated too
+        minimal.with_tokens(U512::from(30_000_000_000u64)).deposit();
+        let second = minimal.migrate_to_v3();
+        assert_eq!(token.balance_of(user), first + second);
+        assert_eq!(vault.get_migrated_amount(minimal.address(), user), U512::from(80_000_000_000u64));
+        assert_eq!(vault.get_total_migrated(), U512::from(80_000_000_000u64));
+    }
+
+    #[test]
+    fn test_migrate_attested_position_from_deployed_contract() {
+        use crate::stakeflow_vault::StakeFlowVault;
+        use crate::stakeflow_minimal::StakeFlowMinimal;
+
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (alice, bob) = (env.get_account(1), env.get_account(2));
+
+        // Legacy copies never pointed at V3, as if deployed without the migration entrypoints
+        let mut minimal = StakeFlowMinimal::deploy(&env, NoArgs);
+        let mut legacy = StakeFlowVault::deploy(&env, NoArgs);
+        let (mut vault, token) = deploy_vault(&env, treasury, 7);
+
+        let amount = U512::from(100_000_000_000u64);
+        env.set_caller(alice);
+        minimal.with_tokens(amount).deposit();
+        assert_eq!(
+            vault.try_attest_legacy_position(minimal.address()),
+            Err(MigrationError::NotMigrationSource.into())
+        );
+
+        env.set_caller(env.get_account(0));
+        vault.set_attested_migration_source(minimal.address(), Some(LegacyKind::Minimal));
+        vault.set_attested_migration_source(legacy.address(), Some(LegacyKind::VaultV1));
+
+        // The position is read from the legacy contract, and must be withdrawn there first
+        env.set_caller(alice);
+        assert_eq!(vault.attest_legacy_position(minimal.address()), amount);
+        assert_eq!(
+            vault.with_tokens(amount).try_migrate_attested_position(minimal.address()),
+            Err(MigrationError::LegacyPositionNotExited.into())
+        );
+
+        // Partly withdrawn: only what left the legacy position migrates
+        minimal.withdraw(U512::from(60_000_000_000u64));
+        assert_eq!(
+            vault.with_tokens(U512::from(70_000_000_000u64)).try_migrate_attested_position(minimal.address()),
+            Err(MigrationError::LegacyPositionNotExited.into())
+        );
+        let first = vault.with_tokens(U512::from(60_000_000_000u64)).migrate_attested_position(minimal.address());
+        minimal.withdraw(U512::from(40_000_000_000u64));
+        let second = vault.with_tokens(U512::from(40_000_000_000u64)).migrate_attested_position(minimal.address());
+        assert_eq!(token.balance_of(alice), first + second);
+        assert_eq!(
+            vault.with_tokens(U512::from(1_000_000_000u64)).try_migrate_attested_position(minimal.address()),
+            Err(MigrationError::ExceedsAttestedPosition.into())
+        );
+        assert_eq!(vault.get_legacy_attestation(minimal.address(), alice), Some(LegacyAttestation {
+            attested: amount,
+            migrated: amount,
+        }));
+
+        // A later legacy deposit is attested and migrated afresh
+        minimal.with_tokens(U512::from(20_000_000_000u64)).deposit();
+        assert_eq!(vault.attest_legacy_position(minimal.address()), U512::from(20_000_000_000u64));
+        minimal.withdraw(U512::from(20_000_000_000u64));
+        vault.with_tokens(U512::from(20_000_000_000u64)).migrate_attested_position(minimal.address());
+        assert_eq!(vault.get_migrated_amount(minimal.address(), alice), U512::from(120_000_000_000u64));
+
+        // v1 positions are valued through the vault's share price
+        env.set_caller(bob);
+        legacy.with_tokens(amount).deposit();
+        assert_eq!(
+            vault.with_tokens(amount).try_migrate_attested_position(legacy.address()),
+            Err(MigrationError::NotAttested.into())
+        );
+        assert_eq!(vault.attest_legacy_position(legacy.address()), amount);
+        legacy.withdraw(legacy.get_user_shares(bob));
+        let stcspr_amount = vault.with_tokens(amount).migrate_attested_position(legacy.address());
+        assert_eq!(token.balance_of(bob), stcspr_amount);
+        assert!(env.emitted_event(&vault, PositionMigrated {
+            source: legacy.address(),
+            user: bob,
+            cspr_amount: amount,
+            stcspr_amount,
+            exchange_rate: vault.get_exchange_rate(),
+            timestamp: env.block_time(),
+        }));
+    }
+
+    #[test]
+    fn test_reattestation_keeps_migrated_amount() {
+        use crate::stakeflow_minimal::StakeFlowMinimal;
+
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let alice = env.get_account(1);
+        let mut minimal = StakeFlowMinimal::deploy(&env, NoArgs);
+        let (mut vault, _) = deploy_vault(&env, treasury, 7);
+        env.set_caller(env.get_account(0));
+        vault.set_attested_migration_source(minimal.address(), Some(LegacyKind::Minimal));
 
-        // User 1 deposits
         let amount = U512::from(100_000_000_000u64);
+        env.set_caller(alice);
+        minimal.with_tokens(amount).deposit();
+        vault.attest_legacy_position(minimal.address());
+        minimal.withdraw(U512::from(60_000_000_000u64));
+        vault.with_tokens(U512::from(60_000_000_000u64)).migrate_attested_position(minimal.address());
+
+        // Re-attesting reads the remaining position but keeps what already migrated
+        assert_eq!(vault.attest_legacy_position(minimal.address()), U512::from(40_000_000_000u64));
+        assert_eq!(vault.get_legacy_attestation(minimal.address(), alice), Some(LegacyAttestation {
+            attested: amount,
+            migrated: U512::from(60_000_000_000u64),
+        }));
+
+        // Re-depositing the withdrawn CSPR does not let it migrate before leaving the legacy contract again
+        minimal.with_tokens(U512::from(60_000_000_000u64)).deposit();
+        assert_eq!(
+            vault.with_tokens(U512::from(40_000_000_000u64)).try_migrate_attested_position(minimal.address()),
+            Err(MigrationError::LegacyPositionNotExited.into())
+        );
+        minimal.withdraw(U512::from(100_000_000_000u64));
+        vault.with_tokens(U512::from(40_000_000_000u64)).migrate_attested_position(minimal.address());
+        assert_eq!(
+            vault.with_tokens(U512::from(1_000_000_000u64)).try_migrate_attested_position(minimal.address()),
+            Err(MigrationError::ExceedsAttestedPosition.into())
+        );
+        assert_eq!(vault.get_migrated_amount(minimal.address(), alice), amount);
+    }
+
+    #[test]
+    fn test_migration_requires_registered_source() {
+        use crate::stakeflow_minimal::StakeFlowMinimal;
+
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let mut minimal = StakeFlowMinimal::deploy(&env, NoArgs);
+        let (vault, _) = deploy_vault(&env, treasury, 7);
+        minimal.set_migration_target(vault.address());
+
         env.set_caller(env.get_account(1));
-        let stcspr = vault.with_tokens(amount).deposit();
+        minimal.with_tokens(U512::from(50_000_000_000u64)).deposit();
+        assert_eq!(minimal.try_migrate_to_v3(), Err(MigrationError::NotMigrationSource.into()));
+        assert_eq!(vault.get_migrated_amount(minimal.address(), env.get_account(1)), U512::zero());
+    }
 
-        // Transfer to user 2
-        let user2 = env.get_account(2);
-        let transfer_amount = stcspr / U256::from(2u64);
-        vault.transfer(user2, transfer_amount);
+    #[test]
+    fn test_vault_drives_standalone_token() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, mut token) = deploy_vault(&env, treasury, 7);
+        assert_eq!(vault.get_token(), Some(token.address()));
+        assert_eq!(token.get_vault(), vault.address());
+
+        let alice = env.get_account(1);
+        env.set_caller(alice);
+        let stcspr = vault.with_tokens(U512::from(1_000_000_000_000u64)).deposit();
+        assert_eq!(token.balance_of(alice), stcspr);
+        assert_eq!(token.total_supply(), stcspr);
+
+        // Rewards move the rate on the token itself
+        report_rewards(&env, &mut vault);
+        assert!(token.get_exchange_rate() > U256::from(1_000_000_000u64));
+        assert_eq!(vault.get_exchange_rate(), token.get_exchange_rate());
+
+        // Withdrawing burns on the token
+        env.set_caller(alice);
+        vault.request_withdrawal(stcspr / U256::from(2u64));
+        assert_eq!(token.balance_of(alice), stcspr - stcspr / U256::from(2u64));
+        // The rest of the supply is the treasury's fee shares
+        assert_eq!(
+            token.total_supply(),
+            stcspr - stcspr / U256::from(2u64) + vault.get_treasury_shares()
+        );
 
-        assert_eq!(vault.balance_of(env.get_account(1)), stcspr - transfer_amount);
-        assert_eq!(vault.balance_of(user2), transfer_amount);
+        // Only the vault mints
+        assert_eq!(
+            token.try_mint(alice, U256::from(1u64)),
+            Err(AccessControlError::MissingRole.into())
+        );
     }
 
     #[test]
-    fn test_approve_and_transfer_from() {
+    fn test_set_token_rejects_foreign_token() {
         let env = odra_test::env();
         let treasury = env.get_account(9);
-        let mut vault = StakeFlowVaultV3::deploy(&env, StakeFlowVaultV3InitArgs {
+        let (_vault, token) = deploy_vault(&env, treasury, 7);
+        let mut other = StakeFlowVaultV3::deploy(&env, StakeFlowVaultV3InitArgs {
             treasury_address: treasury,
             unbonding_days: 7,
         });
 
-        // User 1 deposits
-        let amount = U512::from(100_000_000_000u64);
-        env.set_caller(env.get_account(1));
-        let stcspr = vault.with_tokens(amount).deposit();
+        assert_eq!(
+            other.try_set_token(token.address()),
+            Err(VaultError::ForeignToken.into())
+        );
+        assert_eq!(other.get_token(), None);
+    }
 
-        // Approve user 2 as spender
-        let user2 = env.get_account(2);
-        vault.approve(user2, stcspr);
-        assert_eq!(vault.allowance(env.get_account(1), user2), stcspr);
+    #[test]
+    fn test_set_token_only_once() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (mut vault, token) = deploy_vault(&env, treasury, 7);
 
-        // User 2 transfers on behalf of user 1
-        env.set_caller(user2);
-        let user3 = env.get_account(3);
-        vault.transfer_from(env.get_account(1), user3, stcspr / U256::from(2u64));
+        assert_eq!(
+            vault.try_set_token(token.address()),
+            Err(VaultError::TokenAlreadySet.into())
+        );
+    }
+
+    #[test]
+    fn test_errors_carry_stable_codes() {
+        let env = odra_test::env();
+        let treasury = env.get_account(9);
+        let (vault, _) = deploy_vault(&env, treasury, 7);
 
-        assert_eq!(vault.balance_of(user3), stcspr / U256::from(2u64));
+        env.set_caller(env.get_account(1));
+        let result = vault.with_tokens(U512::from(1_000_000_000u64)).try_deposit();
+        assert_eq!(result, Err(VaultError::BelowMinimumDeposit.into()));
+        assert_eq!(result.unwrap_err().code(), 2000);
+        assert_eq!(vault.get_tvl(), U512::zero());
     }
 }
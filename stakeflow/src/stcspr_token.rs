@@ -1,5 +1,11 @@
 use odra::prelude::*;
-use odra::casper_types::U256;
+use odra::ContractRef;
+use odra::casper_types::{PublicKey, U256};
+use odra::casper_types::bytesrepr::{Bytes, ToBytes};
+use crate::access_control::{AccessControl, Role};
+use crate::math::{self, Rounding};
+use crate::permit::PermitNonces;
+use crate::votes::Votes;
 
 /// stCSPR - Liquid Staking Token (CEP-18 Compliant)
 ///
@@ -8,6 +14,8 @@ use odra::casper_types::U256;
 /// - Appreciates in value as staking rewards accrue (non-rebasing model)
 /// - Can be redeemed 1:1 for CSPR through the StakeFlow vault
 /// - Implements CEP-18 standard for maximum compatibility
+/// - Accepts approvals signed off-chain through `permit`
+/// - Checkpoints delegated voting power by block time for governance
 #[odra::module]
 pub struct StCSPRToken {
     /// Token metadata
@@ -24,9 +32,12 @@ pub struct StCSPRToken {
     /// Allowances: owner -> spender -> amount
     allowances: Mapping<(Address, Address), U256>,
 
-    /// Vault contract address (only vault can mint/burn)
+    /// Vault contract address (holds the minter role)
     vault: Var<Address>,
 
+    /// Role-based permissions (minter mints/burns, guardian pauses)
+    access: SubModule<AccessControl>,
+
     /// Exchange rate: CSPR per stCSPR (scaled by 1e9 for precision)
     /// Starts at 1e9 (1:1) and increases as rewards accrue
     exchange_rate: Var<U256>,
@@ -36,25 +47,40 @@ pub struct StCSPRToken {
 
     /// Paused state for emergency controls
     paused: Var<bool>,
+
+    /// Largest exchange-rate move a single update may make, in bps (0 = unbounded)
+    max_rate_change_bps: Var<u32>,
+
+    /// Contract told about balances dropped by transfers (unset = no callback)
+    transfer_hook: Var<Option<Address>>,
+
+    /// Nonces and signature checks for `permit` approvals
+    permits: SubModule<PermitNonces>,
+
+    /// Delegated voting power, checkpointed by block time
+    votes: SubModule<Votes>,
 }
 
 #[odra::module]
 impl StCSPRToken {
     /// Initialize the stCSPR token
-    /// When used as SubModule, the parent vault is the caller
-    pub fn init(&mut self) {
-        let vault_address = self.env().caller();
+    /// The deployer administers the token; `vault` is its minter
+    pub fn init(&mut self, vault: Address) {
+        let owner = self.env().caller();
         self.name.set("Staked CSPR".to_string());
         self.symbol.set("stCSPR".to_string());
         self.decimals.set(9); // Match CSPR decimals
         self.total_supply.set(U256::zero());
-        self.vault.set(vault_address);
-        self.exchange_rate.set(U256::from(1_000_000_000u64)); // 1:1 initially
+        self.vault.set(vault);
+        self.access.init_owner(owner);
+        self.access.setup_role(Role::Guardian, owner);
+        self.access.setup_role(Role::Minter, vault);
+        self.exchange_rate.set(U256::from(math::RATE_SCALE)); // 1:1 initially
         self.total_cspr_backing.set(U256::zero());
         self.paused.set(false);
 
         self.env().emit_event(TokenInitialized {
-            vault: vault_address,
+            vault,
             timestamp: self.env().get_block_time(),
         });
     }
@@ -91,8 +117,12 @@ impl StCSPRToken {
         self.assert_not_paused();
         let caller = self.env().caller();
 
-        assert!(amount > U256::zero(), "Amount must be greater than zero");
-        assert!(caller != recipient, "Cannot transfer to self");
+        if amount.is_zero() {
+            self.env().revert(TokenError::ZeroAmount);
+        }
+        if caller == recipient {
+            self.env().revert(TokenError::CannotTransferToSelf);
+        }
 
         self.transfer_from_to(caller, recipient, amount);
 
@@ -107,16 +137,35 @@ impl StCSPRToken {
     pub fn approve(&mut self, spender: Address, amount: U256) {
         self.assert_not_paused();
         let caller = self.env().caller();
+        self.approve_from(caller, spender, amount);
+    }
 
-        assert!(caller != spender, "Cannot approve self");
+    /// Approve `spender` on behalf of `owner` with a signature `owner` made off-chain
+    /// Anyone may submit it before `deadline`; `public_key` must be the owner's account key
+    /// and the signature must cover `get_permit_message` for the owner's current nonce
+    pub fn permit(
+        &mut self,
+        owner: Address,
+        spender: Address,
+        amount: U256,
+        deadline: u64,
+        signature: Bytes,
+        public_key: PublicKey,
+    ) {
+        self.assert_not_paused();
+        let payload = self.permit_payload(spender, amount);
+        self.permits.use_permit(owner, payload, deadline, signature, public_key);
+        self.approve_from(owner, spender, amount);
+    }
 
-        self.allowances.set(&(caller, spender), amount);
+    /// Get the nonce `owner`'s next permit must be signed with
+    pub fn nonces(&self, owner: Address) -> u64 {
+        self.permits.nonce(owner)
+    }
 
-        self.env().emit_event(Approval {
-            owner: caller,
-            spender,
-            amount,
-        });
+    /// Message `owner` signs to permit `spender` to spend `amount` until `deadline`
+    pub fn get_permit_message(&self, owner: Address, spender: Address, amount: U256, deadline: u64) -> Bytes {
+        self.permits.message(owner, self.permit_payload(spender, amount), deadline)
     }
 
     /// Get allowance for spender from owner
@@ -129,14 +178,14 @@ impl StCSPRToken {
         self.assert_not_paused();
         let caller = self.env().caller();
 
-        assert!(amount > U256::zero(), "Amount must be greater than zero");
-        assert!(owner != recipient, "Cannot transfer to self");
-
-        // Check and update allowance
-        let current_allowance = self.allowances.get(&(owner, caller)).unwrap_or(U256::zero());
-        assert!(current_allowance >= amount, "Insufficient allowance");
+        if amount.is_zero() {
+            self.env().revert(TokenError::ZeroAmount);
+        }
+        if owner == recipient {
+            self.env().revert(TokenError::CannotTransferToSelf);
+        }
 
-        self.allowances.set(&(owner, caller), current_allowance - amount);
+        self.spend_allowance(owner, caller, amount);
 
         // Perform transfer
         self.transfer_from_to(owner, recipient, amount);
@@ -148,20 +197,54 @@ impl StCSPRToken {
         });
     }
 
+    // ===== VOTING POWER =====
+
+    /// Delegate the caller's voting power to `delegatee` (the caller itself to vote directly)
+    /// Balances carry no votes until delegated
+    pub fn delegate(&mut self, delegatee: Address) {
+        let caller = self.env().caller();
+        let balance = self.balance_of(caller);
+        self.votes.delegate(caller, delegatee, balance);
+    }
+
+    /// Get the account an address delegates its votes to, if any
+    pub fn delegates(&self, account: Address) -> Option<Address> {
+        self.votes.delegates(account)
+    }
+
+    /// Get an account's current voting power
+    pub fn get_votes(&self, account: Address) -> U256 {
+        self.votes.votes(account)
+    }
+
+    /// Get an account's voting power at the end of block time `timestamp` (must be in the past)
+    pub fn get_past_votes(&self, account: Address, timestamp: u64) -> U256 {
+        self.votes.past_votes(account, timestamp)
+    }
+
+    /// Get total supply at the end of block time `timestamp` (must be in the past)
+    pub fn get_past_total_supply(&self, timestamp: u64) -> U256 {
+        self.votes.past_total_supply(timestamp)
+    }
+
     // ===== LIQUID STAKING SPECIFIC FUNCTIONS =====
 
-    /// Mint new stCSPR tokens (only vault can call)
+    /// Mint new stCSPR tokens (minter only)
     /// Returns the amount of stCSPR minted
     pub fn mint(&mut self, to: Address, cspr_amount: U256) -> U256 {
-        self.assert_vault();
+        self.assert_role(Role::Minter);
         self.assert_not_paused();
 
-        assert!(cspr_amount > U256::zero(), "Amount must be greater than zero");
+        if cspr_amount.is_zero() {
+            self.env().revert(TokenError::ZeroAmount);
+        }
 
-        // Calculate stCSPR amount based on current exchange rate
-        // stCSPR_amount = CSPR_amount * 1e9 / exchange_rate
+        // Price against the pool itself rather than the rounded rate, in the pool's favour
         let rate = self.exchange_rate.get_or_default();
-        let stcspr_amount = (cspr_amount * U256::from(1_000_000_000u64)) / rate;
+        let stcspr_amount = self.cspr_to_stcspr(cspr_amount);
+        if stcspr_amount.is_zero() {
+            self.env().revert(TokenError::ZeroSharesMinted);
+        }
 
         // Update balances
         let current_balance = self.balances.get(&to).unwrap_or(U256::zero());
@@ -170,10 +253,12 @@ impl StCSPRToken {
         // Update total supply
         let total = self.total_supply.get_or_default();
         self.total_supply.set(total + stcspr_amount);
+        self.votes.transfer_votes(None, Some(to), stcspr_amount);
 
         // Update CSPR backing
         let backing = self.total_cspr_backing.get_or_default();
         self.total_cspr_backing.set(backing + cspr_amount);
+        self.refresh_exchange_rate();
 
         self.env().emit_event(Mint {
             to,
@@ -192,21 +277,88 @@ impl StCSPRToken {
         stcspr_amount
     }
 
-    /// Burn stCSPR tokens (only vault can call)
+    /// Mint stCSPR without adding backing, diluting every holder (minter only)
+    /// Fee shares are priced by the minter; the dilution is not bound by `max_rate_change_bps`
+    pub fn mint_shares(&mut self, to: Address, stcspr_amount: U256) {
+        self.assert_role(Role::Minter);
+        self.assert_not_paused();
+
+        if stcspr_amount.is_zero() {
+            self.env().revert(TokenError::ZeroAmount);
+        }
+
+        let rate = self.exchange_rate.get_or_default();
+        self.balances.set(&to, self.balances.get(&to).unwrap_or_default() + stcspr_amount);
+        self.total_supply.set(self.total_supply.get_or_default() + stcspr_amount);
+        self.votes.transfer_votes(None, Some(to), stcspr_amount);
+        self.refresh_exchange_rate();
+
+        self.env().emit_event(Mint {
+            to,
+            stcspr_amount,
+            cspr_amount: U256::zero(),
+            exchange_rate: rate,
+            timestamp: self.env().get_block_time(),
+        });
+
+        self.env().emit_event(Transfer {
+            from: None,
+            to: Some(to),
+            amount: stcspr_amount,
+        });
+    }
+
+    /// Mint a predecessor's whole stCSPR supply to `holder` with the CSPR backing it (minter only)
+    /// Only a token with no supply yet can be seeded; the exchange rate follows from the two
+    pub fn seed_supply(&mut self, holder: Address, stcspr_amount: U256, cspr_backing: U256) {
+        self.assert_role(Role::Minter);
+        if !self.total_supply.get_or_default().is_zero() {
+            self.env().revert(TokenError::SupplyAlreadyMinted);
+        }
+        if stcspr_amount.is_zero() {
+            self.env().revert(TokenError::ZeroAmount);
+        }
+
+        let current_balance = self.balances.get(&holder).unwrap_or(U256::zero());
+        self.balances.set(&holder, current_balance + stcspr_amount);
+        self.total_supply.set(stcspr_amount);
+        self.votes.transfer_votes(None, Some(holder), stcspr_amount);
+        self.total_cspr_backing.set(cspr_backing);
+        self.refresh_exchange_rate();
+
+        self.env().emit_event(Mint {
+            to: holder,
+            stcspr_amount,
+            cspr_amount: cspr_backing,
+            exchange_rate: self.exchange_rate.get_or_default(),
+            timestamp: self.env().get_block_time(),
+        });
+
+        self.env().emit_event(Transfer {
+            from: None,
+            to: Some(holder),
+            amount: stcspr_amount,
+        });
+    }
+
+    /// Burn stCSPR tokens (minter only)
     /// Returns the amount of CSPR to return to user
     pub fn burn(&mut self, from: Address, stcspr_amount: U256) -> U256 {
-        self.assert_vault();
+        self.assert_role(Role::Minter);
         self.assert_not_paused();
 
-        assert!(stcspr_amount > U256::zero(), "Amount must be greater than zero");
+        if stcspr_amount.is_zero() {
+            self.env().revert(TokenError::ZeroAmount);
+        }
 
         let balance = self.balances.get(&from).unwrap_or(U256::zero());
-        assert!(balance >= stcspr_amount, "Insufficient balance");
+        if balance < stcspr_amount {
+            self.env().revert(TokenError::InsufficientBalance);
+        }
 
-        // Calculate CSPR amount based on current exchange rate
-        // CSPR_amount = stCSPR_amount * exchange_rate / 1e9
+        // Price against the pool itself rather than the rounded rate, in the pool's favour
         let rate = self.exchange_rate.get_or_default();
-        let cspr_amount = (stcspr_amount * rate) / U256::from(1_000_000_000u64);
+        let cspr_amount = self.stcspr_to_cspr(stcspr_amount);
 
         // Update balances
         self.balances.set(&from, balance - stcspr_amount);
@@ -214,10 +366,12 @@ impl StCSPRToken {
         // Update total supply
         let total = self.total_supply.get_or_default();
         self.total_supply.set(total - stcspr_amount);
+        self.votes.transfer_votes(Some(from), None, stcspr_amount);
 
-        // Update CSPR backing
+        // Update CSPR backing; rounding dust may leave it short of the payout
         let backing = self.total_cspr_backing.get_or_default();
-        self.total_cspr_backing.set(backing - cspr_amount);
+        self.total_cspr_backing.set(backing.saturating_sub(cspr_amount));
+        self.refresh_exchange_rate();
 
         self.env().emit_event(Burn {
             from,
@@ -236,10 +390,18 @@ impl StCSPRToken {
         cspr_amount
     }
 
+    /// Burn stCSPR from `from` on behalf of `spender`, spending the allowance `from` granted it (minter only)
+    /// Returns the amount of CSPR to return to user
+    pub fn burn_from(&mut self, from: Address, spender: Address, stcspr_amount: U256) -> U256 {
+        self.assert_role(Role::Minter);
+        self.spend_allowance(from, spender, stcspr_amount);
+        self.burn(from, stcspr_amount)
+    }
+
     /// Update exchange rate when rewards are compounded
-    /// Only vault can call this
+    /// Minter only; reverts if the rate would move more than `max_rate_change_bps`
     pub fn update_exchange_rate(&mut self, new_total_cspr: U256) {
-        self.assert_vault();
+        self.assert_role(Role::Minter);
 
         let total_stcspr = self.total_supply.get_or_default();
 
@@ -248,12 +410,12 @@ impl StCSPRToken {
             return;
         }
 
-        // New exchange rate = total_cspr * 1e9 / total_stcspr
-        let new_rate = (new_total_cspr * U256::from(1_000_000_000u64)) / total_stcspr;
         let old_rate = self.exchange_rate.get_or_default();
+        let new_rate = math::share_price(new_total_cspr, total_stcspr).unwrap_or_revert(&self.env());
+        self.assert_rate_change_within_bound(old_rate, new_rate);
 
-        self.exchange_rate.set(new_rate);
         self.total_cspr_backing.set(new_total_cspr);
+        self.exchange_rate.set(new_rate);
 
         self.env().emit_event(ExchangeRateUpdated {
             old_rate,
@@ -269,16 +431,24 @@ impl StCSPRToken {
         self.exchange_rate.get_or_default()
     }
 
-    /// Get CSPR value for a given amount of stCSPR
+    /// Get CSPR value for a given amount of stCSPR (rounded down)
     pub fn stcspr_to_cspr(&self, stcspr_amount: U256) -> U256 {
-        let rate = self.exchange_rate.get_or_default();
-        (stcspr_amount * rate) / U256::from(1_000_000_000u64)
+        math::shares_to_assets(
+            stcspr_amount,
+            self.total_cspr_backing.get_or_default(),
+            self.total_supply.get_or_default(),
+            Rounding::Down,
+        ).unwrap_or_revert(&self.env())
     }
 
-    /// Get stCSPR amount for a given CSPR value
+    /// Get stCSPR amount for a given CSPR value (rounded down)
     pub fn cspr_to_stcspr(&self, cspr_amount: U256) -> U256 {
-        let rate = self.exchange_rate.get_or_default();
-        (cspr_amount * U256::from(1_000_000_000u64)) / rate
+        math::assets_to_shares(
+            cspr_amount,
+            self.total_cspr_backing.get_or_default(),
+            self.total_supply.get_or_default(),
+            Rounding::Down,
+        ).unwrap_or_revert(&self.env())
     }
 
     /// Get total CSPR backing all stCSPR tokens
@@ -288,14 +458,14 @@ impl StCSPRToken {
 
     /// Get vault address
     pub fn get_vault(&self) -> Address {
-        self.vault.get().expect("Vault not set")
+        self.vault.get().unwrap_or_revert_with(&self.env(), TokenError::VaultNotSet)
     }
 
     // ===== ADMIN FUNCTIONS =====
 
-    /// Emergency pause (only vault can call)
+    /// Emergency pause (guardian only)
     pub fn pause(&mut self) {
-        self.assert_vault();
+        self.assert_role(Role::Guardian);
         self.paused.set(true);
 
         self.env().emit_event(Paused {
@@ -303,9 +473,9 @@ impl StCSPRToken {
         });
     }
 
-    /// Unpause (only vault can call)
+    /// Unpause (guardian only)
     pub fn unpause(&mut self) {
-        self.assert_vault();
+        self.assert_role(Role::Guardian);
         self.paused.set(false);
 
         self.env().emit_event(Unpaused {
@@ -318,32 +488,183 @@ impl StCSPRToken {
         self.paused.get_or_default()
     }
 
+    /// Bound how far one `update_exchange_rate` call may move the rate (admin only)
+    /// A backstop against a single bad input repricing every stCSPR; 0 disables it
+    pub fn set_max_rate_change(&mut self, max_change_bps: u32) {
+        self.assert_role(Role::Admin);
+        if max_change_bps > 10000 {
+            self.env().revert(TokenError::InvalidRateBound);
+        }
+        self.max_rate_change_bps.set(max_change_bps);
+
+        self.env().emit_event(MaxRateChangeUpdated {
+            max_change_bps,
+            timestamp: self.env().get_block_time(),
+        });
+    }
+
+    /// Get the largest move one rate update may make (bps, 0 = unbounded)
+    pub fn get_max_rate_change(&self) -> u32 {
+        self.max_rate_change_bps.get_or_default()
+    }
+
+    /// Set or clear the contract called after transfers lower a holder's balance (admin only)
+    /// The vault tracks referred stake through it; with none set, transfers make no external call
+    pub fn set_transfer_hook(&mut self, hook: Option<Address>) {
+        self.assert_role(Role::Admin);
+        self.transfer_hook.set(hook);
+
+        self.env().emit_event(TransferHookUpdated {
+            hook,
+            timestamp: self.env().get_block_time(),
+        });
+    }
+
+    /// Get the contract called after transfers, if any
+    pub fn get_transfer_hook(&self) -> Option<Address> {
+        self.transfer_hook.get().flatten()
+    }
+
+    // ===== ACCESS CONTROL =====
+
+    delegate! {
+        to self.access {
+            fn grant_role(&mut self, role: Role, account: Address);
+            fn revoke_role(&mut self, role: Role, account: Address);
+            fn renounce_role(&mut self, role: Role, account: Address);
+            fn set_role_admin(&mut self, role: Role, admin_role: Role);
+            fn has_role(&self, role: Role, account: Address) -> bool;
+            fn transfer_ownership(&mut self, new_owner: Address);
+            fn accept_ownership(&mut self);
+            fn get_owner(&self) -> Address;
+            fn get_pending_owner(&self) -> Option<Address>;
+            fn get_role_admin(&self, role: Role) -> Role;
+        }
+    }
+
     // ===== INTERNAL FUNCTIONS =====
 
     /// Internal transfer function
     fn transfer_from_to(&mut self, from: Address, to: Address, amount: U256) {
         let from_balance = self.balances.get(&from).unwrap_or(U256::zero());
-        assert!(from_balance >= amount, "Insufficient balance");
+        if from_balance < amount {
+            self.env().revert(TokenError::InsufficientBalance);
+        }
 
         let to_balance = self.balances.get(&to).unwrap_or(U256::zero());
 
         self.balances.set(&from, from_balance - amount);
         self.balances.set(&to, to_balance + amount);
+        self.votes.transfer_votes(Some(from), Some(to), amount);
+        self.notify_transfer_hook(from);
     }
 
-    /// Assert caller is vault
-    fn assert_vault(&self) {
-        let caller = self.env().caller();
-        let vault = self.vault.get().expect("Vault not set");
-        assert!(caller == vault, "Only vault can call this function");
+    /// Report a holder's lowered balance to the transfer hook, if one is set
+    /// Skipped for the hook's own transfers
+    fn notify_transfer_hook(&self, from: Address) {
+        let hook = match self.get_transfer_hook() {
+            Some(hook) if hook != from => hook,
+            _ => return,
+        };
+        StCSPRTransferHookContractRef::new(self.env(), hook).on_stcspr_transfer(from, self.balance_of(from));
+    }
+
+    /// Set the allowance `owner` grants `spender`
+    fn approve_from(&mut self, owner: Address, spender: Address, amount: U256) {
+        if owner == spender {
+            self.env().revert(TokenError::CannotApproveSelf);
+        }
+
+        self.allowances.set(&(owner, spender), amount);
+
+        self.env().emit_event(Approval {
+            owner,
+            spender,
+            amount,
+        });
+    }
+
+    /// Approval a permit signs for: spender and amount
+    fn permit_payload(&self, spender: Address, amount: U256) -> Bytes {
+        let mut payload = spender.to_bytes().unwrap_or_revert(&self.env());
+        payload.extend(amount.to_bytes().unwrap_or_revert(&self.env()));
+        Bytes::from(payload)
+    }
+
+    /// Deduct `amount` from the allowance `owner` granted `spender`
+    fn spend_allowance(&mut self, owner: Address, spender: Address, amount: U256) {
+        let current_allowance = self.allowances.get(&(owner, spender)).unwrap_or(U256::zero());
+        if current_allowance < amount {
+            self.env().revert(TokenError::InsufficientAllowance);
+        }
+
+        self.allowances.set(&(owner, spender), current_allowance - amount);
+    }
+
+    /// Recompute the stored exchange rate from backing and supply
+    fn refresh_exchange_rate(&mut self) {
+        let rate = math::share_price(
+            self.total_cspr_backing.get_or_default(),
+            self.total_supply.get_or_default(),
+        ).unwrap_or_revert(&self.env());
+        self.exchange_rate.set(rate);
+    }
+
+    /// Revert if moving from `old_rate` to `new_rate` exceeds the per-update bound
+    fn assert_rate_change_within_bound(&self, old_rate: U256, new_rate: U256) {
+        let max_change_bps = self.max_rate_change_bps.get_or_default();
+        if max_change_bps == 0 {
+            return;
+        }
+        let change = if new_rate > old_rate { new_rate - old_rate } else { old_rate - new_rate };
+        let bound = math::mul_div(
+            old_rate,
+            U256::from(max_change_bps),
+            U256::from(math::BPS_DENOMINATOR),
+            Rounding::Down,
+        ).unwrap_or_revert(&self.env());
+        if change > bound {
+            self.env().revert(TokenError::RateChangeTooLarge);
+        }
+    }
+
+    /// Assert caller holds a role
+    fn assert_role(&self, role: Role) {
+        self.access.check_role(role, self.env().caller());
     }
 
     /// Assert token is not paused
     fn assert_not_paused(&self) {
-        assert!(!self.paused.get_or_default(), "Token is paused");
+        if self.paused.get_or_default() {
+            self.env().revert(TokenError::TokenPaused);
+        }
     }
 }
 
+/// Callback the token makes on its transfer hook after a holder's balance drops by a transfer
+#[odra::external_contract]
+pub trait StCSPRTransferHook {
+    /// `from` sent stCSPR and now holds `balance`
+    fn on_stcspr_transfer(&mut self, from: Address, balance: U256);
+}
+
+// ===== ERRORS =====
+
+#[odra::odra_error]
+pub enum TokenError {
+    ZeroAmount = 1000,
+    CannotTransferToSelf = 1001,
+    CannotApproveSelf = 1002,
+    InsufficientBalance = 1003,
+    InsufficientAllowance = 1004,
+    TokenPaused = 1005,
+    VaultNotSet = 1006,
+    SupplyAlreadyMinted = 1007,
+    ZeroSharesMinted = 1008,
+    RateChangeTooLarge = 1009,
+    InvalidRateBound = 1010,
+}
+
 // ===== EVENTS =====
 
 #[odra::event]
@@ -393,6 +714,18 @@ pub struct ExchangeRateUpdated {
     pub timestamp: u64,
 }
 
+#[odra::event]
+pub struct MaxRateChangeUpdated {
+    pub max_change_bps: u32,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct TransferHookUpdated {
+    pub hook: Option<Address>,
+    pub timestamp: u64,
+}
+
 #[odra::event]
 pub struct Paused {
     pub timestamp: u64,
@@ -408,13 +741,20 @@ pub struct Unpaused {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use odra::host::Deployer;
+    use odra::host::{Deployer, HostEnv};
+    use crate::access_control::AccessControlError;
+    use crate::permit::PermitError;
+    use crate::votes::{DelegateChanged, DelegateVotesChanged, VotesError};
+
+    /// Deploy with the deployer acting as the vault
+    fn deploy_token(env: &HostEnv) -> StCSPRTokenHostRef {
+        StCSPRToken::deploy(env, StCSPRTokenInitArgs { vault: env.get_account(0) })
+    }
 
     #[test]
     fn test_initialization() {
         let env = odra_test::env();
-        use odra::host::NoArgs;
-        let token = StCSPRToken::deploy(&env, NoArgs);
+        let token = deploy_token(&env);
 
         assert_eq!(token.name(), "Staked CSPR");
         assert_eq!(token.symbol(), "stCSPR");
@@ -423,11 +763,22 @@ mod tests {
         assert_eq!(token.get_exchange_rate(), U256::from(1_000_000_000u64));
     }
 
+    #[test]
+    fn test_vault_set_at_deploy() {
+        let env = odra_test::env();
+        let vault = env.get_account(5);
+        let token = StCSPRToken::deploy(&env, StCSPRTokenInitArgs { vault });
+
+        assert_eq!(token.get_vault(), vault);
+        assert!(token.has_role(Role::Minter, vault));
+        assert!(!token.has_role(Role::Minter, env.get_account(0)));
+        assert_eq!(token.get_owner(), env.get_account(0));
+    }
+
     #[test]
     fn test_mint() {
         let env = odra_test::env();
-        use odra::host::NoArgs;
-        let mut token = StCSPRToken::deploy(&env, NoArgs);
+        let mut token = deploy_token(&env);
 
         let user = env.get_account(1);
         let cspr_amount = U256::from(100_000_000_000u64); // 100 CSPR
@@ -443,8 +794,7 @@ mod tests {
     #[test]
     fn test_burn() {
         let env = odra_test::env();
-        use odra::host::NoArgs;
-        let mut token = StCSPRToken::deploy(&env, NoArgs);
+        let mut token = deploy_token(&env);
 
         let user = env.get_account(1);
         let cspr_amount = U256::from(100_000_000_000u64);
@@ -459,11 +809,118 @@ mod tests {
         assert_eq!(cspr_returned, cspr_amount / U256::from(2u64));
     }
 
+    #[test]
+    fn test_seed_supply_only_on_a_fresh_token() {
+        let env = odra_test::env();
+        let mut token = deploy_token(&env);
+        let holder = env.get_account(1);
+
+        // A predecessor's 1000 stCSPR backed by 1050 CSPR
+        token.seed_supply(holder, U256::from(1_000_000_000_000u64), U256::from(1_050_000_000_000u64));
+        assert_eq!(token.balance_of(holder), U256::from(1_000_000_000_000u64));
+        assert_eq!(token.get_total_cspr_backing(), U256::from(1_050_000_000_000u64));
+        assert!(token.get_exchange_rate() > U256::from(1_049_000_000u64));
+
+        assert_eq!(
+            token.try_seed_supply(holder, U256::one(), U256::one()),
+            Err(TokenError::SupplyAlreadyMinted.into())
+        );
+        env.set_caller(holder);
+        assert_eq!(
+            token.try_seed_supply(holder, U256::one(), U256::one()),
+            Err(AccessControlError::MissingRole.into())
+        );
+    }
+
+    #[test]
+    fn test_burn_from_spends_allowance() {
+        let env = odra_test::env();
+        let mut token = deploy_token(&env);
+        let vault = env.get_account(0);
+        let owner = env.get_account(1);
+        let spender = env.get_account(2);
+
+        let stcspr_amount = token.mint(owner, U256::from(100_000_000_000u64));
+        env.set_caller(owner);
+        token.approve(spender, stcspr_amount / U256::from(2u64));
+
+        env.set_caller(vault);
+        assert_eq!(
+            token.try_burn_from(owner, spender, stcspr_amount),
+            Err(TokenError::InsufficientAllowance.into())
+        );
+        token.burn_from(owner, spender, stcspr_amount / U256::from(4u64));
+        assert_eq!(token.allowance(owner, spender), stcspr_amount / U256::from(4u64));
+        assert_eq!(token.balance_of(owner), stcspr_amount - stcspr_amount / U256::from(4u64));
+
+        // Only the minter burns, allowance or not
+        env.set_caller(spender);
+        assert_eq!(
+            token.try_burn_from(owner, spender, U256::one()),
+            Err(AccessControlError::MissingRole.into())
+        );
+    }
+
+    #[test]
+    fn test_delegated_votes_are_checkpointed() {
+        let env = odra_test::env();
+        let mut token = deploy_token(&env);
+        let alice = env.get_account(1);
+        let bob = env.get_account(2);
+        let carol = env.get_account(3);
+
+        let alice_balance = token.mint(alice, U256::from(100_000_000_000u64));
+        let bob_balance = token.mint(bob, U256::from(50_000_000_000u64));
+        assert_eq!(token.get_votes(alice), U256::zero());
+
+        // Balances vote once delegated, to the holder itself or to someone else
+        env.set_caller(alice);
+        token.delegate(alice);
+        env.set_caller(bob);
+        token.delegate(alice);
+        assert_eq!(token.delegates(bob), Some(alice));
+        assert_eq!(token.get_votes(alice), alice_balance + bob_balance);
+        assert!(env.emitted_event(&token, DelegateChanged {
+            delegator: bob,
+            from_delegate: None,
+            to_delegate: alice,
+            timestamp: env.block_time(),
+        }));
+        assert!(env.emitted_event(&token, DelegateVotesChanged {
+            delegate: alice,
+            previous_votes: alice_balance,
+            new_votes: alice_balance + bob_balance,
+            timestamp: env.block_time(),
+        }));
+
+        // Transfers to an undelegated holder take the votes out of circulation
+        let snapshot = env.block_time();
+        env.advance_block_time(1000);
+        env.set_caller(alice);
+        token.transfer(carol, alice_balance / U256::from(2u64));
+        assert_eq!(token.get_votes(alice), alice_balance / U256::from(2u64) + bob_balance);
+
+        // Burns lower votes and the supply; the past stays as it was
+        env.advance_block_time(1000);
+        env.set_caller(env.get_account(0));
+        token.burn(bob, bob_balance);
+        assert_eq!(token.get_votes(alice), alice_balance / U256::from(2u64));
+        assert_eq!(token.get_past_votes(alice, snapshot), alice_balance + bob_balance);
+        assert_eq!(token.get_past_votes(alice, snapshot + 1000), alice_balance / U256::from(2u64) + bob_balance);
+        assert_eq!(token.get_past_total_supply(snapshot), alice_balance + bob_balance);
+        assert_eq!(token.get_past_votes(carol, snapshot + 1000), U256::zero());
+
+        // Votes at the current block time can still move
+        assert_eq!(
+            token.try_get_past_votes(alice, env.block_time()),
+            Err(VotesError::FutureLookup.into())
+        );
+    }
+
     #[test]
     fn test_transfer() {
         let env = odra_test::env();
-        use odra::host::NoArgs;
-        let mut token = StCSPRToken::deploy(&env, NoArgs);
+        let mut token = deploy_token(&env);
 
         let user1 = env.get_account(1);
         let user2 = env.get_account(2);
@@ -481,11 +938,35 @@ mod tests {
         assert_eq!(token.balance_of(user2), transfer_amount);
     }
 
+    #[test]
+    fn test_transfer_hook_is_opt_in() {
+        use crate::stakeflow_vault::StakeFlowVault;
+        use odra::host::NoArgs;
+
+        // A vault contract without the hook entrypoint mints, and transfers make no call into it
+        let env = odra_test::env();
+        let vault = StakeFlowVault::deploy(&env, NoArgs);
+        let mut token = StCSPRToken::deploy(&env, StCSPRTokenInitArgs { vault: vault.address() });
+        token.grant_role(Role::Minter, env.get_account(0));
+        token.mint(env.get_account(1), U256::from(100_000_000_000u64));
+        env.set_caller(env.get_account(1));
+        token.transfer(env.get_account(2), U256::from(50_000_000_000u64));
+        assert_eq!(token.get_transfer_hook(), None);
+
+        // Only the admin sets one
+        assert_eq!(
+            token.try_set_transfer_hook(Some(vault.address())),
+            Err(AccessControlError::MissingRole.into())
+        );
+        env.set_caller(env.get_account(0));
+        token.set_transfer_hook(Some(vault.address()));
+        assert_eq!(token.get_transfer_hook(), Some(vault.address()));
+    }
+
     #[test]
     fn test_approve_and_transfer_from() {
         let env = odra_test::env();
-        use odra::host::NoArgs;
-        let mut token = StCSPRToken::deploy(&env, NoArgs);
+        let mut token = deploy_token(&env);
 
         let owner = env.get_account(1);
         let spender = env.get_account(2);
@@ -509,11 +990,53 @@ mod tests {
         assert_eq!(token.allowance(owner, spender), amount - transfer_amount);
     }
 
+    #[test]
+    fn test_permit_approves_with_signed_message() {
+        let env = odra_test::env();
+        let mut token = deploy_token(&env);
+        let owner = env.get_account(1);
+        let spender = env.get_account(2);
+        let amount = U256::from(100_000_000_000u64);
+        let deadline = env.block_time() + 60_000;
+
+        // The owner signs off-chain; the spender submits and pays for the call
+        let message = token.get_permit_message(owner, spender, amount, deadline);
+        let signature = env.sign_message(&message, &owner);
+        env.set_caller(spender);
+        token.permit(owner, spender, amount, deadline, signature.clone(), env.public_key(&owner));
+        assert_eq!(token.allowance(owner, spender), amount);
+        assert_eq!(token.nonces(owner), 1);
+        assert!(env.emitted_event(&token, Approval { owner, spender, amount }));
+
+        // A used signature does not replay, and signatures are bound to the amount
+        assert_eq!(
+            token.try_permit(owner, spender, amount, deadline, signature, env.public_key(&owner)),
+            Err(PermitError::InvalidPermitSignature.into())
+        );
+        let message = token.get_permit_message(owner, spender, amount, deadline);
+        let signature = env.sign_message(&message, &owner);
+        assert_eq!(
+            token.try_permit(owner, spender, amount * 2, deadline, signature.clone(), env.public_key(&owner)),
+            Err(PermitError::InvalidPermitSignature.into())
+        );
+
+        // Only the owner's own key counts, and only until the deadline
+        let spender_signature = env.sign_message(&message, &spender);
+        assert_eq!(
+            token.try_permit(owner, spender, amount, deadline, spender_signature, env.public_key(&spender)),
+            Err(PermitError::PermitKeyMismatch.into())
+        );
+        env.advance_block_time(60_001);
+        assert_eq!(
+            token.try_permit(owner, spender, amount, deadline, signature, env.public_key(&owner)),
+            Err(PermitError::PermitExpired.into())
+        );
+    }
+
     #[test]
     fn test_exchange_rate_update() {
         let env = odra_test::env();
-        use odra::host::NoArgs;
-        let mut token = StCSPRToken::deploy(&env, NoArgs);
+        let mut token = deploy_token(&env);
 
         let user = env.get_account(1);
         let initial_cspr = U256::from(100_000_000_000u64); // 100 CSPR
@@ -528,16 +1051,16 @@ mod tests {
         let new_rate = token.get_exchange_rate();
         assert!(new_rate > U256::from(1_000_000_000u64));
 
-        // User's stCSPR is worth more CSPR now
+        // User's stCSPR is worth more CSPR now, less the sliver the virtual shares hold
         let cspr_value = token.stcspr_to_cspr(stcspr_minted);
-        assert_eq!(cspr_value, new_total_cspr);
+        assert!(cspr_value <= new_total_cspr);
+        assert!(new_total_cspr - cspr_value < U256::from(math::VIRTUAL_ASSETS));
     }
 
     #[test]
     fn test_conversion_functions() {
         let env = odra_test::env();
-        use odra::host::NoArgs;
-        let token = StCSPRToken::deploy(&env, NoArgs);
+        let token = deploy_token(&env);
 
         let cspr = U256::from(100_000_000_000u64);
         let stcspr = token.cspr_to_stcspr(cspr);
@@ -546,17 +1069,135 @@ mod tests {
         assert_eq!(cspr, cspr_back); // Round-trip conversion
     }
 
+    #[test]
+    fn test_first_depositor_inflation_attack_fails() {
+        let env = odra_test::env();
+        let mut token = deploy_token(&env);
+        let (attacker, victim) = (env.get_account(1), env.get_account(2));
+
+        // Attacker seeds the pool with a single unit, then inflates its backing
+        let donation = U256::from(1_000_000_000_000u64); // 1000 CSPR
+        token.mint(attacker, U256::one());
+        token.update_exchange_rate(U256::one() + donation);
+
+        // The victim's deposit is not rounded away
+        let deposit = U256::from(100_000_000_000u64); // 100 CSPR
+        let minted = token.mint(victim, deposit);
+        assert!(!minted.is_zero());
+        let victim_value = token.stcspr_to_cspr(token.balance_of(victim));
+        assert!(victim_value >= deposit * U256::from(99u64) / U256::from(100u64));
+
+        // The attacker cannot recover the donation
+        let attacker_value = token.stcspr_to_cspr(token.balance_of(attacker));
+        assert!(attacker_value < U256::from(1_000_000_000u64));
+    }
+
+    #[test]
+    fn test_mint_rounding_to_zero_reverts() {
+        let env = odra_test::env();
+        let mut token = deploy_token(&env);
+
+        token.mint(env.get_account(1), U256::one());
+        token.update_exchange_rate(U256::from(1_000_000_000_000u64));
+
+        assert_eq!(
+            token.try_mint(env.get_account(2), U256::one()),
+            Err(TokenError::ZeroSharesMinted.into())
+        );
+    }
+
+    #[test]
+    fn test_rate_change_bound_rejects_outsized_updates() {
+        let env = odra_test::env();
+        let mut token = deploy_token(&env);
+
+        let deposit = U256::from(1_000_000_000_000u64); // 1000 CSPR
+        token.mint(env.get_account(1), deposit);
+        token.set_max_rate_change(100); // 1%
+
+        // Within the bound in either direction
+        token.update_exchange_rate(U256::from(1_005_000_000_000u64));
+        token.update_exchange_rate(U256::from(1_000_000_000_000u64));
+
+        // A 10% jump or drop in one update is rejected and leaves the rate alone
+        let rate = token.get_exchange_rate();
+        assert_eq!(
+            token.try_update_exchange_rate(U256::from(1_100_000_000_000u64)),
+            Err(TokenError::RateChangeTooLarge.into())
+        );
+        assert_eq!(
+            token.try_update_exchange_rate(U256::from(900_000_000_000u64)),
+            Err(TokenError::RateChangeTooLarge.into())
+        );
+        assert_eq!(token.get_exchange_rate(), rate);
+
+        // Only the admin sets the bound
+        env.set_caller(env.get_account(1));
+        assert_eq!(token.try_set_max_rate_change(0), Err(AccessControlError::MissingRole.into()));
+    }
+
+    #[test]
+    fn test_mint_shares_dilutes_past_the_rate_bound() {
+        let env = odra_test::env();
+        let mut token = deploy_token(&env);
+
+        let deposit = U256::from(1_000_000_000_000u64); // 1000 CSPR
+        token.mint(env.get_account(1), deposit);
+        token.set_max_rate_change(100); // 1%
+
+        // Shares minted without backing dilute holders by 10% in one go
+        let (rate, supply) = (token.get_exchange_rate(), token.total_supply());
+        token.mint_shares(env.get_account(2), supply / U256::from(9u64));
+        assert_eq!(token.get_total_cspr_backing(), deposit);
+        assert!(token.get_exchange_rate() < rate * U256::from(91u64) / U256::from(100u64));
+
+        env.set_caller(env.get_account(1));
+        assert_eq!(
+            token.try_mint_shares(env.get_account(1), U256::one()),
+            Err(AccessControlError::MissingRole.into())
+        );
+    }
+
     #[test]
     fn test_pause_and_unpause() {
         let env = odra_test::env();
-        use odra::host::NoArgs;
-        let mut token = StCSPRToken::deploy(&env, NoArgs);
+        let mut token = deploy_token(&env);
 
-        // Deployer is vault
+        // Deployer is the guardian
         token.pause();
         assert!(token.is_paused());
 
         token.unpause();
         assert!(!token.is_paused());
     }
+
+    #[test]
+    fn test_minter_role() {
+        let env = odra_test::env();
+        let mut token = deploy_token(&env);
+
+        // Deployer administers roles
+        let minter = env.get_account(2);
+        let user = env.get_account(1);
+        token.grant_role(Role::Minter, minter);
+        assert!(token.has_role(Role::Minter, minter));
+
+        env.set_caller(minter);
+        let amount = U256::from(100_000_000_000u64);
+        token.mint(user, amount);
+        assert_eq!(token.balance_of(user), amount);
+    }
+
+    #[test]
+    fn test_mint_requires_minter_role() {
+        let env = odra_test::env();
+        let mut token = deploy_token(&env);
+
+        env.set_caller(env.get_account(1));
+        assert_eq!(
+            token.try_mint(env.get_account(1), U256::from(100_000_000_000u64)),
+            Err(AccessControlError::MissingRole.into())
+        );
+        assert_eq!(token.total_supply(), U256::zero());
+    }
 }
@@ -0,0 +1,178 @@
+use odra::prelude::*;
+use odra::casper_types::bytesrepr::Bytes;
+
+/// Default minimum delay: 2 days in milliseconds (block time units)
+pub const DEFAULT_MIN_DELAY: u64 = 2 * 24 * 60 * 60 * 1000;
+
+/// StakeFlow Timelock
+///
+/// Queue for sensitive admin calls:
+/// - A call is scheduled as serialized bytes and becomes executable after `min_delay`
+/// - Pending calls can be cancelled before they are executed
+/// - Each call executes at most once
+///
+/// Used as a `SubModule`; the parent decides who may schedule, execute and cancel,
+/// and decodes and applies the call bytes itself.
+#[odra::module]
+pub struct Timelock {
+    /// Minimum time between scheduling and execution
+    min_delay: Var<u64>,
+    /// Scheduled operations: operation_id -> TimelockOperation
+    operations: Mapping<u64, TimelockOperation>,
+    /// Next operation ID
+    next_operation_id: Var<u64>,
+}
+
+/// Admin call waiting in the timelock
+#[odra::odra_type]
+pub struct TimelockOperation {
+    /// Serialized call, decoded by the parent contract
+    pub call: Bytes,
+    pub scheduled_at: u64,
+    /// Earliest block time at which the call may execute
+    pub eta: u64,
+    pub executed: bool,
+    pub cancelled: bool,
+}
+
+#[odra::module]
+impl Timelock {
+    /// Set the minimum delay
+    pub fn init_delay(&mut self, min_delay: u64) {
+        self.min_delay.set(min_delay);
+    }
+
+    /// Queue a call; returns operation ID
+    pub fn schedule(&mut self, call: Bytes) -> u64 {
+        let operation_id = self.next_operation_id.get_or_default();
+        let now = self.env().get_block_time();
+        let eta = now + self.min_delay.get_or_default();
+
+        self.operations.set(&operation_id, TimelockOperation {
+            call,
+            scheduled_at: now,
+            eta,
+            executed: false,
+            cancelled: false,
+        });
+        self.next_operation_id.set(operation_id + 1);
+
+        self.env().emit_event(CallScheduled {
+            operation_id,
+            eta,
+            timestamp: now,
+        });
+
+        operation_id
+    }
+
+    /// Mark a ready call as executed and return its bytes
+    pub fn execute(&mut self, operation_id: u64) -> Bytes {
+        let mut operation = self.operations.get(&operation_id)
+            .unwrap_or_revert_with(&self.env(), TimelockError::OperationNotFound);
+
+        if operation.executed {
+            self.env().revert(TimelockError::AlreadyExecuted);
+        }
+        if operation.cancelled {
+            self.env().revert(TimelockError::OperationCancelled);
+        }
+        if self.env().get_block_time() < operation.eta {
+            self.env().revert(TimelockError::TimelockNotExpired);
+        }
+
+        operation.executed = true;
+        let call = operation.call.clone();
+        self.operations.set(&operation_id, operation);
+
+        self.env().emit_event(CallExecuted {
+            operation_id,
+            timestamp: self.env().get_block_time(),
+        });
+
+        call
+    }
+
+    /// Cancel a pending call
+    pub fn cancel(&mut self, operation_id: u64) {
+        let mut operation = self.operations.get(&operation_id)
+            .unwrap_or_revert_with(&self.env(), TimelockError::OperationNotFound);
+
+        if operation.executed {
+            self.env().revert(TimelockError::AlreadyExecuted);
+        }
+        if operation.cancelled {
+            self.env().revert(TimelockError::OperationCancelled);
+        }
+
+        operation.cancelled = true;
+        self.operations.set(&operation_id, operation);
+
+        self.env().emit_event(CallCancelled {
+            operation_id,
+            timestamp: self.env().get_block_time(),
+        });
+    }
+
+    /// Change the minimum delay; only reachable through an executed operation
+    pub fn set_min_delay(&mut self, min_delay: u64) {
+        let old_delay = self.min_delay.get_or_default();
+        self.min_delay.set(min_delay);
+
+        self.env().emit_event(MinDelayChanged {
+            old_delay,
+            new_delay: min_delay,
+            timestamp: self.env().get_block_time(),
+        });
+    }
+
+    // ===== VIEW FUNCTIONS =====
+
+    /// Get a scheduled operation
+    pub fn get_operation(&self, operation_id: u64) -> Option<TimelockOperation> {
+        self.operations.get(&operation_id)
+    }
+
+    /// Get minimum delay
+    pub fn min_delay(&self) -> u64 {
+        self.min_delay.get_or_default()
+    }
+}
+
+// ===== ERRORS =====
+
+#[odra::odra_error]
+pub enum TimelockError {
+    OperationNotFound = 200,
+    AlreadyExecuted = 201,
+    OperationCancelled = 202,
+    TimelockNotExpired = 203,
+}
+
+// ===== EVENTS =====
+
+#[odra::event]
+pub struct CallScheduled {
+    pub operation_id: u64,
+    pub eta: u64,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct CallExecuted {
+    pub operation_id: u64,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct CallCancelled {
+    pub operation_id: u64,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct MinDelayChanged {
+    pub old_delay: u64,
+    pub new_delay: u64,
+    pub timestamp: u64,
+}
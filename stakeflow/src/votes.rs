@@ -0,0 +1,186 @@
+use odra::prelude::*;
+use odra::casper_types::U256;
+
+/// StakeFlow Vote Checkpoints
+///
+/// Voting power for a token, checkpointed by block time so governance can read it
+/// as it stood when a proposal was created:
+/// - Holders delegate their whole balance to an account (themselves to vote directly);
+///   undelegated balances carry no votes
+/// - Every mint, burn and transfer moves votes between the holders' delegates
+/// - Total supply is checkpointed alongside, for quorums
+///
+/// Used as a `SubModule`; the parent token reports balance movements.
+#[odra::module]
+pub struct Votes {
+    /// Holder -> account their votes are delegated to
+    delegates: Mapping<Address, Address>,
+    /// (delegate, index) -> votes from that block time on; `None` tracks total supply
+    checkpoints: Mapping<(Option<Address>, u64), VoteCheckpoint>,
+    /// Delegate (or `None` for total supply) -> number of checkpoints
+    checkpoint_count: Mapping<Option<Address>, u64>,
+}
+
+/// Votes held from a block time until the next checkpoint
+#[odra::odra_type]
+pub struct VoteCheckpoint {
+    pub timestamp: u64,
+    pub votes: U256,
+}
+
+#[odra::module]
+impl Votes {
+    /// Delegate `delegator`'s votes, backed by its `balance`, to `delegatee`
+    pub fn delegate(&mut self, delegator: Address, delegatee: Address, balance: U256) {
+        let previous = self.delegates(delegator);
+        self.delegates.set(&delegator, delegatee);
+
+        self.env().emit_event(DelegateChanged {
+            delegator,
+            from_delegate: previous,
+            to_delegate: delegatee,
+            timestamp: self.env().get_block_time(),
+        });
+
+        self.move_votes(previous, Some(delegatee), balance);
+    }
+
+    /// Record `amount` moving from `from` to `to`; `None` is a mint or burn
+    pub fn transfer_votes(&mut self, from: Option<Address>, to: Option<Address>, amount: U256) {
+        if from.is_none() {
+            let supply = self.latest(None) + amount;
+            self.write(None, supply);
+        }
+        if to.is_none() {
+            let supply = self.latest(None) - amount;
+            self.write(None, supply);
+        }
+
+        let from_delegate = from.and_then(|holder| self.delegates(holder));
+        let to_delegate = to.and_then(|holder| self.delegates(holder));
+        self.move_votes(from_delegate, to_delegate, amount);
+    }
+
+    // ===== VIEW FUNCTIONS =====
+
+    /// Get the account a holder delegates to, if any
+    pub fn delegates(&self, holder: Address) -> Option<Address> {
+        self.delegates.get(&holder)
+    }
+
+    /// Get an account's current votes
+    pub fn votes(&self, account: Address) -> U256 {
+        self.latest(Some(account))
+    }
+
+    /// Get an account's votes at the end of block time `timestamp`, which must have passed
+    pub fn past_votes(&self, account: Address, timestamp: u64) -> U256 {
+        self.assert_past(timestamp);
+        self.lookup(Some(account), timestamp)
+    }
+
+    /// Get the checkpointed total supply at the end of block time `timestamp`, which must have passed
+    pub fn past_total_supply(&self, timestamp: u64) -> U256 {
+        self.assert_past(timestamp);
+        self.lookup(None, timestamp)
+    }
+
+    // ===== INTERNAL =====
+
+    fn move_votes(&mut self, from: Option<Address>, to: Option<Address>, amount: U256) {
+        if from == to || amount.is_zero() {
+            return;
+        }
+        if let Some(delegate) = from {
+            let previous_votes = self.latest(Some(delegate));
+            self.write_votes(delegate, previous_votes, previous_votes - amount);
+        }
+        if let Some(delegate) = to {
+            let previous_votes = self.latest(Some(delegate));
+            self.write_votes(delegate, previous_votes, previous_votes + amount);
+        }
+    }
+
+    fn write_votes(&mut self, delegate: Address, previous_votes: U256, new_votes: U256) {
+        self.write(Some(delegate), new_votes);
+
+        self.env().emit_event(DelegateVotesChanged {
+            delegate,
+            previous_votes,
+            new_votes,
+            timestamp: self.env().get_block_time(),
+        });
+    }
+
+    /// Record `votes` for the current block time, overwriting a checkpoint from the same time
+    fn write(&mut self, series: Option<Address>, votes: U256) {
+        let timestamp = self.env().get_block_time();
+        let count = self.checkpoint_count.get(&series).unwrap_or(0);
+        if count > 0 && self.checkpoint(series, count - 1).timestamp == timestamp {
+            self.checkpoints.set(&(series, count - 1), VoteCheckpoint { timestamp, votes });
+            return;
+        }
+        self.checkpoints.set(&(series, count), VoteCheckpoint { timestamp, votes });
+        self.checkpoint_count.set(&series, count + 1);
+    }
+
+    fn latest(&self, series: Option<Address>) -> U256 {
+        match self.checkpoint_count.get(&series).unwrap_or(0) {
+            0 => U256::zero(),
+            count => self.checkpoint(series, count - 1).votes,
+        }
+    }
+
+    /// Votes of the last checkpoint at or before `timestamp`
+    fn lookup(&self, series: Option<Address>, timestamp: u64) -> U256 {
+        let count = self.checkpoint_count.get(&series).unwrap_or(0);
+        if count == 0 || self.checkpoint(series, 0).timestamp > timestamp {
+            return U256::zero();
+        }
+
+        let (mut low, mut high) = (0, count - 1);
+        while low < high {
+            let mid = (low + high).div_ceil(2);
+            if self.checkpoint(series, mid).timestamp <= timestamp { low = mid } else { high = mid - 1 }
+        }
+        self.checkpoint(series, low).votes
+    }
+
+    fn checkpoint(&self, series: Option<Address>, index: u64) -> VoteCheckpoint {
+        self.checkpoints.get(&(series, index))
+            .unwrap_or_revert_with(&self.env(), VotesError::CheckpointMissing)
+    }
+
+    /// Votes at the current block time can still change within it
+    fn assert_past(&self, timestamp: u64) {
+        if timestamp >= self.env().get_block_time() {
+            self.env().revert(VotesError::FutureLookup);
+        }
+    }
+}
+
+// ===== ERRORS =====
+
+#[odra::odra_error]
+pub enum VotesError {
+    FutureLookup = 1400,
+    CheckpointMissing = 1401,
+}
+
+// ===== EVENTS =====
+
+#[odra::event]
+pub struct DelegateChanged {
+    pub delegator: Address,
+    pub from_delegate: Option<Address>,
+    pub to_delegate: Address,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct DelegateVotesChanged {
+    pub delegate: Address,
+    pub previous_votes: U256,
+    pub new_votes: U256,
+    pub timestamp: u64,
+}
@@ -0,0 +1,545 @@
+use odra::prelude::*;
+use odra::casper_types::{U512, U256};
+use crate::math::{self, Rounding};
+
+/// Fixed-point scale of the queue unit value (1e18 = 1 CSPR mote per unit)
+pub const UNIT_VALUE_SCALE: u128 = 1_000_000_000_000_000_000;
+
+/// StakeFlow Withdrawal Queue
+///
+/// First-in, first-out queue of withdrawal requests:
+/// - Each request occupies a CSPR range `[queue_offset, queue_offset + cspr_amount)`
+/// - Incoming liquidity advances a single fulfilment cursor through those ranges,
+///   so requests are finalized strictly in order
+/// - A request straddling the cursor is partly filled; the filled part can be claimed
+/// - Ranges are measured in queue units, 1:1 with CSPR until a loss: slashing during
+///   unbonding lowers the value of every unfulfilled unit, so pending requests share it
+/// - Every request is a transferable ticket with an owner, single-ticket approvals and
+///   operators (CEP-78-like), so unbonding positions can be sold or pledged
+///
+/// Used as a `SubModule` by `StakeFlowVaultV3`. Ticket ownership is enforced here;
+/// the parent performs all other access control.
+#[odra::module]
+pub struct WithdrawalQueue {
+    /// Withdrawal requests: withdrawal_id -> WithdrawalRequest
+    requests: Mapping<u64, WithdrawalRequest>,
+    /// Ticket owner: withdrawal_id -> owner
+    ticket_owner: Mapping<u64, Address>,
+    /// Tickets held by an owner: (owner, index) -> withdrawal_id
+    user_withdrawal_ids: Mapping<(Address, u64), u64>,
+    /// Tickets held by an owner: owner -> count
+    user_withdrawal_count: Mapping<Address, u64>,
+    /// Position of a ticket in its owner's list: withdrawal_id -> index
+    ticket_index: Mapping<u64, u64>,
+    /// Approved spender of a single ticket: withdrawal_id -> spender
+    ticket_approvals: Mapping<u64, Option<Address>>,
+    /// Operators allowed to move all of an owner's tickets: (owner, operator) -> approved
+    operators: Mapping<(Address, Address), bool>,
+    /// Next withdrawal ID
+    next_withdrawal_id: Var<u64>,
+    /// Cumulative queue units ever requested (tail of the queue)
+    queue_tail: Var<U512>,
+    /// Cumulative queue units ever fulfilled (fulfilment cursor)
+    fulfilment_cursor: Var<U512>,
+    /// Cumulative CSPR ever claimed
+    total_claimed: Var<U512>,
+    /// CSPR per unfulfilled queue unit, scaled by `UNIT_VALUE_SCALE` (unset = 1:1)
+    unit_value: Var<U512>,
+    /// Points where the unit value changed: index -> checkpoint
+    loss_checkpoints: Mapping<u64, LossCheckpoint>,
+    /// Number of loss checkpoints
+    loss_checkpoint_count: Var<u64>,
+    /// Queue units of a request: withdrawal_id -> units (unset = `cspr_amount`, queued 1:1)
+    request_units: Mapping<u64, U512>,
+}
+
+/// Withdrawal request with time-lock
+#[odra::odra_type]
+pub struct WithdrawalRequest {
+    /// Original requester; the current holder is the ticket owner
+    pub user: Address,
+    pub stcspr_amount: U256,
+    pub cspr_amount: U512,
+    pub request_time: u64,
+    /// Era in which the matching undelegation has fully unbonded
+    pub unlock_era: u64,
+    /// Start of `unlock_era`; fixed when the request is created
+    pub unlock_time: u64,
+    /// Cumulative queue units ahead of this request when it was created
+    pub queue_offset: U512,
+    /// CSPR already paid out for this request
+    pub claimed_amount: U512,
+    /// True once the request is fully fulfilled and paid out
+    /// `cspr_amount` is what was owed at request time; losses can lower the payout
+    pub is_claimed: bool,
+}
+
+/// Unit value in force from a queue position onwards
+#[odra::odra_type]
+pub struct LossCheckpoint {
+    /// Fulfilment cursor (queue units) when the value changed
+    pub units: U512,
+    /// CSPR fulfilled up to `units`
+    pub cspr: U512,
+    /// CSPR per unit from `units` on, scaled by `UNIT_VALUE_SCALE`
+    pub unit_value: U512,
+}
+
+/// Where a request stands in the queue
+#[odra::odra_type]
+pub struct QueuePosition {
+    /// CSPR queued ahead of the request and not yet fulfilled
+    pub cspr_ahead: U512,
+    /// CSPR of the request already fulfilled
+    pub fulfilled_amount: U512,
+    /// CSPR of the request still waiting for liquidity
+    pub remaining_amount: U512,
+    /// Block time at which the request should be fully claimable,
+    /// `None` if no scheduled liquidity covers it yet
+    pub estimated_fulfilment_time: Option<u64>,
+}
+
+#[odra::module]
+impl WithdrawalQueue {
+    /// Append a request to the tail of the queue
+    /// Returns withdrawal request ID
+    pub fn enqueue(
+        &mut self,
+        user: Address,
+        stcspr_amount: U256,
+        cspr_amount: U512,
+        unlock_era: u64,
+        unlock_time: u64,
+    ) -> u64 {
+        let withdrawal_id = self.next_withdrawal_id.get_or_default();
+        let queue_offset = self.queue_tail.get_or_default();
+        let units = math::mul_div_u512(cspr_amount, unit_scale(), self.unit_value(), Rounding::Down)
+            .unwrap_or_revert(&self.env());
+
+        let withdrawal = WithdrawalRequest {
+            user,
+            stcspr_amount,
+            cspr_amount,
+            request_time: self.env().get_block_time(),
+            unlock_era,
+            unlock_time,
+            queue_offset,
+            claimed_amount: U512::zero(),
+            is_claimed: false,
+        };
+
+        self.requests.set(&withdrawal_id, withdrawal);
+        self.request_units.set(&withdrawal_id, units);
+        self.queue_tail.set(queue_offset + units);
+
+        // The requester holds the ticket
+        self.ticket_owner.set(&withdrawal_id, user);
+        self.add_ticket(user, withdrawal_id);
+
+        self.next_withdrawal_id.set(withdrawal_id + 1);
+
+        withdrawal_id
+    }
+
+    /// Allocate liquidity to the queue in FIFO order
+    /// Returns the CSPR newly reserved for queued requests
+    pub fn fulfil(&mut self, liquidity: U512) -> U512 {
+        let queued = self.total_queued();
+        if queued.is_zero() || liquidity.is_zero() {
+            return U512::zero();
+        }
+
+        let previous = self.fulfilment_cursor.get_or_default();
+        let cursor = if liquidity >= queued {
+            self.queue_tail.get_or_default()
+        } else {
+            let units = math::mul_div_u512(liquidity, unit_scale(), self.unit_value(), Rounding::Down)
+                .unwrap_or_revert(&self.env());
+            previous + units
+        };
+        let amount = self.cspr_at(cursor) - self.cspr_at(previous);
+        if amount.is_zero() {
+            return U512::zero();
+        }
+        self.fulfilment_cursor.set(cursor);
+
+        self.env().emit_event(WithdrawalsFulfilled {
+            amount,
+            fulfilment_cursor: cursor,
+            total_queued: queued - amount,
+            timestamp: self.env().get_block_time(),
+        });
+
+        amount
+    }
+
+    /// Pay out the fulfilled, unclaimed part of a request
+    /// A fully covered request closes even if losses left nothing to pay
+    /// Returns the CSPR to transfer
+    pub fn claim(&mut self, withdrawal_id: u64) -> U512 {
+        let mut withdrawal = self.requests.get(&withdrawal_id)
+            .unwrap_or_revert_with(&self.env(), WithdrawalQueueError::WithdrawalNotFound);
+        if withdrawal.is_claimed {
+            self.env().revert(WithdrawalQueueError::AlreadyClaimed);
+        }
+
+        let amount = self.claimable(withdrawal_id);
+        let covered =
+            self.fulfilment_cursor.get_or_default() >= withdrawal.queue_offset + self.units_of(withdrawal_id, &withdrawal);
+        if amount.is_zero() && !covered {
+            self.env().revert(WithdrawalQueueError::NothingToClaim);
+        }
+
+        withdrawal.claimed_amount += amount;
+        withdrawal.is_claimed = covered;
+        self.requests.set(&withdrawal_id, withdrawal);
+        self.total_claimed.set(self.total_claimed.get_or_default() + amount);
+
+        amount
+    }
+
+    /// Spread a loss over every unfulfilled request, pro rata to what each is owed
+    /// Fulfilled and reserved CSPR is untouched. Returns the CSPR the queue absorbed
+    pub fn absorb_loss(&mut self, loss: U512) -> U512 {
+        let queued = self.total_queued();
+        if queued.is_zero() || loss.is_zero() {
+            return U512::zero();
+        }
+        let loss = if loss < queued { loss } else { queued };
+
+        let cursor = self.fulfilment_cursor.get_or_default();
+        let fulfilled = self.cspr_at(cursor);
+        let mut unit_value = math::mul_div_u512(self.unit_value(), queued - loss, queued, Rounding::Down)
+            .unwrap_or_revert(&self.env());
+        self.push_checkpoint(cursor, fulfilled, unit_value);
+
+        if unit_value.is_zero() {
+            // Nothing is left to pay: close the wiped-out range and price new requests 1:1 again
+            let tail = self.queue_tail.get_or_default();
+            self.fulfilment_cursor.set(tail);
+            unit_value = unit_scale();
+            self.push_checkpoint(tail, fulfilled, unit_value);
+        }
+        self.unit_value.set(unit_value);
+
+        let total_queued = self.total_queued();
+        let absorbed = queued - total_queued;
+
+        self.env().emit_event(QueueLossAbsorbed {
+            loss: absorbed,
+            total_queued,
+            unit_value,
+            timestamp: self.env().get_block_time(),
+        });
+
+        absorbed
+    }
+
+    // ===== TICKETS =====
+
+    /// Move a ticket from `from` to `to`
+    /// `caller` must be the owner, the ticket's approved spender or an operator of the owner
+    pub fn transfer(&mut self, caller: Address, from: Address, to: Address, withdrawal_id: u64) {
+        let owner = self.owner_of(withdrawal_id)
+            .unwrap_or_revert_with(&self.env(), WithdrawalQueueError::WithdrawalNotFound);
+        if owner != from {
+            self.env().revert(WithdrawalQueueError::NotTicketOwner);
+        }
+        if !self.is_approved_or_owner(caller, withdrawal_id) {
+            self.env().revert(WithdrawalQueueError::NotApprovedForTicket);
+        }
+        let withdrawal = self.requests.get(&withdrawal_id)
+            .unwrap_or_revert_with(&self.env(), WithdrawalQueueError::WithdrawalNotFound);
+        if withdrawal.is_claimed {
+            self.env().revert(WithdrawalQueueError::AlreadyClaimed);
+        }
+
+        // Approvals do not travel with the ticket
+        self.ticket_approvals.set(&withdrawal_id, None);
+        self.remove_ticket(from, withdrawal_id);
+        self.add_ticket(to, withdrawal_id);
+        self.ticket_owner.set(&withdrawal_id, to);
+
+        self.env().emit_event(WithdrawalTicketTransferred {
+            withdrawal_id,
+            from,
+            to,
+            timestamp: self.env().get_block_time(),
+        });
+    }
+
+    /// Approve `spender` to transfer a single ticket, or clear the approval with `None`
+    /// `caller` must be the owner or an operator of the owner
+    pub fn approve(&mut self, caller: Address, spender: Option<Address>, withdrawal_id: u64) {
+        let owner = self.owner_of(withdrawal_id)
+            .unwrap_or_revert_with(&self.env(), WithdrawalQueueError::WithdrawalNotFound);
+        if caller != owner && !self.is_operator(owner, caller) {
+            self.env().revert(WithdrawalQueueError::NotTicketOwner);
+        }
+        if spender == Some(owner) {
+            self.env().revert(WithdrawalQueueError::CannotApproveTicketOwner);
+        }
+
+        self.ticket_approvals.set(&withdrawal_id, spender);
+
+        self.env().emit_event(WithdrawalTicketApproval {
+            withdrawal_id,
+            owner,
+            spender,
+            timestamp: self.env().get_block_time(),
+        });
+    }
+
+    /// Allow or revoke `operator` moving all of `owner`'s tickets
+    pub fn set_operator(&mut self, owner: Address, operator: Address, approved: bool) {
+        if owner == operator {
+            self.env().revert(WithdrawalQueueError::CannotSetSelfAsOperator);
+        }
+        self.operators.set(&(owner, operator), approved);
+
+        self.env().emit_event(WithdrawalOperatorSet {
+            owner,
+            operator,
+            approved,
+            timestamp: self.env().get_block_time(),
+        });
+    }
+
+    // ===== VIEW FUNCTIONS =====
+
+    /// Get withdrawal request details
+    pub fn get(&self, withdrawal_id: u64) -> Option<WithdrawalRequest> {
+        self.requests.get(&withdrawal_id)
+    }
+
+    /// Get current owner of a ticket
+    pub fn owner_of(&self, withdrawal_id: u64) -> Option<Address> {
+        self.ticket_owner.get(&withdrawal_id)
+    }
+
+    /// Get approved spender of a ticket
+    pub fn approved_for(&self, withdrawal_id: u64) -> Option<Address> {
+        self.ticket_approvals.get(&withdrawal_id).flatten()
+    }
+
+    /// Check if `operator` may move all of `owner`'s tickets
+    pub fn is_operator(&self, owner: Address, operator: Address) -> bool {
+        self.operators.get(&(owner, operator)).unwrap_or(false)
+    }
+
+    /// Check if `spender` may transfer a ticket
+    pub fn is_approved_or_owner(&self, spender: Address, withdrawal_id: u64) -> bool {
+        match self.owner_of(withdrawal_id) {
+            Some(owner) => {
+                spender == owner
+                    || self.approved_for(withdrawal_id) == Some(spender)
+                    || self.is_operator(owner, spender)
+            }
+            None => false,
+        }
+    }
+
+    /// Get IDs of the tickets a user currently holds
+    pub fn user_withdrawals(&self, user: Address) -> Vec<u64> {
+        let count = self.user_withdrawal_count.get(&user).unwrap_or(0);
+        let mut ids = Vec::new();
+        for i in 0..count {
+            if let Some(id) = self.user_withdrawal_ids.get(&(user, i)) {
+                ids.push(id);
+            }
+        }
+        ids
+    }
+
+    /// Get CSPR of a request covered by the fulfilment cursor
+    pub fn fulfilled_amount(&self, withdrawal_id: u64) -> U512 {
+        let withdrawal = match self.requests.get(&withdrawal_id) {
+            Some(w) => w,
+            None => return U512::zero(),
+        };
+        let cursor = self.fulfilment_cursor.get_or_default();
+        if cursor <= withdrawal.queue_offset {
+            return U512::zero();
+        }
+        let end = withdrawal.queue_offset + self.units_of(withdrawal_id, &withdrawal);
+        let covered = if cursor < end { cursor } else { end };
+        self.cspr_at(covered) - self.cspr_at(withdrawal.queue_offset)
+    }
+
+    /// Get the CSPR a request pays out in total at the current unit value
+    /// Equal to `cspr_amount` unless a loss hit the request while it was queued
+    pub fn owed_amount(&self, withdrawal_id: u64) -> U512 {
+        match self.requests.get(&withdrawal_id) {
+            Some(w) => {
+                let end = w.queue_offset + self.units_of(withdrawal_id, &w);
+                self.cspr_at(end) - self.cspr_at(w.queue_offset)
+            }
+            None => U512::zero(),
+        }
+    }
+
+    /// Get CSPR of a request that is fulfilled but not yet paid out
+    pub fn claimable(&self, withdrawal_id: u64) -> U512 {
+        match self.requests.get(&withdrawal_id) {
+            Some(w) => self.fulfilled_amount(withdrawal_id) - w.claimed_amount,
+            None => U512::zero(),
+        }
+    }
+
+    /// Get unfulfilled CSPR queued ahead of a request
+    pub fn cspr_ahead(&self, withdrawal_id: u64) -> U512 {
+        let withdrawal = match self.requests.get(&withdrawal_id) {
+            Some(w) => w,
+            None => return U512::zero(),
+        };
+        let cursor = self.fulfilment_cursor.get_or_default();
+        if cursor >= withdrawal.queue_offset {
+            U512::zero()
+        } else {
+            self.cspr_at(withdrawal.queue_offset) - self.cspr_at(cursor)
+        }
+    }
+
+    /// Get CSPR requested but not yet fulfilled
+    pub fn total_queued(&self) -> U512 {
+        self.cspr_at(self.queue_tail.get_or_default()) - self.cspr_at(self.fulfilment_cursor.get_or_default())
+    }
+
+    /// Get CSPR fulfilled and reserved for claims
+    pub fn total_reserved(&self) -> U512 {
+        self.cspr_at(self.fulfilment_cursor.get_or_default()) - self.total_claimed.get_or_default()
+    }
+
+    /// Get the fulfilment cursor (cumulative queue units fulfilled)
+    pub fn fulfilment_cursor(&self) -> U512 {
+        self.fulfilment_cursor.get_or_default()
+    }
+
+    /// Get CSPR per unfulfilled queue unit, scaled by `UNIT_VALUE_SCALE`
+    pub fn unit_value(&self) -> U512 {
+        self.unit_value.get().unwrap_or_else(unit_scale)
+    }
+
+    // ===== INTERNAL =====
+
+    /// Cumulative CSPR the queue pays for its first `units` units
+    /// Piecewise linear: each loss checkpoint starts a segment at its unit value
+    fn cspr_at(&self, units: U512) -> U512 {
+        // Before the first loss units and CSPR are 1:1
+        let count = self.loss_checkpoint_count.get_or_default();
+        if count == 0 || units < self.checkpoint(0).units {
+            return units;
+        }
+
+        // Last checkpoint at or below `units`
+        let (mut low, mut high) = (0, count - 1);
+        while low < high {
+            let mid = (low + high).div_ceil(2);
+            if self.checkpoint(mid).units <= units { low = mid } else { high = mid - 1 }
+        }
+        let checkpoint = self.checkpoint(low);
+        let segment = math::mul_div_u512(units - checkpoint.units, checkpoint.unit_value, unit_scale(), Rounding::Down)
+            .unwrap_or_revert(&self.env());
+        checkpoint.cspr + segment
+    }
+
+    fn checkpoint(&self, index: u64) -> LossCheckpoint {
+        self.loss_checkpoints.get(&index)
+            .unwrap_or_revert_with(&self.env(), WithdrawalQueueError::CheckpointMissing)
+    }
+
+    fn push_checkpoint(&mut self, units: U512, cspr: U512, unit_value: U512) {
+        let count = self.loss_checkpoint_count.get_or_default();
+        self.loss_checkpoints.set(&count, LossCheckpoint { units, cspr, unit_value });
+        self.loss_checkpoint_count.set(count + 1);
+    }
+
+    /// Queue units a request occupies
+    fn units_of(&self, withdrawal_id: u64, withdrawal: &WithdrawalRequest) -> U512 {
+        self.request_units.get(&withdrawal_id).unwrap_or(withdrawal.cspr_amount)
+    }
+
+    /// Append a ticket to its owner's list
+    fn add_ticket(&mut self, owner: Address, withdrawal_id: u64) {
+        let count = self.user_withdrawal_count.get(&owner).unwrap_or(0);
+        self.user_withdrawal_ids.set(&(owner, count), withdrawal_id);
+        self.ticket_index.set(&withdrawal_id, count);
+        self.user_withdrawal_count.set(&owner, count + 1);
+    }
+
+    /// Remove a ticket from its owner's list, moving the last entry into its slot
+    fn remove_ticket(&mut self, owner: Address, withdrawal_id: u64) {
+        let count = self.user_withdrawal_count.get(&owner).unwrap_or(0);
+        let index = self.ticket_index.get(&withdrawal_id).unwrap_or(0);
+        let last = count - 1;
+        if index != last {
+            let last_id = self.user_withdrawal_ids.get(&(owner, last))
+                .unwrap_or_revert_with(&self.env(), WithdrawalQueueError::TicketIndexCorrupted);
+            self.user_withdrawal_ids.set(&(owner, index), last_id);
+            self.ticket_index.set(&last_id, index);
+        }
+        self.user_withdrawal_count.set(&owner, last);
+    }
+}
+
+/// One queue unit per mote, the value before any loss
+fn unit_scale() -> U512 {
+    U512::from(UNIT_VALUE_SCALE)
+}
+
+// ===== ERRORS =====
+
+#[odra::odra_error]
+pub enum WithdrawalQueueError {
+    WithdrawalNotFound = 300,
+    AlreadyClaimed = 301,
+    NothingToClaim = 302,
+    NotTicketOwner = 303,
+    NotApprovedForTicket = 304,
+    CannotApproveTicketOwner = 305,
+    CannotSetSelfAsOperator = 306,
+    TicketIndexCorrupted = 307,
+    CheckpointMissing = 308,
+}
+
+// ===== EVENTS =====
+
+#[odra::event]
+pub struct WithdrawalsFulfilled {
+    pub amount: U512,
+    pub fulfilment_cursor: U512,
+    pub total_queued: U512,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct QueueLossAbsorbed {
+    pub loss: U512,
+    pub total_queued: U512,
+    pub unit_value: U512,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct WithdrawalTicketTransferred {
+    pub withdrawal_id: u64,
+    pub from: Address,
+    pub to: Address,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct WithdrawalTicketApproval {
+    pub withdrawal_id: u64,
+    pub owner: Address,
+    pub spender: Option<Address>,
+    pub timestamp: u64,
+}
+
+#[odra::event]
+pub struct WithdrawalOperatorSet {
+    pub owner: Address,
+    pub operator: Address,
+    pub approved: bool,
+    pub timestamp: u64,
+}